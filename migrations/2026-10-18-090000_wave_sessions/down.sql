@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE wave_sessions;
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE wave_sessions (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    game_session_id INT UNSIGNED NOT NULL,
+    wave SMALLINT UNSIGNED NOT NULL,
+    ai_remaining INT UNSIGNED NOT NULL,
+    longest_stall INT UNSIGNED NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    trader_opened_at TIMESTAMP NULL,
+    ended_at TIMESTAMP NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (game_session_id) REFERENCES game_sessions(id)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE utf8mb4_swedish_ci;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE player_sessions DROP COLUMN team;
+
+ALTER TABLE game_sessions
+    DROP COLUMN game_mode,
+    DROP COLUMN weekly_outbreak,
+    DROP COLUMN objectives,
+    DROP COLUMN versus_round;
\ No newline at end of file
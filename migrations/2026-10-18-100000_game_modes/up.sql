@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE game_sessions
+    ADD COLUMN game_mode VARCHAR(50) NOT NULL DEFAULT 'Survival' AFTER game_type,
+    ADD COLUMN weekly_outbreak VARCHAR(50) NULL AFTER boss,
+    ADD COLUMN objectives SMALLINT UNSIGNED NULL AFTER weekly_outbreak,
+    ADD COLUMN versus_round SMALLINT UNSIGNED NULL AFTER objectives;
+
+ALTER TABLE player_sessions
+    ADD COLUMN team VARCHAR(50) NULL AFTER kills;
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE control_actions (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    action VARCHAR(32) NOT NULL,
+    target VARCHAR(255) NULL,
+    source VARCHAR(32) NOT NULL,
+    error VARCHAR(255) NULL,
+    created TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE utf8mb4_swedish_ci;
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE afk_periods (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    player_session_id INT UNSIGNED NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (player_session_id) REFERENCES player_sessions(id)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE utf8mb4_swedish_ci;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE bans (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    server VARCHAR(255) NOT NULL,
+    unique_net_id VARCHAR(50) NOT NULL,
+    steam_id BIGINT UNSIGNED NULL,
+    name VARCHAR(50) NULL,
+    created TIMESTAMP NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE (server, unique_net_id)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE utf8mb4_swedish_ci;
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE server_settings (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    server VARCHAR(255) NOT NULL,
+    name VARCHAR(50) NOT NULL,
+    value VARCHAR(255) NOT NULL,
+    updated TIMESTAMP NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE (server, name)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE utf8mb4_swedish_ci;
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP VIEW game_session_configs;
+
+DROP TABLE server_config_versions;
+
+ALTER TABLE game_sessions DROP COLUMN server;
\ No newline at end of file
@@ -0,0 +1,27 @@
+-- Your SQL goes here
+CREATE TABLE server_config_versions (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    server VARCHAR(255) NOT NULL,
+    settings TEXT NOT NULL,
+    created TIMESTAMP NOT NULL,
+    PRIMARY KEY (id),
+    INDEX (server, created)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE utf8mb4_swedish_ci;
+
+-- Games logged before this have no server
+ALTER TABLE game_sessions ADD server VARCHAR(255) NULL AFTER id;
+
+-- The configuration each game was started with, the last version of its
+-- server stored before the game started
+CREATE VIEW game_session_configs AS
+SELECT
+    game_sessions.id AS game_session_id,
+    (
+        SELECT server_config_versions.id
+        FROM server_config_versions
+        WHERE server_config_versions.server = game_sessions.server
+            AND server_config_versions.created <= game_sessions.started_at
+        ORDER BY server_config_versions.created DESC, server_config_versions.id DESC
+        LIMIT 1
+    ) AS config_version_id
+FROM game_sessions;
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE ip_bans (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT,
+    server VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45) NOT NULL,
+    created TIMESTAMP NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE (server, ip_address)
+) ENGINE = InnoDB DEFAULT CHARSET = utf8mb4 COLLATE utf8mb4_swedish_ci;
\ No newline at end of file
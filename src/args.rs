@@ -1,18 +1,36 @@
+use crate::kf2_config::{ServerConfig, ADMIN_USERNAME};
+use crate::kf2_database::export::ExportFilter;
+use crate::kf2_export::{ExportFormat, ExportTable, IpMode};
+use crate::kf2_log::logger::afk::AfkAction;
+use crate::kf2_log::logger::control::{game_type_class, ControlAction};
+use crate::kf2_scrape::models::steam_unique_net_id;
 use dotenv::dotenv;
+use log::warn;
+use std::collections::HashMap;
 use std::env;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 use url::Url;
 
 #[derive(Debug)]
 pub struct Kf2ServerArgs {
-    server_ip: Url,
-    username: String,
-    password: String,
+    pub(super) server_ip: Url,
+    pub(super) username: String,
+    pub(super) password: String,
+    /// Tells the server apart in the tables servers share, like the bans.
+    pub(super) server_id: String,
 }
 
 impl Kf2ServerArgs {
     pub fn get(self) -> (Url, String, String) {
         (self.server_ip, self.username, self.password)
     }
+
+    pub fn server_id(&self) -> &str {
+        &self.server_id
+    }
 }
 
 #[derive(Debug)]
@@ -40,6 +58,130 @@ impl Kf2DbArgs {
     }
 }
 
+/// Optional HTTP API, enabled by setting `API_ADDRESS`.
+#[derive(Debug)]
+pub struct Kf2ApiArgs {
+    pub(super) address: SocketAddr,
+}
+
+/// Optional webhooks, enabled by setting `DISCORD_WEBHOOK_URL` or
+/// `WEBHOOK_URL`. `WEBHOOK_EVENTS` limits the events sent.
+#[derive(Debug)]
+pub struct Kf2WebhookArgs {
+    pub(super) discord_url: Option<Url>,
+    pub(super) url: Option<Url>,
+    pub(super) secret: Option<String>,
+    pub(super) events: Option<Vec<String>>,
+}
+
+/// Optional JSONL event log, enabled by setting `EVENT_LOG_PATH`. Files are
+/// rotated at `EVENT_LOG_MAX_SIZE_MB`, 10 MB by default.
+#[derive(Debug)]
+pub struct Kf2EventLogArgs {
+    pub(super) path: PathBuf,
+    pub(super) max_size: u64,
+}
+
+/// Optional high ping kicking, enabled by setting `PING_KICK_THRESHOLD` in
+/// milliseconds. Players are warned after `PING_KICK_DURATION_SECS` of high
+/// ping and kicked if it stays high as long again. Nobody is kicked within
+/// `PING_KICK_GRACE_SECS` of joining, or at all if they are admins and
+/// `PING_KICK_EXEMPT_ADMINS` is not `false`.
+#[derive(Debug, Clone)]
+pub struct Kf2PingKickArgs {
+    pub(super) threshold: u32,
+    pub(super) duration: Duration,
+    pub(super) grace: Duration,
+    pub(super) exempt_admins: bool,
+}
+
+/// Optional AFK tracking, enabled by setting `AFK_WAVES`. Players whose
+/// kills, dosh and health do not change for that many waves are AFK.
+/// `AFK_ACTION` is `record`, `warn` or `kick`, and the last two are only
+/// done when the server is full.
+#[derive(Debug, Clone)]
+pub struct Kf2AfkArgs {
+    pub(super) waves: u16,
+    pub(super) action: AfkAction,
+}
+
+/// Optional ban list sync, enabled by setting `BAN_SYNC_INTERVAL_SECS`. The
+/// bans page is stored in the `bans` table and the bans listed in
+/// `BAN_SYNC_FILE` or stored in the `BAN_SYNC_DATABASE_NAME` database are
+/// added to the server. With `BAN_SYNC_IPS` the known addresses of banned
+/// players are denied too.
+#[derive(Debug)]
+pub struct Kf2BanSyncArgs {
+    pub(super) interval: Duration,
+    pub(super) file: Option<PathBuf>,
+    pub(super) database: Option<Kf2DbArgs>,
+    pub(super) ips: bool,
+}
+
+/// Optional scheduled broadcasts, enabled by setting `ANNOUNCEMENTS_FILE`.
+/// See the announce module for the file format.
+#[derive(Debug)]
+pub struct Kf2AnnounceArgs {
+    pub(super) path: PathBuf,
+}
+
+/// Optional greetings of joining players, enabled by setting any of
+/// `WELCOME_NEW_MESSAGE`, `WELCOME_BACK_MESSAGE` or
+/// `WELCOME_MILESTONE_MESSAGE`. The milestone message replaces the welcome
+/// back message on the map counts listed in `WELCOME_MILESTONES`.
+#[derive(Debug, Clone)]
+pub struct Kf2WelcomeArgs {
+    pub(super) new_message: Option<String>,
+    pub(super) back_message: Option<String>,
+    pub(super) milestone_message: Option<String>,
+    pub(super) milestones: Vec<u32>,
+}
+
+/// Optional Steam server queries, enabled by setting `QUERY_ADDRESS` to the
+/// query port of the server. They cross-check the webadmin and keep the
+/// player count going while it is unreachable.
+#[derive(Debug)]
+pub struct Kf2QueryArgs {
+    pub(super) address: SocketAddr,
+    pub(super) timeout: Duration,
+}
+
+/// Optional server output tailing, enabled by setting `SERVER_LOG_PATH` to
+/// the logs directory of `server_manager.py`. Joins and map loads in the
+/// output are published as events and trigger a tick right away.
+#[derive(Debug)]
+pub struct Kf2ServerLogArgs {
+    pub(super) path: PathBuf,
+    pub(super) poll: Duration,
+}
+
+/// Optional history of the webadmin settings, enabled by setting
+/// `CONFIG_HISTORY_INTERVAL_SECS`. A new version is stored in
+/// `server_config_versions` when the settings change.
+#[derive(Debug)]
+pub struct Kf2ConfigHistoryArgs {
+    pub(super) interval: Duration,
+}
+
+/// Everything the logger reads from the .env file.
+#[derive(Debug)]
+pub struct Kf2LogArgs {
+    pub(super) server: Kf2ServerArgs,
+    pub(super) db: Kf2DbArgs,
+    pub(super) api: Option<Kf2ApiArgs>,
+    pub(super) webhook: Option<Kf2WebhookArgs>,
+    pub(super) event_log: Option<Kf2EventLogArgs>,
+    pub(super) ping_kick: Option<Kf2PingKickArgs>,
+    pub(super) afk: Option<Kf2AfkArgs>,
+    pub(super) ban_sync: Option<Kf2BanSyncArgs>,
+    pub(super) announce: Option<Kf2AnnounceArgs>,
+    pub(super) welcome: Option<Kf2WelcomeArgs>,
+    pub(super) query: Option<Kf2QueryArgs>,
+    pub(super) server_log: Option<Kf2ServerLogArgs>,
+    pub(super) server_config: Option<ServerConfig>,
+    pub(super) config_history: Option<Kf2ConfigHistoryArgs>,
+}
+
 #[derive(Debug)]
 struct Args {
     web_admin_url: Url,
@@ -51,41 +193,534 @@ struct Args {
     database_password: String,
 }
 
+fn get_env(s: &str) -> String {
+    fn fmt_env_msg(s: &str) -> String {
+        format!("Could not read {s} environment variable!")
+    }
+
+    env::var(s).unwrap_or_else(|_| panic!("{}", fmt_env_msg(s)))
+}
+
+fn get_optional_env(s: &str) -> Option<String> {
+    env::var(s).ok().filter(|value| !value.is_empty())
+}
+
+fn get_parsed_env<T: FromStr>(s: &str, default: T) -> T {
+    get_optional_env(s)
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("Could not parse {s}: {value}"))
+        })
+        .unwrap_or(default)
+}
+
+fn get_optional_url(s: &str) -> Option<Url> {
+    get_optional_env(s).map(|url_str| {
+        Url::parse(&url_str).unwrap_or_else(|_| panic!("Could not parse {s}: {url_str}"))
+    })
+}
+
+fn db_args() -> Kf2DbArgs {
+    Kf2DbArgs {
+        server_address: get_env("DATABASE_URL"),
+        database: get_env("DATABASE_NAME"),
+        username: get_env("DATABASE_USERNAME"),
+        password: get_env("DATABASE_PASSWORD"),
+    }
+}
+
+/// Ini files of the server in `SERVER_PATH`, if they can be read.
+fn server_config() -> Option<ServerConfig> {
+    let path = get_optional_env("SERVER_PATH")?;
+    ServerConfig::read(Path::new(&path))
+        .inspect_err(|e| warn!("Server settings not read: {e}"))
+        .ok()
+}
+
+/// The webadmin connection. Settings missing from the .env file are read
+/// from the ini files of the server. The server is known by `SERVER_ID`,
+/// the server name in the ini files or else the webadmin url.
+fn server_args(config: Option<&ServerConfig>) -> Kf2ServerArgs {
+    let web_admin_enabled = config.is_none_or(|config| config.web_admin_enabled());
+    let server_ip = match config {
+        Some(config) if get_optional_env("WEB_ADMIN_URL").is_none() => {
+            if !web_admin_enabled {
+                panic!("The webadmin is disabled in KFWeb.ini");
+            }
+            config.web_admin_url()
+        }
+        _ => {
+            if !web_admin_enabled {
+                warn!("The webadmin is disabled in KFWeb.ini");
+            }
+            let url_str = get_env("WEB_ADMIN_URL");
+            Url::parse(&url_str).unwrap_or_else(|_| panic!("Could not parse url: {url_str}"))
+        }
+    };
+    let server_id = get_optional_env("SERVER_ID")
+        .or_else(|| config.and_then(|config| config.server_name().map(String::from)))
+        .unwrap_or_else(|| server_ip.to_string());
+    Kf2ServerArgs {
+        server_ip,
+        server_id,
+        username: get_optional_env("WEB_ADMIN_USERNAME")
+            .or_else(|| config.map(|_| String::from(ADMIN_USERNAME)))
+            .unwrap_or_else(|| get_env("WEB_ADMIN_USERNAME")),
+        password: get_optional_env("WEB_ADMIN_PASSWORD")
+            .or_else(|| config.and_then(|config| config.admin_password().map(String::from)))
+            .unwrap_or_else(|| get_env("WEB_ADMIN_PASSWORD")),
+    }
+}
+
+/// Read only the database arguments from .env file, for the commands that do
+/// not talk to the webadmin.
+pub fn parse_db() -> Kf2DbArgs {
+    dotenv().ok();
+    db_args()
+}
+
+/// Read the webadmin and database arguments from .env file, for the commands
+/// that control the server.
+pub fn parse_server() -> (Kf2ServerArgs, Kf2DbArgs) {
+    dotenv().ok();
+    (server_args(server_config().as_ref()), db_args())
+}
+
 /// Read arguments from .env file
-pub fn parse() -> (Kf2ServerArgs, Kf2DbArgs) {
+pub fn parse() -> Kf2LogArgs {
     dotenv().ok();
 
-    fn get_env(s: &str) -> String {
-        fn fmt_env_msg(s: &str) -> String {
-            format!("Could not read {s} environment variable!")
-        }
+    let api_address = "API_ADDRESS";
+    let discord_webhook_url = "DISCORD_WEBHOOK_URL";
+    let webhook_url = "WEBHOOK_URL";
+    let webhook_secret = "WEBHOOK_SECRET";
+    let webhook_events = "WEBHOOK_EVENTS";
+    let event_log_path = "EVENT_LOG_PATH";
+    let event_log_max_size = "EVENT_LOG_MAX_SIZE_MB";
+    let ping_kick_threshold = "PING_KICK_THRESHOLD";
+    let afk_waves = "AFK_WAVES";
+    let ban_sync_interval = "BAN_SYNC_INTERVAL_SECS";
+    let query_address = "QUERY_ADDRESS";
+    let config_history_interval = "CONFIG_HISTORY_INTERVAL_SECS";
 
-        env::var(s).unwrap_or_else(|_| panic!("{}", fmt_env_msg(s)))
+    let db_args = db_args();
+    let server_config = server_config();
+    let server_args = server_args(server_config.as_ref());
+    let api_args = get_optional_env(api_address).map(|address| Kf2ApiArgs {
+        address: address
+            .parse()
+            .unwrap_or_else(|_| panic!("Could not parse {api_address}: {address}")),
+    });
+    let webhook_args = Kf2WebhookArgs {
+        discord_url: get_optional_url(discord_webhook_url),
+        url: get_optional_url(webhook_url),
+        secret: get_optional_env(webhook_secret),
+        events: get_optional_env(webhook_events).map(|events| {
+            events
+                .split(',')
+                .map(|event| event.trim().to_string())
+                .filter(|event| !event.is_empty())
+                .collect()
+        }),
+    };
+    let webhook_args = if webhook_args.discord_url.is_some() || webhook_args.url.is_some() {
+        Some(webhook_args)
+    } else {
+        None
+    };
+    let event_log_args = get_optional_env(event_log_path).map(|path| Kf2EventLogArgs {
+        path: PathBuf::from(path),
+        max_size: get_parsed_env(event_log_max_size, 10u64) * 1024 * 1024,
+    });
+    let ping_kick_args = get_optional_env(ping_kick_threshold).map(|threshold| Kf2PingKickArgs {
+        threshold: threshold
+            .parse()
+            .unwrap_or_else(|_| panic!("Could not parse {ping_kick_threshold}: {threshold}")),
+        duration: Duration::from_secs(get_parsed_env("PING_KICK_DURATION_SECS", 60)),
+        grace: Duration::from_secs(get_parsed_env("PING_KICK_GRACE_SECS", 60)),
+        exempt_admins: get_parsed_env("PING_KICK_EXEMPT_ADMINS", true),
+    });
+    let afk_args = get_optional_env(afk_waves).map(|waves| Kf2AfkArgs {
+        waves: waves
+            .parse()
+            .unwrap_or_else(|_| panic!("Could not parse {afk_waves}: {waves}")),
+        action: get_parsed_env("AFK_ACTION", AfkAction::default()),
+    });
+    let ban_sync_args = get_optional_env(ban_sync_interval).map(|interval| Kf2BanSyncArgs {
+        interval: Duration::from_secs(
+            interval
+                .parse()
+                .unwrap_or_else(|_| panic!("Could not parse {ban_sync_interval}: {interval}")),
+        ),
+        file: get_optional_env("BAN_SYNC_FILE").map(PathBuf::from),
+        database: get_optional_env("BAN_SYNC_DATABASE_NAME").map(|database| Kf2DbArgs {
+            database,
+            ..self::db_args()
+        }),
+        ips: get_parsed_env("BAN_SYNC_IPS", false),
+    });
+    let welcome_milestones = "WELCOME_MILESTONES";
+    let welcome_args = Kf2WelcomeArgs {
+        new_message: get_optional_env("WELCOME_NEW_MESSAGE"),
+        back_message: get_optional_env("WELCOME_BACK_MESSAGE"),
+        milestone_message: get_optional_env("WELCOME_MILESTONE_MESSAGE"),
+        milestones: get_optional_env(welcome_milestones)
+            .map(|milestones| {
+                milestones
+                    .split(',')
+                    .map(|milestone| {
+                        milestone.trim().parse().unwrap_or_else(|_| {
+                            panic!("Could not parse {welcome_milestones}: {milestones}")
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_else(|| vec![10, 25, 50, 100, 250, 500, 1000]),
+    };
+    let welcome_args = if welcome_args.new_message.is_some()
+        || welcome_args.back_message.is_some()
+        || welcome_args.milestone_message.is_some()
+    {
+        Some(welcome_args)
+    } else {
+        None
+    };
+    Kf2LogArgs {
+        server: server_args,
+        db: db_args,
+        api: api_args,
+        webhook: webhook_args,
+        event_log: event_log_args,
+        ping_kick: ping_kick_args,
+        afk: afk_args,
+        ban_sync: ban_sync_args,
+        announce: get_optional_env("ANNOUNCEMENTS_FILE").map(|path| Kf2AnnounceArgs {
+            path: PathBuf::from(path),
+        }),
+        welcome: welcome_args,
+        query: get_optional_env(query_address).map(|address| Kf2QueryArgs {
+            address: address
+                .parse()
+                .unwrap_or_else(|_| panic!("Could not parse {query_address}: {address}")),
+            timeout: Duration::from_secs(get_parsed_env("QUERY_TIMEOUT_SECS", 2)),
+        }),
+        server_log: get_optional_env("SERVER_LOG_PATH").map(|path| Kf2ServerLogArgs {
+            path: PathBuf::from(path),
+            poll: Duration::from_millis(get_parsed_env("SERVER_LOG_POLL_MILLIS", 500)),
+        }),
+        server_config,
+        config_history: get_optional_env(config_history_interval).map(|interval| {
+            Kf2ConfigHistoryArgs {
+                interval: Duration::from_secs(interval.parse().unwrap_or_else(|_| {
+                    panic!("Could not parse {config_history_interval}: {interval}")
+                })),
+            }
+        }),
     }
+}
+
+pub const USAGE: &str = "Usage:
+    kf2_server_logger
+        Log the server, configured by the .env file
+    kf2_server_logger export [options]
+        --tables <list>      unique_players,game_sessions,player_sessions,ip_addresses
+        --format <csv|json>  csv by default
+        --from <YYYY-MM-DD>  --to <YYYY-MM-DD>
+        --map <name>  --difficulty <name>  --game-mode <name>
+        --perk <name>  --player <steam id>
+        --ips <omit|hash|plain>  omit by default
+        --ip-key <key>       keeps IP hashes the same between exports
+        --output <dir>       export by default
+    kf2_server_logger generate-site [--output <dir>]
+        Render the static stats site, to site by default
+    kf2_server_logger control <action>
+        change-map --map <name> [--game-type <type>] [--difficulty <name>]
+        restart-map
+        broadcast --message <text>
+        kick --player <steam id>
+        ban --player <steam id>
+        ban-id --id <unique id or steam id>
+        ban-ip --ip <address>
+    kf2_server_logger query [--address <host:port>]
+        Print the Steam query answers of a server, 127.0.0.1:27015 by default";
+
+/// Export of the history tables to files.
+#[derive(Debug)]
+pub struct Kf2ExportArgs {
+    pub(super) tables: Vec<ExportTable>,
+    pub(super) format: ExportFormat,
+    pub(super) ips: IpMode,
+    pub(super) ip_key: Option<String>,
+    pub(super) output: PathBuf,
+    pub(super) filter: ExportFilter,
+}
+
+/// Static stats site rendered from the database.
+#[derive(Debug)]
+pub struct Kf2SiteArgs {
+    pub(super) output: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Log,
+    Export(Kf2ExportArgs),
+    GenerateSite(Kf2SiteArgs),
+    Control(ControlAction),
+    Query(Kf2QueryArgs),
+}
 
-    let web_admin_url = "WEB_ADMIN_URL";
-    let web_admin_username = "WEB_ADMIN_USERNAME";
-    let web_admin_password = "WEB_ADMIN_PASSWORD";
-    let database_url = "DATABASE_URL";
-    let database_name = "DATABASE_NAME";
-    let database_username = "DATABASE_USERNAME";
-    let database_password = "DATABASE_PASSWORD";
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {flag}: {value}"))
+}
 
-    let db_args = Kf2DbArgs {
-        server_address: get_env(database_url),
-        database: get_env(database_name),
-        username: get_env(database_username),
-        password: get_env(database_password),
+fn parse_export(args: &mut impl Iterator<Item = String>) -> Result<Kf2ExportArgs, String> {
+    let mut export = Kf2ExportArgs {
+        tables: ExportTable::ALL.to_vec(),
+        format: ExportFormat::default(),
+        ips: IpMode::default(),
+        ip_key: None,
+        output: PathBuf::from("export"),
+        filter: ExportFilter::default(),
     };
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {flag}"))?;
+        let filter = &mut export.filter;
+        match flag.as_str() {
+            "--tables" => {
+                export.tables = value
+                    .split(',')
+                    .map(|table| table.trim().parse())
+                    .collect::<Result<_, _>>()?
+            }
+            "--format" => export.format = value.parse()?,
+            "--ips" => export.ips = value.parse()?,
+            "--ip-key" => export.ip_key = Some(value),
+            "--output" => export.output = PathBuf::from(value),
+            "--from" => filter.query.from = Some(parse_value(&flag, &value)?),
+            "--to" => filter.query.to = Some(parse_value(&flag, &value)?),
+            "--map" => filter.query.map = Some(value),
+            "--difficulty" => filter.query.difficulty = Some(value),
+            "--game-mode" => filter.query.game_mode = Some(value),
+            "--perk" => filter.query.perk = Some(value),
+            "--player" => filter.player = Some(parse_value(&flag, &value)?),
+            _ => return Err(format!("Unknown option {flag}")),
+        }
+    }
+    Ok(export)
+}
 
-    let url_str = get_env(web_admin_url);
-    let web_admin_url =
-        Url::parse(&url_str).unwrap_or_else(|_| panic!("Could not parse url: {url_str}"));
+fn parse_site(args: &mut impl Iterator<Item = String>) -> Result<Kf2SiteArgs, String> {
+    let mut site = Kf2SiteArgs {
+        output: PathBuf::from("site"),
+    };
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {flag}"))?;
+        match flag.as_str() {
+            "--output" => site.output = PathBuf::from(value),
+            _ => return Err(format!("Unknown option {flag}")),
+        }
+    }
+    Ok(site)
+}
+
+fn parse_control(args: &mut impl Iterator<Item = String>) -> Result<ControlAction, String> {
+    let action = args.next().ok_or("Missing control action")?;
+    let mut options = HashMap::new();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {flag}"))?;
+        options.insert(flag, value);
+    }
+    let mut option = |flag: &str| options.remove(flag);
+    let player = |steam_id: Option<String>| -> Result<u64, String> {
+        let steam_id = steam_id.ok_or("Missing --player")?;
+        parse_value("--player", &steam_id)
+    };
+    let control = match action.as_str() {
+        "change-map" => ControlAction::ChangeMap {
+            map: option("--map").ok_or("Missing --map")?,
+            game_type: option("--game-type")
+                .map(|game_type| game_type_class(&game_type))
+                .transpose()?,
+            difficulty: option("--difficulty")
+                .map(|difficulty| difficulty.parse())
+                .transpose()?,
+        },
+        "restart-map" => ControlAction::RestartMap,
+        "broadcast" => ControlAction::Broadcast {
+            message: option("--message").ok_or("Missing --message")?,
+        },
+        "kick" => ControlAction::Kick {
+            steam_id: player(option("--player"))?,
+        },
+        "ban" => ControlAction::Ban {
+            steam_id: player(option("--player"))?,
+        },
+        "ban-id" => {
+            let id = option("--id").ok_or("Missing --id")?;
+            ControlAction::BanId {
+                unique_net_id: match id.parse() {
+                    Ok(steam_id) => steam_unique_net_id(steam_id),
+                    Err(_) => id,
+                },
+            }
+        }
+        "ban-ip" => ControlAction::BanIp {
+            ip: parse_value("--ip", &option("--ip").ok_or("Missing --ip")?)?,
+        },
+        _ => return Err(format!("Unknown control action {action}")),
+    };
+    match options.keys().next() {
+        Some(flag) => Err(format!("Unknown option {flag}")),
+        None => Ok(control),
+    }
+}
 
-    let server_args = Kf2ServerArgs {
-        server_ip: web_admin_url,
-        username: get_env(web_admin_username),
-        password: get_env(web_admin_password),
+fn parse_query(args: &mut impl Iterator<Item = String>) -> Result<Kf2QueryArgs, String> {
+    let mut query = Kf2QueryArgs {
+        address: SocketAddr::from(([127, 0, 0, 1], 27015)),
+        timeout: Duration::from_secs(2),
     };
-    (server_args, db_args)
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {flag}"))?;
+        match flag.as_str() {
+            "--address" => query.address = parse_value(&flag, &value)?,
+            _ => return Err(format!("Unknown option {flag}")),
+        }
+    }
+    Ok(query)
+}
+
+fn parse_command(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    match args.next().as_deref() {
+        None => Ok(Command::Log),
+        Some("export") => Ok(Command::Export(parse_export(&mut args)?)),
+        Some("generate-site") => Ok(Command::GenerateSite(parse_site(&mut args)?)),
+        Some("control") => Ok(Command::Control(parse_control(&mut args)?)),
+        Some("query") => Ok(Command::Query(parse_query(&mut args)?)),
+        Some(command) => Err(format!("Unknown command {command}")),
+    }
+}
+
+/// Read the command from the command line.
+pub fn command() -> Result<Command, String> {
+    parse_command(env::args().skip(1))
+}
+
+#[cfg(test)]
+mod tests_args {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, String> {
+        parse_command(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_no_command_logs() {
+        assert!(matches!(parse(""), Ok(Command::Log)));
+    }
+
+    #[test]
+    fn test_export_options() {
+        let Ok(Command::Export(export)) = parse(
+            "export --tables game_sessions,ip_addresses --format json --ips hash \
+             --from 2023-10-01 --map KF-Outpost --game-mode Endless --player 76561198039614177",
+        ) else {
+            panic!("Export not parsed");
+        };
+        assert_eq!(
+            export.tables,
+            vec![ExportTable::GameSessions, ExportTable::IpAddresses]
+        );
+        assert_eq!(export.format, ExportFormat::Json);
+        assert_eq!(export.ips, IpMode::Hash);
+        assert_eq!(
+            export.filter.query.from,
+            chrono::NaiveDate::from_ymd_opt(2023, 10, 1)
+        );
+        assert_eq!(export.filter.query.map.as_deref(), Some("KF-Outpost"));
+        assert_eq!(export.filter.query.game_mode.as_deref(), Some("Endless"));
+        assert_eq!(export.filter.player, Some(76561198039614177));
+    }
+
+    #[test]
+    fn test_export_defaults_hide_ips() {
+        let Ok(Command::Export(export)) = parse("export") else {
+            panic!("Export not parsed");
+        };
+        assert_eq!(export.tables.len(), 4);
+        assert_eq!(export.ips, IpMode::Omit);
+    }
+
+    #[test]
+    fn test_generate_site() {
+        let Ok(Command::GenerateSite(site)) = parse("generate-site --output /var/www/kf2") else {
+            panic!("Site not parsed");
+        };
+        assert_eq!(site.output, PathBuf::from("/var/www/kf2"));
+    }
+
+    #[test]
+    fn test_control() {
+        let Ok(Command::Control(action)) =
+            parse("control change-map --map KF-Outpost --game-type endless --difficulty hoe")
+        else {
+            panic!("Control not parsed");
+        };
+        assert_eq!(
+            action,
+            ControlAction::ChangeMap {
+                map: String::from("KF-Outpost"),
+                game_type: Some(String::from("KFGameContent.KFGameInfo_Endless")),
+                difficulty: Some(crate::kf2_scrape::models::KfDifficulty::HellOnEarth),
+            }
+        );
+        assert!(matches!(
+            parse("control kick --player 76561198039614177"),
+            Ok(Command::Control(ControlAction::Kick {
+                steam_id: 76561198039614177
+            }))
+        ));
+        assert!(matches!(
+            parse("control ban-id --id 76561198039614177"),
+            Ok(Command::Control(ControlAction::BanId { unique_net_id }))
+                if unique_net_id == "0x0110000104BAC2E1"
+        ));
+        assert!(parse("control ban-ip --ip 10.0.0.300").is_err());
+        assert!(parse("control kick").is_err());
+        assert!(parse("control ban --player koira").is_err());
+        assert!(parse("control restart-map --map KF-Outpost").is_err());
+        assert!(parse("control change-map --map KF-Outpost --difficulty easy").is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let Ok(Command::Query(query)) = parse("query --address 10.0.0.2:27016") else {
+            panic!("Query not parsed");
+        };
+        assert_eq!(query.address, SocketAddr::from(([10, 0, 0, 2], 27016)));
+        let Ok(Command::Query(query)) = parse("query") else {
+            panic!("Query not parsed");
+        };
+        assert_eq!(query.address.port(), 27015);
+        assert!(parse("query --address localhost").is_err());
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(parse("import").is_err());
+        assert!(parse("export --from yesterday").is_err());
+        assert!(parse("export --format").is_err());
+    }
 }
@@ -0,0 +1,3 @@
+pub(super) mod feed;
+pub(super) mod live;
+pub(super) mod server;
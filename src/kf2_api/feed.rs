@@ -0,0 +1,133 @@
+use super::live::{LiveSnapshot, LiveState};
+use crate::kf2_event::{EventBus, EventKind, TrackerEvent};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use log::{debug, warn};
+use tokio::sync::broadcast::error::RecvError;
+
+#[derive(Clone)]
+struct FeedState {
+    live: LiveState,
+    events: EventBus,
+}
+
+impl From<&TrackerEvent> for LiveSnapshot {
+    fn from(event: &TrackerEvent) -> Self {
+        Self {
+            game_session: event.game_session.clone(),
+            players: event.players.clone(),
+            updated_at: Some(event.timestamp),
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, snapshot: &LiveSnapshot) -> bool {
+    match serde_json::to_string(snapshot) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
+        Err(e) => {
+            warn!("Could not serialize live snapshot: {}", e);
+            true
+        }
+    }
+}
+
+/// Sends the latest snapshot on connect and then one after every tick of
+/// the logger, until the client goes away.
+async fn feed(mut socket: WebSocket, state: FeedState) {
+    let mut receiver = state.events.subscribe();
+    if !send(&mut socket, &state.live.snapshot()).await {
+        return;
+    }
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Overlays only listen
+                Some(Ok(_)) => (),
+            },
+            event = receiver.recv() => match event {
+                Ok(event) if event.kind == EventKind::Snapshot => {
+                    if !send(&mut socket, &LiveSnapshot::from(event.as_ref())).await {
+                        break;
+                    }
+                }
+                Ok(_) => (),
+                // Every snapshot is complete, so skipped ones do not matter
+                Err(RecvError::Lagged(missed)) => debug!("Live feed skipped {} events", missed),
+                Err(RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+async fn live_feed(ws: WebSocketUpgrade, State(state): State<FeedState>) -> Response {
+    ws.on_upgrade(move |socket| feed(socket, state))
+}
+
+pub(super) fn feed_routes(live: LiveState, events: EventBus) -> Router {
+    Router::new()
+        .route("/ws/live", get(live_feed))
+        .with_state(FeedState { live, events })
+}
+
+#[cfg(test)]
+mod tests_feed {
+    use super::*;
+    use crate::kf2_api::live::LivePlayer;
+    use futures_util::StreamExt;
+    use tokio_tungstenite::tungstenite;
+
+    async fn next_json(
+        socket: &mut tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+    ) -> serde_json::Value {
+        match socket.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+
+    fn event(kind: EventKind, kills: u32) -> TrackerEvent {
+        TrackerEvent {
+            kind,
+            timestamp: chrono::Utc::now().naive_utc(),
+            game_session: None,
+            players: vec![LivePlayer {
+                name: String::from("koira"),
+                perk: String::from("Berserker"),
+                dosh: 250,
+                health: 75,
+                kills,
+                ping: 40,
+                admin: false,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_live_feed() {
+        let events = EventBus::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = feed_routes(LiveState::default(), events.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/live", address))
+            .await
+            .unwrap();
+        let initial = next_json(&mut socket).await;
+        assert!(initial["updated_at"].is_null());
+
+        // Only snapshots are pushed
+        events.publish(event(EventKind::ServerFull, 1));
+        events.publish(event(EventKind::Snapshot, 12));
+        let snapshot = next_json(&mut socket).await;
+        assert_eq!(snapshot["players"][0]["kills"], 12);
+        assert_eq!(snapshot["players"][0]["health"], 75);
+        assert!(snapshot["updated_at"].is_string());
+    }
+}
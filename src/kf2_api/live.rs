@@ -0,0 +1,137 @@
+use crate::kf2_event::{EventKind, EventSink, TrackerEvent};
+use crate::kf2_log::logger::GameSession;
+use crate::kf2_scrape::models::PlayerInGame;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+
+/// Events kept for `/api/events`.
+const RECENT_EVENTS: usize = 100;
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct LiveGame {
+    pub(crate) id: Option<u32>,
+    pub(crate) map_name: String,
+    pub(crate) difficulty: String,
+    pub(crate) game_type: String,
+    pub(crate) game_mode: String,
+    pub(crate) boss: String,
+    pub(crate) current_wave: u16,
+    pub(crate) max_waves: u16,
+    pub(crate) reached_wave: u16,
+    pub(crate) max_players: u16,
+    pub(crate) players_at_most: u16,
+    pub(crate) weekly_outbreak: Option<String>,
+    pub(crate) objective: Option<String>,
+    pub(crate) versus_round: u16,
+    pub(crate) started_at: chrono::NaiveDateTime,
+}
+
+impl From<&GameSession> for LiveGame {
+    fn from(game_session: &GameSession) -> Self {
+        Self {
+            id: game_session.db_id,
+            map_name: game_session.map_name.clone(),
+            difficulty: game_session.difficulty.to_string(),
+            game_type: game_session.game_type.clone(),
+            game_mode: game_session.game_mode.to_string(),
+            boss: game_session.boss.to_string(),
+            current_wave: game_session.current_wave,
+            max_waves: game_session.max_waves,
+            reached_wave: game_session.reached_wave,
+            max_players: game_session.max_players,
+            players_at_most: game_session.players_at_most,
+            weekly_outbreak: game_session.weekly_outbreak.clone(),
+            objective: game_session.objective.clone(),
+            versus_round: game_session.versus_round,
+            started_at: game_session.started_at,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct LivePlayer {
+    pub(crate) name: String,
+    pub(crate) perk: String,
+    pub(crate) dosh: u32,
+    pub(crate) health: u32,
+    pub(crate) kills: u32,
+    pub(crate) ping: u32,
+    pub(crate) admin: bool,
+}
+
+impl From<&PlayerInGame> for LivePlayer {
+    fn from(player: &PlayerInGame) -> Self {
+        Self {
+            name: player.name.clone(),
+            perk: player.perk.to_string(),
+            dosh: player.dosh,
+            health: player.health,
+            kills: player.kills,
+            ping: player.ping,
+            admin: player.admin,
+        }
+    }
+}
+
+/// What the logger saw on the latest tick.
+#[derive(Serialize, Clone, Debug, Default)]
+pub(crate) struct LiveSnapshot {
+    pub(crate) game_session: Option<LiveGame>,
+    pub(crate) players: Vec<LivePlayer>,
+    pub(crate) updated_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Live snapshot shared between the logger loop and the API. As an event
+/// sink it keeps the latest events of the tracker.
+#[derive(Clone, Default)]
+pub(crate) struct LiveState {
+    snapshot: Arc<RwLock<LiveSnapshot>>,
+    events: Arc<RwLock<VecDeque<TrackerEvent>>>,
+}
+
+impl LiveState {
+    pub(crate) fn update(&self, game_session: Option<&GameSession>, players: &[PlayerInGame]) {
+        let snapshot = LiveSnapshot {
+            game_session: game_session.map(LiveGame::from),
+            players: players.iter().map(LivePlayer::from).collect(),
+            updated_at: Some(chrono::Utc::now().naive_utc()),
+        };
+        match self.snapshot.write() {
+            Ok(mut live) => *live = snapshot,
+            Err(e) => log::error!("Live snapshot lock poisoned: {}", e),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> LiveSnapshot {
+        match self.snapshot.read() {
+            Ok(live) => live.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    /// Latest events, oldest first.
+    pub(crate) fn recent_events(&self) -> Vec<TrackerEvent> {
+        match self.events.read() {
+            Ok(events) => events.iter().cloned().collect(),
+            Err(e) => e.into_inner().iter().cloned().collect(),
+        }
+    }
+}
+
+impl EventSink for LiveState {
+    fn handle(&mut self, event: &TrackerEvent) {
+        if event.kind == EventKind::Snapshot {
+            return;
+        }
+        match self.events.write() {
+            Ok(mut events) => {
+                if events.len() == RECENT_EVENTS {
+                    events.pop_front();
+                }
+                events.push_back(event.clone());
+            }
+            Err(e) => log::error!("Live events lock poisoned: {}", e),
+        }
+    }
+}
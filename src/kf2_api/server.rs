@@ -0,0 +1,285 @@
+use super::feed::feed_routes;
+use super::live::LiveState;
+use crate::args::Kf2ApiArgs;
+use crate::kf2_database::management::KfDbManager;
+use crate::kf2_database::queries::{LeaderboardQuery, Pagination, QueryFilter};
+use crate::kf2_event::EventBus;
+use crate::kf2_metrics;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use log::{error, info};
+use serde::Serialize;
+use std::error::Error;
+
+pub(crate) enum ApiError {
+    NotFound,
+    Internal(String),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            ApiError::NotFound => (StatusCode::NOT_FOUND, String::from("Not found")),
+            ApiError::Internal(e) => {
+                error!("API query failed: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    String::from("Internal server error"),
+                )
+            }
+        };
+        (status, Json(ErrorBody { error })).into_response()
+    }
+}
+
+/// Runs a blocking database query on the blocking thread pool.
+async fn run_query<T, F>(db: KfDbManager, query: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&KfDbManager) -> Result<T, Box<dyn Error>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || query(&db).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(ApiError::Internal)
+}
+
+async fn live(State(live): State<LiveState>) -> impl IntoResponse {
+    Json(live.snapshot())
+}
+
+async fn live_players(State(live): State<LiveState>) -> impl IntoResponse {
+    Json(live.snapshot().players)
+}
+
+async fn live_game(State(live): State<LiveState>) -> Result<impl IntoResponse, ApiError> {
+    let game_session = live.snapshot().game_session.ok_or(ApiError::NotFound)?;
+    Ok(Json(game_session))
+}
+
+async fn events(State(live): State<LiveState>) -> impl IntoResponse {
+    Json(live.recent_events())
+}
+
+async fn metrics() -> Result<impl IntoResponse, ApiError> {
+    let metrics = kf2_metrics::render().map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics,
+    ))
+}
+
+async fn players(
+    State(db): State<KfDbManager>,
+    Query(filter): Query<QueryFilter>,
+    Query(pagination): Query<Pagination>,
+) -> Result<impl IntoResponse, ApiError> {
+    let page = run_query(db, move |db| db.query_players(&filter, &pagination)).await?;
+    Ok(Json(page))
+}
+
+async fn player(
+    State(db): State<KfDbManager>,
+    Path(steam_id): Path<u64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let player = run_query(db, move |db| db.query_player(steam_id)).await?;
+    Ok(Json(player.ok_or(ApiError::NotFound)?))
+}
+
+async fn player_sessions_of_player(
+    State(db): State<KfDbManager>,
+    Path(steam_id): Path<u64>,
+    Query(filter): Query<QueryFilter>,
+    Query(pagination): Query<Pagination>,
+) -> Result<impl IntoResponse, ApiError> {
+    let page = run_query(db, move |db| {
+        db.query_player_sessions(Some(steam_id), &filter, &pagination)
+    })
+    .await?;
+    Ok(Json(page))
+}
+
+async fn player_sessions(
+    State(db): State<KfDbManager>,
+    Query(filter): Query<QueryFilter>,
+    Query(pagination): Query<Pagination>,
+) -> Result<impl IntoResponse, ApiError> {
+    let page = run_query(db, move |db| {
+        db.query_player_sessions(None, &filter, &pagination)
+    })
+    .await?;
+    Ok(Json(page))
+}
+
+async fn game_sessions(
+    State(db): State<KfDbManager>,
+    Query(filter): Query<QueryFilter>,
+    Query(pagination): Query<Pagination>,
+) -> Result<impl IntoResponse, ApiError> {
+    let page = run_query(db, move |db| db.query_game_sessions(&filter, &pagination)).await?;
+    Ok(Json(page))
+}
+
+async fn game_session(
+    State(db): State<KfDbManager>,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let game_session = run_query(db, move |db| db.query_game_session(id)).await?;
+    Ok(Json(game_session.ok_or(ApiError::NotFound)?))
+}
+
+async fn leaderboard(
+    State(db): State<KfDbManager>,
+    Query(leaderboard): Query<LeaderboardQuery>,
+    Query(filter): Query<QueryFilter>,
+    Query(pagination): Query<Pagination>,
+) -> Result<impl IntoResponse, ApiError> {
+    let page = run_query(db, move |db| {
+        db.query_leaderboard(leaderboard.by, &filter, &pagination)
+    })
+    .await?;
+    Ok(Json(page))
+}
+
+fn live_routes(live: LiveState) -> Router {
+    Router::new()
+        .route("/api/live", get(self::live))
+        .route("/api/live/players", get(live_players))
+        .route("/api/live/game", get(live_game))
+        .route("/api/events", get(events))
+        .with_state(live)
+}
+
+fn history_routes(db: KfDbManager) -> Router {
+    Router::new()
+        .route("/api/players", get(players))
+        .route("/api/players/:steam_id", get(player))
+        .route(
+            "/api/players/:steam_id/sessions",
+            get(player_sessions_of_player),
+        )
+        .route("/api/player-sessions", get(player_sessions))
+        .route("/api/game-sessions", get(game_sessions))
+        .route("/api/game-sessions/:id", get(game_session))
+        .route("/api/leaderboard", get(leaderboard))
+        .with_state(db)
+}
+
+pub(crate) async fn serve(
+    args: Kf2ApiArgs,
+    db: KfDbManager,
+    live: LiveState,
+    events: EventBus,
+) -> Result<(), Box<dyn Error>> {
+    let listener = tokio::net::TcpListener::bind(args.address).await?;
+    info!("API listening on {}", listener.local_addr()?);
+    let router = live_routes(live.clone())
+        .merge(feed_routes(live, events))
+        .merge(history_routes(db))
+        .route("/metrics", get(metrics));
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_server {
+    use super::*;
+    use crate::kf2_event::{EventKind, EventSink, TrackerEvent};
+    use crate::kf2_scrape::models::{Perk, PlayerInGame};
+
+    async fn start(live: LiveState) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, live_routes(live)).await });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn test_live_players() {
+        let live = LiveState::default();
+        let player = PlayerInGame {
+            name: String::from("Tom & Jerry"),
+            perk: Perk::FieldMedic,
+            dosh: 300,
+            health: 100,
+            kills: 12,
+            ping: 40,
+            admin: false,
+            player_key: None,
+        };
+        live.update(None, &[player]);
+        let url = start(live).await;
+
+        let players: serde_json::Value = reqwest::get(format!("{}/api/live/players", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(players[0]["name"], "Tom & Jerry");
+        assert_eq!(players[0]["kills"], 12);
+
+        let snapshot: serde_json::Value = reqwest::get(format!("{}/api/live", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(snapshot["game_session"].is_null());
+        assert!(snapshot["updated_at"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = Router::new().route("/metrics", get(metrics));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        kf2_metrics::set_current_players(3);
+        let response = reqwest::get(format!("http://{}/metrics", address))
+            .await
+            .unwrap();
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        let text = response.text().await.unwrap();
+        assert!(text.contains("kf2_current_players"));
+    }
+
+    #[tokio::test]
+    async fn test_live_game_not_found() {
+        let url = start(LiveState::default()).await;
+        let response = reqwest::get(format!("{}/api/live/game", url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let mut live = LiveState::default();
+        live.handle(&TrackerEvent {
+            kind: EventKind::ServerFull,
+            timestamp: chrono::Utc::now().naive_utc(),
+            game_session: None,
+            players: Vec::new(),
+        });
+        let url = start(live).await;
+        let events: serde_json::Value = reqwest::get(format!("{}/api/events", url))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(events[0]["event"], "server_full");
+    }
+}
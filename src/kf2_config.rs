@@ -0,0 +1,175 @@
+mod ini;
+
+use crate::kf2_scrape::models::KfDifficulty;
+use ini::Ini;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use url::Url;
+
+/// Where the server keeps its ini files under `SERVER_PATH`.
+const CONFIG_DIRECTORY: &str = "KFGame/Config";
+
+/// Username of the admin account the server creates for `AdminPassword`.
+pub(crate) const ADMIN_USERNAME: &str = "Admin";
+
+/// Settings read from the ini files of the server. Defaults are the ones
+/// the server uses when a key is missing.
+#[derive(Debug)]
+pub(crate) struct ServerConfig {
+    game: Ini,
+    web: Ini,
+    engine: Ini,
+}
+
+impl ServerConfig {
+    /// Reads `PCServer-KFGame.ini`, `KFWeb.ini` and `KFEngine.ini`.
+    pub(crate) fn read(server_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let read = |name: &str| -> Result<Ini, Box<dyn Error>> {
+            let path = server_path.join(CONFIG_DIRECTORY).join(name);
+            let text =
+                fs::read(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+            Ok(Ini::parse(&String::from_utf8_lossy(&text)))
+        };
+        Ok(Self {
+            game: read("PCServer-KFGame.ini")?,
+            web: read("KFWeb.ini")?,
+            engine: read("KFEngine.ini")?,
+        })
+    }
+
+    pub(crate) fn web_admin_enabled(&self) -> bool {
+        self.web
+            .get("IpDrv.WebServer", "bEnabled")
+            .is_some_and(|enabled| enabled.eq_ignore_ascii_case("true"))
+    }
+
+    fn web_admin_port(&self) -> u16 {
+        self.web
+            .get("IpDrv.WebServer", "ListenPort")
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(8080)
+    }
+
+    /// The webadmin on this machine.
+    pub(crate) fn web_admin_url(&self) -> Url {
+        Url::parse(&format!("http://127.0.0.1:{}/", self.web_admin_port()))
+            .expect("Webadmin url is valid")
+    }
+
+    pub(crate) fn server_name(&self) -> Option<&str> {
+        self.game
+            .get("Engine.GameReplicationInfo", "ServerName")
+            .filter(|name| !name.is_empty())
+    }
+
+    pub(crate) fn admin_password(&self) -> Option<&str> {
+        self.game
+            .get("Engine.AccessControl", "AdminPassword")
+            .filter(|password| !password.is_empty())
+    }
+
+    /// Settings recorded in the database, by name. Passwords are only
+    /// recorded as being set or not.
+    pub(crate) fn settings(&self) -> Vec<(&'static str, String)> {
+        let game_password = self
+            .game
+            .get("Engine.AccessControl", "GamePassword")
+            .is_some_and(|password| !password.is_empty());
+        let game_length =
+            self.game
+                .get("KFGame.KFGameInfo", "GameLength")
+                .map(|length| match length {
+                    "0" => String::from("Short"),
+                    "1" => String::from("Medium"),
+                    "2" => String::from("Long"),
+                    length => length.to_string(),
+                });
+        let difficulty = self
+            .game
+            .get("KFGame.KFGameInfo", "GameDifficulty")
+            .map(|difficulty| {
+                difficulty
+                    .parse::<f32>()
+                    .ok()
+                    .and_then(|value| KfDifficulty::from_value(value.round() as u8))
+                    .map_or_else(|| difficulty.to_string(), |d| d.to_string())
+            });
+        let settings = [
+            ("server_name", self.server_name().map(String::from)),
+            (
+                "max_players",
+                self.game
+                    .get("Engine.GameInfo", "MaxPlayers")
+                    .map(String::from),
+            ),
+            ("game_length", game_length),
+            ("difficulty", difficulty),
+            ("game_password", Some(game_password.to_string())),
+            ("web_admin", Some(self.web_admin_enabled().to_string())),
+            ("web_admin_port", Some(self.web_admin_port().to_string())),
+            (
+                "game_port",
+                Some(self.engine.get("URL", "Port").unwrap_or("7777").to_string()),
+            ),
+            (
+                "query_port",
+                Some(
+                    self.engine
+                        .get(
+                            "OnlineSubsystemSteamworks.OnlineSubsystemSteamworks",
+                            "QueryPort",
+                        )
+                        .unwrap_or("27015")
+                        .to_string(),
+                ),
+            ),
+        ];
+        settings
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests_config {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture() -> ServerConfig {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("server");
+        ServerConfig::read(&path).unwrap()
+    }
+
+    #[test]
+    fn test_connection() {
+        let config = fixture();
+        assert!(config.web_admin_enabled());
+        assert_eq!(config.web_admin_url().as_str(), "http://127.0.0.1:8081/");
+        assert_eq!(config.admin_password(), Some("hunter2"));
+        assert_eq!(config.server_name(), Some("Koiran Killing Floor ; EU"));
+        assert!(ServerConfig::read(Path::new("/nonexistent")).is_err());
+    }
+
+    #[test]
+    fn test_settings() {
+        assert_eq!(
+            fixture().settings(),
+            vec![
+                ("server_name", String::from("Koiran Killing Floor ; EU")),
+                ("max_players", String::from("6")),
+                ("game_length", String::from("Medium")),
+                ("difficulty", String::from("Hell on Earth")),
+                ("game_password", String::from("false")),
+                ("web_admin", String::from("true")),
+                ("web_admin_port", String::from("8081")),
+                ("game_port", String::from("7777")),
+                ("query_port", String::from("27016")),
+            ]
+        );
+    }
+}
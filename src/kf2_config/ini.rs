@@ -0,0 +1,67 @@
+/// Settings of an Unreal Engine 3 ini file. Keys repeated with `+` or `-`
+/// for arrays are not needed here and the last value of a key wins.
+#[derive(Debug, Default)]
+pub(crate) struct Ini {
+    /// Section, key and value, in file order.
+    values: Vec<(String, String, String)>,
+}
+
+impl Ini {
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut values = Vec::new();
+        let mut section = String::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            if key.starts_with(['+', '-', '.', '!']) {
+                continue;
+            }
+            values.push((section.clone(), key.to_string(), value.trim().to_string()));
+        }
+        Self { values }
+    }
+
+    /// Value of the key, which like the section is not case sensitive.
+    pub(crate) fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(s, k, _)| s.eq_ignore_ascii_case(section) && k.eq_ignore_ascii_case(key))
+            .map(|(_, _, value)| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests_ini {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let ini = Ini::parse(
+            "; Written by the server\r\n\
+             [Engine.GameReplicationInfo]\r\n\
+             ServerName=Koira ; EU\r\n\
+             [KFGame.KFGameInfo]\r\n\
+             GameLength=0\r\n\
+             +GameMapCycles=(Maps=(\"KF-Outpost\"))\r\n\
+             GameLength=2\r\n",
+        );
+        assert_eq!(
+            ini.get("engine.gamereplicationinfo", "servername"),
+            Some("Koira ; EU")
+        );
+        assert_eq!(ini.get("KFGame.KFGameInfo", "GameLength"), Some("2"));
+        assert_eq!(ini.get("KFGame.KFGameInfo", "GameMapCycles"), None);
+        assert_eq!(ini.get("Engine.GameInfo", "GameLength"), None);
+    }
+}
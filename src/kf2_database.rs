@@ -1,4 +1,9 @@
+pub(super) mod export;
 pub(super) mod management;
+#[cfg(test)]
+pub(super) mod memory;
 pub(super) mod models;
 pub(super) mod operations;
+pub(super) mod queries;
+pub(super) mod store;
 mod tests;
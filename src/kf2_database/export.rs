@@ -0,0 +1,115 @@
+use super::management::KfDbManager;
+use super::models::{GameSessionDbQ, IpAddressDbQ, PlayerDbQ, PlayerSessionDbQ};
+use super::queries::{game_session_query, player_session_query, QueryFilter};
+use crate::schema::{game_sessions, ip_addresses, player_sessions, unique_players};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+use std::error::Error;
+
+/// Filters of the export. The history filters apply to the sessions; players
+/// and IP addresses are limited to the players of the matching sessions.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ExportFilter {
+    pub(crate) query: QueryFilter,
+    pub(crate) player: Option<u64>,
+}
+
+impl ExportFilter {
+    fn filters_sessions(&self) -> bool {
+        self.query.map.is_some()
+            || self.query.difficulty.is_some()
+            || self.query.game_mode.is_some()
+            || self.query.perk.is_some()
+    }
+}
+
+impl KfDbManager {
+    pub(crate) fn export_unique_players(
+        &self,
+        filter: &ExportFilter,
+    ) -> Result<Vec<PlayerDbQ>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let mut query = unique_players::table.into_boxed();
+        if let Some(steam_id) = filter.player {
+            query = query.filter(unique_players::steam_id.eq(steam_id));
+        }
+        if filter.filters_sessions() {
+            query = query.filter(
+                unique_players::steam_id
+                    .eq_any(player_session_query(&filter.query).select(player_sessions::steam_id)),
+            );
+        }
+        if let Some(from) = filter.query.start_time() {
+            query = query.filter(unique_players::last_seen.ge(from));
+        }
+        if let Some(until) = filter.query.until_time() {
+            query = query.filter(unique_players::created.lt(until));
+        }
+        Ok(query
+            .select(PlayerDbQ::as_select())
+            .order(unique_players::created.asc())
+            .load(&mut connection)?)
+    }
+
+    pub(crate) fn export_game_sessions(
+        &self,
+        filter: &ExportFilter,
+    ) -> Result<Vec<GameSessionDbQ>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let mut query = game_session_query(&filter.query);
+        if let Some(steam_id) = filter.player {
+            query = query.filter(
+                game_sessions::id.eq_any(
+                    player_sessions::table
+                        .filter(player_sessions::steam_id.eq(steam_id))
+                        .select(player_sessions::game_session_id),
+                ),
+            );
+        }
+        Ok(query
+            .select(GameSessionDbQ::as_select())
+            .order(game_sessions::started_at.asc())
+            .load(&mut connection)?)
+    }
+
+    pub(crate) fn export_player_sessions(
+        &self,
+        filter: &ExportFilter,
+    ) -> Result<Vec<PlayerSessionDbQ>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let mut query = player_session_query(&filter.query);
+        if let Some(steam_id) = filter.player {
+            query = query.filter(player_sessions::steam_id.eq(steam_id));
+        }
+        Ok(query
+            .select(PlayerSessionDbQ::as_select())
+            .order(player_sessions::started_at.asc())
+            .load(&mut connection)?)
+    }
+
+    pub(crate) fn export_ip_addresses(
+        &self,
+        filter: &ExportFilter,
+    ) -> Result<Vec<IpAddressDbQ>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let mut query = ip_addresses::table.into_boxed();
+        if let Some(steam_id) = filter.player {
+            query = query.filter(ip_addresses::steam_id.eq(steam_id));
+        }
+        if filter.filters_sessions() {
+            query = query.filter(
+                ip_addresses::steam_id
+                    .eq_any(player_session_query(&filter.query).select(player_sessions::steam_id)),
+            );
+        }
+        if let Some(from) = filter.query.start_time() {
+            query = query.filter(ip_addresses::created.ge(from));
+        }
+        if let Some(until) = filter.query.until_time() {
+            query = query.filter(ip_addresses::created.lt(until));
+        }
+        Ok(query
+            .select(IpAddressDbQ::as_select())
+            .order(ip_addresses::created.asc())
+            .load(&mut connection)?)
+    }
+}
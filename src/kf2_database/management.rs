@@ -5,6 +5,7 @@ use diesel::r2d2::PooledConnection;
 use diesel::MysqlConnection;
 use std::error::Error;
 
+#[derive(Clone)]
 pub struct KfDbManager {
     pub(super) ip_addr: String,
     pub(super) username: String,
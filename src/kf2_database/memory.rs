@@ -0,0 +1,211 @@
+use super::store::Kf2Store;
+use crate::kf2_log::logger::afk::AfkPeriod;
+use crate::kf2_log::logger::control::ControlRecord;
+use crate::kf2_log::logger::{GameSession, PlayerSession, SessionStatus, WaveSession};
+use crate::kf2_scrape::models::{BanInfo, PlayerInGame, PlayerInfo};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::IpAddr;
+
+/// In-memory store with the same insert and update rules as the database.
+#[derive(Debug, Default)]
+pub(crate) struct MemoryStore {
+    pub(crate) unique_players: Vec<PlayerInfo>,
+    pub(crate) maps_played: Vec<(u64, u32)>,
+    pub(crate) ip_addresses: Vec<(u64, IpAddr)>,
+    pub(crate) current_players: Vec<PlayerInGame>,
+    pub(crate) game_sessions: Vec<GameSession>,
+    pub(crate) wave_sessions: Vec<WaveSession>,
+    pub(crate) player_sessions: Vec<PlayerSession>,
+    pub(crate) control_actions: Vec<ControlRecord>,
+    pub(crate) afk_periods: Vec<AfkPeriod>,
+    pub(crate) bans: Vec<(String, BanInfo)>,
+    pub(crate) ip_bans: Vec<(String, IpAddr)>,
+    pub(crate) config_versions: Vec<(String, BTreeMap<String, String>)>,
+}
+
+impl MemoryStore {
+    pub(crate) fn maps_played(&self, steam_id: u64) -> u32 {
+        self.maps_played
+            .iter()
+            .find(|(id, _)| *id == steam_id)
+            .map_or(0, |(_, count)| *count)
+    }
+}
+
+impl Kf2Store for MemoryStore {
+    async fn log_unique_players(
+        &mut self,
+        players: Vec<PlayerInfo>,
+    ) -> Result<Vec<u64>, Box<dyn Error>> {
+        let mut new_players = Vec::new();
+        for player in players {
+            if !self.ip_addresses.contains(&(player.steam_id, player.ip)) {
+                self.ip_addresses.push((player.steam_id, player.ip));
+            }
+            match self
+                .unique_players
+                .iter_mut()
+                .find(|p| p.steam_id == player.steam_id)
+            {
+                Some(existing) => *existing = player,
+                None => {
+                    new_players.push(player.steam_id);
+                    self.unique_players.push(player);
+                }
+            }
+        }
+        Ok(new_players)
+    }
+
+    async fn log_in_game_players(
+        &mut self,
+        players: Vec<PlayerInGame>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.current_players = players;
+        Ok(())
+    }
+
+    async fn log_game_session(
+        &mut self,
+        mut game_info: GameSession,
+    ) -> Result<u32, Box<dyn Error>> {
+        match (game_info.status.clone(), game_info.db_id) {
+            (SessionStatus::New, _) => {
+                let db_id = self.game_sessions.len() as u32 + 1;
+                game_info.db_id = Some(db_id);
+                self.game_sessions.push(game_info);
+                Ok(db_id)
+            }
+            (SessionStatus::InProgress, Some(db_id)) => {
+                let index = db_id as usize - 1;
+                let session = self
+                    .game_sessions
+                    .get_mut(index)
+                    .ok_or("no game session rows")?;
+                *session = game_info;
+                Ok(db_id)
+            }
+            (status, _) => Err(format!("Invalid game session status {:?}", status).into()),
+        }
+    }
+
+    async fn log_wave_session(
+        &mut self,
+        mut wave_session: WaveSession,
+    ) -> Result<u32, Box<dyn Error>> {
+        if wave_session.status == SessionStatus::New {
+            let db_id = self.wave_sessions.len() as u32 + 1;
+            wave_session.db_id = Some(db_id);
+            self.wave_sessions.push(wave_session);
+            return Ok(db_id);
+        }
+        let db_id = wave_session.db_id.ok_or(format!(
+            "No wave session id for status {:?}",
+            wave_session.status
+        ))?;
+        let session = self
+            .wave_sessions
+            .get_mut(db_id as usize - 1)
+            .ok_or("no wave session rows")?;
+        *session = wave_session;
+        Ok(db_id)
+    }
+
+    async fn log_player_sessions(
+        &mut self,
+        players: Vec<PlayerSession>,
+    ) -> Result<Vec<PlayerSession>, Box<dyn Error>> {
+        let mut logged = Vec::new();
+        for mut player in players {
+            match player.db_id {
+                Some(db_id) => {
+                    let session = self
+                        .player_sessions
+                        .get_mut(db_id as usize - 1)
+                        .ok_or("no player session rows")?;
+                    *session = player.clone();
+                }
+                None => {
+                    let db_id = self.player_sessions.len() as u32 + 1;
+                    player.db_id = Some(db_id);
+                    self.player_sessions.push(player.clone());
+                    match self
+                        .maps_played
+                        .iter_mut()
+                        .find(|(id, _)| *id == player.steam_id)
+                    {
+                        Some((_, count)) => *count += 1,
+                        None => self.maps_played.push((player.steam_id, 1)),
+                    }
+                }
+            }
+            logged.push(player);
+        }
+        Ok(logged)
+    }
+
+    async fn log_control_action(&mut self, record: ControlRecord) -> Result<(), Box<dyn Error>> {
+        self.control_actions.push(record);
+        Ok(())
+    }
+
+    async fn log_afk_period(&mut self, period: AfkPeriod) -> Result<(), Box<dyn Error>> {
+        self.afk_periods.push(period);
+        Ok(())
+    }
+
+    async fn log_bans(&mut self, server: &str, bans: Vec<BanInfo>) -> Result<(), Box<dyn Error>> {
+        self.bans.retain(|(ban_server, _)| ban_server != server);
+        self.bans
+            .extend(bans.into_iter().map(|ban| (server.to_string(), ban)));
+        Ok(())
+    }
+
+    async fn ip_bans(&mut self, server: &str) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+        Ok(self
+            .ip_bans
+            .iter()
+            .filter(|(ban_server, _)| ban_server == server)
+            .map(|(_, ip)| *ip)
+            .collect())
+    }
+
+    async fn log_ip_ban(&mut self, server: &str, ip: IpAddr) -> Result<(), Box<dyn Error>> {
+        let ban = (server.to_string(), ip);
+        if !self.ip_bans.contains(&ban) {
+            self.ip_bans.push(ban);
+        }
+        Ok(())
+    }
+
+    async fn log_server_config(
+        &mut self,
+        server: &str,
+        config: BTreeMap<String, String>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let latest = self
+            .config_versions
+            .iter()
+            .rev()
+            .find(|(version_server, _)| version_server == server);
+        if latest.is_some_and(|(_, latest)| *latest == config) {
+            return Ok(false);
+        }
+        self.config_versions.push((server.to_string(), config));
+        Ok(true)
+    }
+
+    async fn player_ip_addresses(&mut self, steam_id: u64) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+        Ok(self
+            .ip_addresses
+            .iter()
+            .filter(|(id, _)| *id == steam_id)
+            .map(|(_, ip)| *ip)
+            .collect())
+    }
+
+    async fn player_maps_played(&mut self, steam_id: u64) -> Result<u32, Box<dyn Error>> {
+        Ok(self.maps_played(steam_id))
+    }
+}
@@ -1,11 +1,14 @@
 use crate::{
-    kf2_log::logger::{GameSession, PlayerSession},
-    kf2_scrape::models::{PlayerInGame, PlayerInfo},
+    kf2_log::logger::afk::AfkPeriod,
+    kf2_log::logger::control::ControlRecord,
+    kf2_log::logger::{GameSession, PlayerSession, WaveSession},
+    kf2_scrape::models::{BanInfo, GameMode, PlayerInGame, PlayerInfo},
 };
 use chrono;
 use diesel::prelude::*;
 use log::error;
-use std::net::Ipv4Addr;
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr};
 
 #[derive(Queryable, Selectable, Clone)]
 #[diesel(table_name = crate::schema::ip_addresses)]
@@ -16,6 +19,49 @@ pub(crate) struct IpAddressDbQ {
     pub(super) ip_address: u32,
     pub(super) created: chrono::NaiveDateTime,
 }
+impl IpAddressDbQ {
+    #[cfg(test)]
+    pub(crate) fn new(
+        id: u32,
+        steam_id: u64,
+        ip_address: Ipv4Addr,
+        created: chrono::NaiveDateTime,
+    ) -> Self {
+        Self {
+            id,
+            steam_id,
+            ip_address: ip_address.into(),
+            created,
+        }
+    }
+
+    pub(crate) fn ip(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.ip_address)
+    }
+}
+
+/// Exported IP address row. The address is left out of the file when the
+/// export omits IP addresses.
+#[derive(Serialize, Clone)]
+pub(crate) struct IpAddressRow {
+    pub(crate) id: u32,
+    pub(crate) steam_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) ip_address: Option<String>,
+    pub(crate) created: chrono::NaiveDateTime,
+}
+
+impl IpAddressRow {
+    pub(crate) fn new(ip_address: &IpAddressDbQ, exported_ip: Option<String>) -> Self {
+        Self {
+            id: ip_address.id,
+            steam_id: ip_address.steam_id,
+            ip_address: exported_ip,
+            created: ip_address.created,
+        }
+    }
+}
+
 #[derive(Insertable, AsChangeset, Clone)]
 #[diesel(table_name = crate::schema::ip_addresses)]
 #[diesel(check_for_backend(diesel::mysql::Mysql))]
@@ -40,17 +86,17 @@ impl IpAddressDbI {
     }
 }
 
-#[derive(Queryable, Selectable, Clone)]
+#[derive(Queryable, Selectable, Serialize, Clone)]
 #[diesel(table_name = crate::schema::unique_players)]
 #[diesel(check_for_backend(diesel::mysql::Mysql))]
 pub(crate) struct PlayerDbQ {
-    pub(super) steam_id: u64,
-    pub(super) name: String,
-    pub(super) maps_played: u32,
-    pub(super) avg_ping: u32,
-    pub(super) unique_net_id: String,
-    pub(super) created: chrono::NaiveDateTime,
-    pub(super) last_seen: chrono::NaiveDateTime,
+    pub(crate) steam_id: u64,
+    pub(crate) name: String,
+    pub(crate) maps_played: u32,
+    pub(crate) avg_ping: u32,
+    pub(crate) unique_net_id: String,
+    pub(crate) created: chrono::NaiveDateTime,
+    pub(crate) last_seen: chrono::NaiveDateTime,
 }
 
 #[derive(Insertable, AsChangeset, Clone)]
@@ -108,6 +154,7 @@ impl From<PlayerInGame> for CurrentPlayer {
 #[diesel(table_name = crate::schema::game_sessions)]
 #[diesel(check_for_backend(diesel::mysql::Mysql))]
 pub(super) struct GameSessionDbI {
+    pub(crate) server: Option<String>,
     pub(crate) max_waves: u16,
     pub(crate) reached_wave: u16,
     pub(crate) max_players: u16,
@@ -115,7 +162,11 @@ pub(super) struct GameSessionDbI {
     pub(crate) map_name: String,
     pub(crate) difficulty: String,
     pub(crate) game_type: String,
+    pub(crate) game_mode: String,
     pub(crate) boss: String,
+    pub(crate) weekly_outbreak: Option<String>,
+    pub(crate) objectives: Option<u16>,
+    pub(crate) versus_round: Option<u16>,
     pub(crate) started_at: chrono::NaiveDateTime,
     pub(crate) ended_at: Option<chrono::NaiveDateTime>,
 }
@@ -123,6 +174,7 @@ pub(super) struct GameSessionDbI {
 impl Into<GameSessionDbI> for GameSession {
     fn into(self) -> GameSessionDbI {
         GameSessionDbI {
+            server: Some(varchar_255(self.server)),
             max_waves: self.max_waves,
             reached_wave: self.reached_wave,
             max_players: self.max_players,
@@ -130,7 +182,17 @@ impl Into<GameSessionDbI> for GameSession {
             map_name: self.map_name,
             difficulty: self.difficulty.to_string(),
             game_type: self.game_type,
+            objectives: match self.game_mode {
+                GameMode::Objective => Some(self.objectives),
+                _ => None,
+            },
+            versus_round: match self.game_mode {
+                GameMode::Versus => Some(self.versus_round),
+                _ => None,
+            },
+            game_mode: self.game_mode.to_string(),
             boss: self.boss.to_string(),
+            weekly_outbreak: self.weekly_outbreak,
             started_at: self.started_at,
             ended_at: self.ended_at,
         }
@@ -149,7 +211,11 @@ pub(super) struct GameSessionDbU {
     pub(crate) map_name: String,
     pub(crate) difficulty: String,
     pub(crate) game_type: String,
+    pub(crate) game_mode: String,
     pub(crate) boss: String,
+    pub(crate) weekly_outbreak: Option<String>,
+    pub(crate) objectives: Option<u16>,
+    pub(crate) versus_round: Option<u16>,
     pub(crate) started_at: chrono::NaiveDateTime,
     pub(crate) ended_at: Option<chrono::NaiveDateTime>,
 }
@@ -165,7 +231,17 @@ impl Into<GameSessionDbU> for GameSession {
             map_name: self.map_name,
             difficulty: self.difficulty.to_string(),
             game_type: self.game_type,
+            objectives: match self.game_mode {
+                GameMode::Objective => Some(self.objectives),
+                _ => None,
+            },
+            versus_round: match self.game_mode {
+                GameMode::Versus => Some(self.versus_round),
+                _ => None,
+            },
+            game_mode: self.game_mode.to_string(),
             boss: self.boss.to_string(),
+            weekly_outbreak: self.weekly_outbreak,
             started_at: self.started_at,
             ended_at: self.ended_at,
         }
@@ -180,6 +256,7 @@ pub(crate) struct PlayerSessionDbI {
     pub(crate) steam_id: u64,
     pub(crate) perk: String,
     pub(crate) kills: u32,
+    pub(crate) team: Option<String>,
     pub(crate) started_at: chrono::NaiveDateTime,
     pub(crate) ended_at: chrono::NaiveDateTime,
 }
@@ -192,6 +269,7 @@ impl PlayerSessionDbI {
             steam_id: self.steam_id,
             perk: self.perk,
             kills: self.kills,
+            team: self.team,
             started_at: self.started_at,
             ended_at: self.ended_at,
         }
@@ -205,6 +283,7 @@ impl Into<PlayerSessionDbI> for PlayerSession {
             steam_id: self.steam_id,
             perk: self.perk.to_string(),
             kills: self.kills,
+            team: self.team,
             started_at: self.started_at,
             ended_at: self.ended_at,
         }
@@ -220,6 +299,7 @@ pub(crate) struct PlayerSessionDbU {
     pub(crate) steam_id: u64,
     pub(crate) perk: String,
     pub(crate) kills: u32,
+    pub(crate) team: Option<String>,
     pub(crate) started_at: chrono::NaiveDateTime,
     pub(crate) ended_at: chrono::NaiveDateTime,
 }
@@ -232,8 +312,290 @@ impl Into<PlayerSessionDbU> for PlayerSession {
             steam_id: self.steam_id,
             perk: self.perk.to_string(),
             kills: self.kills,
+            team: self.team,
             started_at: self.started_at,
             ended_at: self.ended_at,
         }
     }
 }
+
+#[derive(Clone, Insertable)]
+#[diesel(table_name = crate::schema::wave_sessions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct WaveSessionDbI {
+    pub(crate) game_session_id: u32,
+    pub(crate) wave: u16,
+    pub(crate) ai_remaining: u32,
+    pub(crate) longest_stall: u32,
+    pub(crate) started_at: chrono::NaiveDateTime,
+    pub(crate) trader_opened_at: Option<chrono::NaiveDateTime>,
+    pub(crate) ended_at: Option<chrono::NaiveDateTime>,
+}
+
+impl From<WaveSession> for WaveSessionDbI {
+    fn from(wave_session: WaveSession) -> Self {
+        Self {
+            game_session_id: wave_session.game_session_id,
+            wave: wave_session.wave,
+            ai_remaining: wave_session.ai_remaining,
+            longest_stall: wave_session.longest_stall,
+            started_at: wave_session.started_at,
+            trader_opened_at: wave_session.trader_opened_at,
+            ended_at: wave_session.ended_at,
+        }
+    }
+}
+
+#[derive(Clone, AsChangeset)]
+#[diesel(table_name = crate::schema::wave_sessions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct WaveSessionDbU {
+    pub(crate) id: u32,
+    pub(crate) ai_remaining: u32,
+    pub(crate) longest_stall: u32,
+    pub(crate) trader_opened_at: Option<chrono::NaiveDateTime>,
+    pub(crate) ended_at: Option<chrono::NaiveDateTime>,
+}
+
+impl From<WaveSession> for WaveSessionDbU {
+    fn from(wave_session: WaveSession) -> Self {
+        Self {
+            id: wave_session.db_id.expect("no wave session id!"),
+            ai_remaining: wave_session.ai_remaining,
+            longest_stall: wave_session.longest_stall,
+            trader_opened_at: wave_session.trader_opened_at,
+            ended_at: wave_session.ended_at,
+        }
+    }
+}
+
+/// Cuts text to fit a `VARCHAR(255)` column.
+fn varchar_255(text: String) -> String {
+    match text.char_indices().nth(255) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+#[derive(Clone, Insertable)]
+#[diesel(table_name = crate::schema::control_actions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct ControlActionDbI {
+    pub(crate) action: String,
+    pub(crate) target: Option<String>,
+    pub(crate) source: String,
+    pub(crate) error: Option<String>,
+    pub(crate) created: chrono::NaiveDateTime,
+}
+
+impl From<ControlRecord> for ControlActionDbI {
+    fn from(record: ControlRecord) -> Self {
+        Self {
+            action: record.action.to_string(),
+            target: record.target.map(varchar_255),
+            source: record.source,
+            error: record.error.map(varchar_255),
+            created: record.created,
+        }
+    }
+}
+
+#[derive(Clone, Insertable)]
+#[diesel(table_name = crate::schema::afk_periods)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct AfkPeriodDbI {
+    pub(crate) player_session_id: u32,
+    pub(crate) started_at: chrono::NaiveDateTime,
+    pub(crate) ended_at: chrono::NaiveDateTime,
+}
+
+impl From<AfkPeriod> for AfkPeriodDbI {
+    fn from(period: AfkPeriod) -> Self {
+        Self {
+            player_session_id: period.player_session_id,
+            started_at: period.started_at,
+            ended_at: period.ended_at,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::bans)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct BanDbQ {
+    pub(super) unique_net_id: String,
+    pub(super) steam_id: Option<u64>,
+    pub(super) name: Option<String>,
+}
+
+impl From<BanDbQ> for BanInfo {
+    fn from(ban: BanDbQ) -> Self {
+        Self {
+            unique_net_id: ban.unique_net_id,
+            steam_id: ban.steam_id,
+            name: ban.name,
+        }
+    }
+}
+
+#[derive(Clone, Insertable)]
+#[diesel(table_name = crate::schema::bans)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct BanDbI {
+    pub(crate) server: String,
+    pub(crate) unique_net_id: String,
+    pub(crate) steam_id: Option<u64>,
+    pub(crate) name: Option<String>,
+    pub(crate) created: chrono::NaiveDateTime,
+}
+
+impl BanDbI {
+    pub(super) fn new(server: &str, ban: BanInfo) -> Self {
+        Self {
+            server: varchar_255(server.to_string()),
+            unique_net_id: ban.unique_net_id,
+            steam_id: ban.steam_id,
+            name: ban.name,
+            created: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(Clone, Insertable)]
+#[diesel(table_name = crate::schema::ip_bans)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct IpBanDbI {
+    pub(crate) server: String,
+    pub(crate) ip_address: String,
+    pub(crate) created: chrono::NaiveDateTime,
+}
+
+impl IpBanDbI {
+    pub(super) fn new(server: &str, ip: IpAddr) -> Self {
+        Self {
+            server: varchar_255(server.to_string()),
+            ip_address: ip.to_string(),
+            created: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(Clone, Insertable)]
+#[diesel(table_name = crate::schema::server_config_versions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct ServerConfigVersionDbI {
+    pub(crate) server: String,
+    /// The settings as a JSON object.
+    pub(crate) settings: String,
+    pub(crate) created: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, Insertable)]
+#[diesel(table_name = crate::schema::server_settings)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(super) struct ServerSettingDbI {
+    pub(crate) server: String,
+    pub(crate) name: String,
+    pub(crate) value: String,
+    pub(crate) updated: chrono::NaiveDateTime,
+}
+
+impl ServerSettingDbI {
+    pub(super) fn new(server: &str, name: &str, value: String) -> Self {
+        Self {
+            server: varchar_255(server.to_string()),
+            name: name.to_string(),
+            value: varchar_255(value),
+            updated: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Serialize, Clone)]
+#[diesel(table_name = crate::schema::game_sessions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(crate) struct GameSessionDbQ {
+    pub(crate) id: u32,
+    pub(crate) max_waves: u16,
+    pub(crate) reached_wave: u16,
+    pub(crate) max_players: u16,
+    pub(crate) players_at_most: u16,
+    pub(crate) map_name: String,
+    pub(crate) difficulty: String,
+    pub(crate) game_type: String,
+    pub(crate) game_mode: String,
+    pub(crate) boss: String,
+    pub(crate) weekly_outbreak: Option<String>,
+    pub(crate) objectives: Option<u16>,
+    pub(crate) versus_round: Option<u16>,
+    pub(crate) started_at: chrono::NaiveDateTime,
+    pub(crate) ended_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Queryable, Selectable, Serialize, Clone)]
+#[diesel(table_name = crate::schema::player_sessions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(crate) struct PlayerSessionDbQ {
+    pub(crate) id: u32,
+    pub(crate) game_session_id: u32,
+    pub(crate) steam_id: u64,
+    pub(crate) perk: String,
+    pub(crate) kills: u32,
+    pub(crate) team: Option<String>,
+    pub(crate) started_at: chrono::NaiveDateTime,
+    pub(crate) ended_at: chrono::NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Serialize, Clone)]
+#[diesel(table_name = crate::schema::wave_sessions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub(crate) struct WaveSessionDbQ {
+    pub(super) id: u32,
+    pub(super) game_session_id: u32,
+    pub(super) wave: u16,
+    pub(super) ai_remaining: u32,
+    pub(super) longest_stall: u32,
+    pub(super) started_at: chrono::NaiveDateTime,
+    pub(super) trader_opened_at: Option<chrono::NaiveDateTime>,
+    pub(super) ended_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Player session with the player name and the map and difficulty of the game.
+#[derive(Serialize, Clone)]
+pub(crate) struct PlayerSessionRow {
+    #[serde(flatten)]
+    pub(super) session: PlayerSessionDbQ,
+    pub(super) name: String,
+    pub(super) map_name: String,
+    pub(super) difficulty: String,
+}
+
+impl From<(PlayerSessionDbQ, String, String, String)> for PlayerSessionRow {
+    fn from(
+        (session, name, map_name, difficulty): (PlayerSessionDbQ, String, String, String),
+    ) -> Self {
+        Self {
+            session,
+            name,
+            map_name,
+            difficulty,
+        }
+    }
+}
+
+#[derive(Queryable, Serialize, Clone)]
+pub(crate) struct LeaderboardRow {
+    pub(super) steam_id: u64,
+    pub(super) name: String,
+    pub(super) kills: u64,
+    pub(super) games: i64,
+    pub(super) highest_wave: Option<u16>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct GameSessionDetails {
+    #[serde(flatten)]
+    pub(super) game_session: GameSessionDbQ,
+    pub(super) players: Vec<PlayerSessionRow>,
+    pub(super) waves: Vec<WaveSessionDbQ>,
+}
@@ -1,36 +1,45 @@
 use super::management::KfDbManager;
 use super::models::{
-    CurrentPlayer, GameSessionDbI, GameSessionDbU, IpAddressDbI, IpAddressDbQ, PlayerDbI,
-    PlayerDbQ, PlayerSessionDbI, PlayerSessionDbU,
+    AfkPeriodDbI, BanDbI, BanDbQ, ControlActionDbI, CurrentPlayer, GameSessionDbI, GameSessionDbU,
+    IpAddressDbI, IpAddressDbQ, IpBanDbI, PlayerDbI, PlayerDbQ, PlayerSessionDbI, PlayerSessionDbU,
+    ServerConfigVersionDbI, ServerSettingDbI, WaveSessionDbI, WaveSessionDbU,
 };
-use crate::kf2_log::logger::{GameSession, PlayerSession, SessionStatus};
-use crate::kf2_scrape::models::{PlayerInGame, PlayerInfo};
+use crate::kf2_log::logger::afk::AfkPeriod;
+use crate::kf2_log::logger::control::ControlRecord;
+use crate::kf2_log::logger::{GameSession, PlayerSession, SessionStatus, WaveSession};
+use crate::kf2_scrape::models::{normalize_unique_net_id, BanInfo, PlayerInGame, PlayerInfo};
 use diesel::mysql::MysqlConnection;
 use diesel::r2d2::{ConnectionManager, PooledConnection};
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::SelectableHelper;
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
 use log::{error, info};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
 use std::{error::Error, net::Ipv4Addr};
 use std::{io::ErrorKind, thread};
 
 impl KfDbManager {
+    /// Saves the players and returns the Steam ids of players seen for the
+    /// first time.
     pub(crate) async fn log_unique_players(
         &mut self,
         players: Vec<PlayerInfo>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<Vec<u64>, Box<dyn Error>> {
         if players.is_empty() {
-            return Ok(());
+            return Ok(Vec::new());
         }
         let mut connection1 = self.get_connection()?;
         let mut connection2 = self.get_connection()?;
         let players2 = players.clone();
 
-        let thread_players = thread::spawn(move || {
-            if let Err(err) = Self::insert_unique_players(&mut connection1, players) {
-                return Err(std::io::Error::new(ErrorKind::Other, err.to_string()));
-            };
-            Ok(())
-        });
-        thread_players.join().unwrap()?;
+        let thread_players =
+            thread::spawn(
+                move || match Self::insert_unique_players(&mut connection1, players) {
+                    Ok(new_players) => Ok(new_players),
+                    Err(err) => Err(std::io::Error::new(ErrorKind::Other, err.to_string())),
+                },
+            );
+        let new_players = thread_players.join().unwrap()?;
         let thread_ip = thread::spawn(move || {
             if let Err(err) = Self::insert_ip_addresses(&mut connection2, players2) {
                 return Err(std::io::Error::new(ErrorKind::Other, err.to_string()));
@@ -38,7 +47,7 @@ impl KfDbManager {
             Ok(())
         });
         thread_ip.join().unwrap()?;
-        Ok(())
+        Ok(new_players)
     }
 
     pub(super) fn insert_ip_addresses(
@@ -81,7 +90,7 @@ impl KfDbManager {
     pub(super) fn insert_unique_players(
         connection: &mut PooledConnection<ConnectionManager<MysqlConnection>>,
         players: Vec<PlayerInfo>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<Vec<u64>, Box<dyn Error>> {
         use crate::schema::unique_players::dsl::*;
         let players = players
             .into_iter()
@@ -110,6 +119,7 @@ impl KfDbManager {
                     .any(|ep| ep.steam_id == p.steam_id)
             })
             .collect::<Vec<_>>();
+        let new_steam_ids = new_players.iter().map(|p| p.steam_id).collect();
 
         if !new_players.is_empty() {
             let len = new_players.len();
@@ -152,7 +162,7 @@ impl KfDbManager {
                 info!("Updated player: {}", p_name);
             }
         }
-        Ok(())
+        Ok(new_steam_ids)
     }
 
     pub(super) fn clean_current_players(
@@ -245,6 +255,253 @@ impl KfDbManager {
         Ok(db_id)
     }
 
+    pub(super) fn insert_wave_session(
+        connection: &mut PooledConnection<ConnectionManager<MysqlConnection>>,
+        wave_session: WaveSessionDbI,
+    ) -> Result<u32, Box<dyn Error>> {
+        use crate::schema::wave_sessions::dsl::*;
+        diesel::insert_into(wave_sessions)
+            .values(wave_session)
+            .execute(connection)?;
+        let db_id = wave_sessions
+            .select(diesel::dsl::max(id))
+            .load::<Option<u32>>(connection)?
+            .first()
+            .ok_or("no wave session rows")?
+            .ok_or("no wave session id")?;
+        Ok(db_id)
+    }
+
+    pub(super) fn update_wave_session(
+        connection: &mut PooledConnection<ConnectionManager<MysqlConnection>>,
+        wave_session: WaveSessionDbU,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::wave_sessions::dsl::*;
+        diesel::update(wave_sessions.find(wave_session.id))
+            .set(wave_session)
+            .execute(connection)?;
+        Ok(())
+    }
+
+    pub(crate) async fn log_wave_session(
+        &mut self,
+        wave_session: WaveSession,
+    ) -> Result<u32, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let wave = wave_session.wave;
+        let db_id;
+        if wave_session.status == SessionStatus::New {
+            db_id = Self::insert_wave_session(&mut connection, wave_session.into())?;
+            info!("New wave session: {}, wave {}", db_id, wave);
+        } else if let Some(id) = wave_session.db_id {
+            db_id = id;
+            Self::update_wave_session(&mut connection, wave_session.into())?;
+        } else {
+            return Err(format!("No wave session id for status {:?}", wave_session.status).into());
+        }
+        Ok(db_id)
+    }
+
+    pub(crate) async fn log_control_action(
+        &mut self,
+        record: ControlRecord,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::control_actions::dsl::*;
+        let mut connection = self.get_connection()?;
+        diesel::insert_into(control_actions)
+            .values(ControlActionDbI::from(record))
+            .execute(&mut connection)?;
+        Ok(())
+    }
+
+    pub(crate) async fn log_afk_period(&mut self, period: AfkPeriod) -> Result<(), Box<dyn Error>> {
+        use crate::schema::afk_periods::dsl::*;
+        let mut connection = self.get_connection()?;
+        diesel::insert_into(afk_periods)
+            .values(AfkPeriodDbI::from(period))
+            .execute(&mut connection)?;
+        Ok(())
+    }
+
+    /// Replaces the bans of the server with the ones on its bans page.
+    pub(crate) async fn log_bans(
+        &mut self,
+        server_name: &str,
+        bans_now: Vec<BanInfo>,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::bans::dsl::*;
+        let bans_now = unique_bans(bans_now);
+        let mut connection = self.get_connection()?;
+        let existing: Vec<String> = bans
+            .filter(server.eq(server_name))
+            .select(unique_net_id)
+            .load(&mut connection)?;
+        let lifted: Vec<&String> = existing
+            .iter()
+            .filter(|ban_id| {
+                !bans_now
+                    .iter()
+                    .any(|ban| ban.unique_net_id.eq_ignore_ascii_case(ban_id))
+            })
+            .collect();
+        if !lifted.is_empty() {
+            diesel::delete(
+                bans.filter(server.eq(server_name))
+                    .filter(unique_net_id.eq_any(lifted)),
+            )
+            .execute(&mut connection)?;
+        }
+        let new_bans: Vec<BanDbI> = bans_now
+            .into_iter()
+            .filter(|ban| {
+                !existing
+                    .iter()
+                    .any(|ban_id| ban_id.eq_ignore_ascii_case(&ban.unique_net_id))
+            })
+            .map(|ban| BanDbI::new(server_name, ban))
+            .collect();
+        if !new_bans.is_empty() {
+            info!("Added {} new bans", new_bans.len());
+            diesel::insert_into(bans)
+                .values(new_bans)
+                .execute(&mut connection)?;
+        }
+        Ok(())
+    }
+
+    /// Addresses the ban sync has denied on the server.
+    pub(crate) async fn ip_bans(
+        &mut self,
+        server_name: &str,
+    ) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+        use crate::schema::ip_bans::dsl::*;
+        let mut connection = self.get_connection()?;
+        let rows: Vec<String> = ip_bans
+            .filter(server.eq(server_name))
+            .select(ip_address)
+            .load(&mut connection)?;
+        Ok(rows.iter().filter_map(|row| row.parse().ok()).collect())
+    }
+
+    pub(crate) async fn log_ip_ban(
+        &mut self,
+        server_name: &str,
+        ip: IpAddr,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::ip_bans::dsl::*;
+        let mut connection = self.get_connection()?;
+        diesel::insert_or_ignore_into(ip_bans)
+            .values(IpBanDbI::new(server_name, ip))
+            .execute(&mut connection)?;
+        Ok(())
+    }
+
+    /// Bans of the other servers logging into the database. The rows of
+    /// `except_server` are left out, as they are what its bans page was at
+    /// the last sync and would add back a ban lifted since.
+    pub(crate) fn load_bans(&self, except_server: &str) -> Result<Vec<BanInfo>, Box<dyn Error>> {
+        use crate::schema::bans::dsl::*;
+        let mut connection = self.get_connection()?;
+        let rows = bans
+            .filter(server.ne(except_server))
+            .select(BanDbQ::as_select())
+            .order_by(id.asc())
+            .load(&mut connection)?;
+        Ok(rows.into_iter().map(BanInfo::from).collect())
+    }
+
+    /// Saves the settings read from the ini files of the server. Only
+    /// changed values are written, so `updated` tells when they changed.
+    pub(crate) fn log_server_settings(
+        &self,
+        server_name: &str,
+        settings: Vec<(&str, String)>,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::server_settings::dsl::*;
+        let mut connection = self.get_connection()?;
+        let existing: Vec<(String, String)> = server_settings
+            .filter(server.eq(server_name))
+            .select((name, value))
+            .load(&mut connection)?;
+        for (setting, setting_value) in settings {
+            let row = ServerSettingDbI::new(server_name, setting, setting_value);
+            match existing.iter().find(|(n, _)| n == setting) {
+                Some((_, old)) if *old == row.value => (),
+                Some((_, old)) => {
+                    info!(
+                        "Server setting {} changed from {} to {}",
+                        setting, old, row.value
+                    );
+                    diesel::update(
+                        server_settings
+                            .filter(server.eq(server_name))
+                            .filter(name.eq(setting)),
+                    )
+                    .set((value.eq(&row.value), updated.eq(row.updated)))
+                    .execute(&mut connection)?;
+                }
+                None => {
+                    diesel::insert_into(server_settings)
+                        .values(row)
+                        .execute(&mut connection)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the configuration as a new version when it differs from the
+    /// last stored one, and tells whether it did.
+    pub(crate) async fn log_server_config(
+        &mut self,
+        server_name: &str,
+        config: BTreeMap<String, String>,
+    ) -> Result<bool, Box<dyn Error>> {
+        use crate::schema::server_config_versions::dsl::*;
+        let mut connection = self.get_connection()?;
+        let latest: Option<String> = server_config_versions
+            .filter(server.eq(server_name))
+            .order_by(id.desc())
+            .select(settings)
+            .first(&mut connection)
+            .optional()?;
+        let latest: Option<BTreeMap<String, String>> = latest
+            .map(|latest| serde_json::from_str(&latest))
+            .transpose()?;
+        if latest.as_ref() == Some(&config) {
+            return Ok(false);
+        }
+        diesel::insert_into(server_config_versions)
+            .values(ServerConfigVersionDbI {
+                server: server_name.to_string(),
+                settings: serde_json::to_string(&config)?,
+                created: chrono::Utc::now().naive_utc(),
+            })
+            .execute(&mut connection)?;
+        Ok(true)
+    }
+
+    pub(crate) async fn player_ip_addresses(
+        &mut self,
+        player: u64,
+    ) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+        use crate::schema::ip_addresses::dsl::*;
+        let mut connection = self.get_connection()?;
+        let rows = ip_addresses
+            .filter(steam_id.eq(player))
+            .load::<IpAddressDbQ>(&mut connection)?;
+        Ok(rows.iter().map(|row| IpAddr::V4(row.ip())).collect())
+    }
+
+    pub(crate) async fn player_maps_played(&mut self, player: u64) -> Result<u32, Box<dyn Error>> {
+        use crate::schema::unique_players::dsl::*;
+        let mut connection = self.get_connection()?;
+        Ok(unique_players
+            .find(player)
+            .select(maps_played)
+            .first(&mut connection)?)
+    }
+
     pub(super) fn increment_played_sessions(
         connection: &mut PooledConnection<ConnectionManager<MysqlConnection>>,
         player: &PlayerSession,
@@ -332,7 +589,40 @@ impl KfDbManager {
     }
 }
 
+/// Bans with normalised unique net ids, each id once.
+fn unique_bans(bans: Vec<BanInfo>) -> Vec<BanInfo> {
+    let mut unique: Vec<BanInfo> = Vec::new();
+    for ban in bans {
+        let ban = BanInfo {
+            unique_net_id: normalize_unique_net_id(&ban.unique_net_id),
+            ..ban
+        };
+        if !unique.iter().any(|b| b.unique_net_id == ban.unique_net_id) {
+            unique.push(ban);
+        }
+    }
+    unique
+}
+
 #[cfg(test)]
 mod tests {
     use super::KfDbManager;
+    use super::{unique_bans, BanInfo};
+
+    #[test]
+    fn test_unique_bans() {
+        let ban = |unique_net_id: &str| BanInfo {
+            unique_net_id: String::from(unique_net_id),
+            steam_id: None,
+            name: None,
+        };
+        assert_eq!(
+            unique_bans(vec![
+                ban("0x0110000104bac2e1"),
+                ban("0X0110000104BAC2E1"),
+                ban("0x00000000DEADBEEF"),
+            ]),
+            vec![ban("0x0110000104BAC2E1"), ban("0x00000000DEADBEEF")]
+        );
+    }
 }
@@ -0,0 +1,420 @@
+use super::management::KfDbManager;
+use super::models::{
+    GameSessionDbQ, GameSessionDetails, LeaderboardRow, PlayerDbQ, PlayerSessionDbQ,
+    PlayerSessionRow, WaveSessionDbQ,
+};
+use crate::schema::{game_sessions, player_sessions, unique_players, wave_sessions};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::dsl::{count, sql, InnerJoin, IntoBoxed};
+use diesel::mysql::Mysql;
+use diesel::sql_types::{BigInt, Unsigned};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+const DEFAULT_PER_PAGE: u32 = 50;
+const MAX_PER_PAGE: u32 = 500;
+
+/// Filters of the history queries. Dates are inclusive and compared with the
+/// start time of the game or player session.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct QueryFilter {
+    pub(crate) map: Option<String>,
+    pub(crate) difficulty: Option<String>,
+    pub(crate) game_mode: Option<String>,
+    pub(crate) perk: Option<String>,
+    pub(crate) from: Option<NaiveDate>,
+    pub(crate) to: Option<NaiveDate>,
+}
+
+impl QueryFilter {
+    pub(super) fn start_time(&self) -> Option<NaiveDateTime> {
+        self.from.map(|date| date.and_time(NaiveTime::MIN))
+    }
+
+    /// Start of the day after `to`, so the whole last day is included.
+    pub(super) fn until_time(&self) -> Option<NaiveDateTime> {
+        self.to
+            .and_then(|date| date.succ_opt())
+            .map(|date| date.and_time(NaiveTime::MIN))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub(crate) struct Pagination {
+    pub(crate) page: Option<u32>,
+    pub(crate) per_page: Option<u32>,
+}
+
+impl Pagination {
+    fn page(&self) -> u32 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    fn per_page(&self) -> u32 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    fn limit(&self) -> i64 {
+        self.per_page() as i64
+    }
+
+    fn offset(&self) -> i64 {
+        (self.page() as i64 - 1) * self.per_page() as i64
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct Page<T> {
+    pub(crate) items: Vec<T>,
+    pub(crate) page: u32,
+    pub(crate) per_page: u32,
+    pub(crate) total: i64,
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, pagination: &Pagination, total: i64) -> Self {
+        Self {
+            items,
+            page: pagination.page(),
+            per_page: pagination.per_page(),
+            total,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LeaderboardOrder {
+    #[default]
+    Kills,
+    Games,
+    Waves,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub(crate) struct LeaderboardQuery {
+    #[serde(default)]
+    pub(crate) by: LeaderboardOrder,
+}
+
+pub(super) type PlayerSessionQuery = IntoBoxed<
+    'static,
+    InnerJoin<InnerJoin<player_sessions::table, game_sessions::table>, unique_players::table>,
+    Mysql,
+>;
+
+/// Applies the filter to a boxed query over player sessions joined with game
+/// sessions. A macro because grouped and plain boxed queries are different
+/// types.
+macro_rules! filter_player_sessions {
+    ($query:expr, $filter:expr) => {{
+        let filter: &QueryFilter = $filter;
+        let mut query = $query;
+        if let Some(map) = &filter.map {
+            query = query.filter(game_sessions::map_name.eq(map.clone()));
+        }
+        if let Some(difficulty) = &filter.difficulty {
+            query = query.filter(game_sessions::difficulty.eq(difficulty.clone()));
+        }
+        if let Some(game_mode) = &filter.game_mode {
+            query = query.filter(game_sessions::game_mode.eq(game_mode.clone()));
+        }
+        if let Some(perk) = &filter.perk {
+            query = query.filter(player_sessions::perk.eq(perk.clone()));
+        }
+        if let Some(from) = filter.start_time() {
+            query = query.filter(player_sessions::started_at.ge(from));
+        }
+        if let Some(until) = filter.until_time() {
+            query = query.filter(player_sessions::started_at.lt(until));
+        }
+        query
+    }};
+}
+
+pub(super) fn player_session_query(filter: &QueryFilter) -> PlayerSessionQuery {
+    let query = player_sessions::table
+        .inner_join(game_sessions::table)
+        .inner_join(unique_players::table)
+        .into_boxed();
+    filter_player_sessions!(query, filter)
+}
+
+pub(super) fn game_session_query(
+    filter: &QueryFilter,
+) -> game_sessions::BoxedQuery<'static, Mysql> {
+    let mut query = game_sessions::table.into_boxed();
+    if let Some(map) = &filter.map {
+        query = query.filter(game_sessions::map_name.eq(map.clone()));
+    }
+    if let Some(difficulty) = &filter.difficulty {
+        query = query.filter(game_sessions::difficulty.eq(difficulty.clone()));
+    }
+    if let Some(game_mode) = &filter.game_mode {
+        query = query.filter(game_sessions::game_mode.eq(game_mode.clone()));
+    }
+    if let Some(perk) = &filter.perk {
+        query = query.filter(
+            game_sessions::id.eq_any(
+                player_sessions::table
+                    .filter(player_sessions::perk.eq(perk.clone()))
+                    .select(player_sessions::game_session_id),
+            ),
+        );
+    }
+    if let Some(from) = filter.start_time() {
+        query = query.filter(game_sessions::started_at.ge(from));
+    }
+    if let Some(until) = filter.until_time() {
+        query = query.filter(game_sessions::started_at.lt(until));
+    }
+    query
+}
+
+impl KfDbManager {
+    pub(crate) fn query_players(
+        &self,
+        filter: &QueryFilter,
+        pagination: &Pagination,
+    ) -> Result<Page<PlayerDbQ>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let query = || {
+            let mut query = unique_players::table.into_boxed();
+            if let Some(from) = filter.start_time() {
+                query = query.filter(unique_players::last_seen.ge(from));
+            }
+            if let Some(until) = filter.until_time() {
+                query = query.filter(unique_players::last_seen.lt(until));
+            }
+            query
+        };
+        let total = query().count().get_result(&mut connection)?;
+        let players = query()
+            .select(PlayerDbQ::as_select())
+            .order(unique_players::last_seen.desc())
+            .limit(pagination.limit())
+            .offset(pagination.offset())
+            .load(&mut connection)?;
+        Ok(Page::new(players, pagination, total))
+    }
+
+    pub(crate) fn query_player(&self, steam_id: u64) -> Result<Option<PlayerDbQ>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        Ok(unique_players::table
+            .find(steam_id)
+            .select(PlayerDbQ::as_select())
+            .first(&mut connection)
+            .optional()?)
+    }
+
+    pub(crate) fn query_player_sessions(
+        &self,
+        steam_id: Option<u64>,
+        filter: &QueryFilter,
+        pagination: &Pagination,
+    ) -> Result<Page<PlayerSessionRow>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let query = || match steam_id {
+            Some(steam_id) => {
+                player_session_query(filter).filter(player_sessions::steam_id.eq(steam_id))
+            }
+            None => player_session_query(filter),
+        };
+        let total = query().count().get_result(&mut connection)?;
+        let sessions = query()
+            .select((
+                PlayerSessionDbQ::as_select(),
+                unique_players::name,
+                game_sessions::map_name,
+                game_sessions::difficulty,
+            ))
+            .order(player_sessions::started_at.desc())
+            .limit(pagination.limit())
+            .offset(pagination.offset())
+            .load::<(PlayerSessionDbQ, String, String, String)>(&mut connection)?;
+        Ok(Page::new(
+            sessions.into_iter().map(PlayerSessionRow::from).collect(),
+            pagination,
+            total,
+        ))
+    }
+
+    pub(crate) fn query_game_sessions(
+        &self,
+        filter: &QueryFilter,
+        pagination: &Pagination,
+    ) -> Result<Page<GameSessionDbQ>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let total = game_session_query(filter)
+            .count()
+            .get_result(&mut connection)?;
+        let game_sessions = game_session_query(filter)
+            .select(GameSessionDbQ::as_select())
+            .order(game_sessions::started_at.desc())
+            .limit(pagination.limit())
+            .offset(pagination.offset())
+            .load(&mut connection)?;
+        Ok(Page::new(game_sessions, pagination, total))
+    }
+
+    pub(crate) fn query_game_session(
+        &self,
+        id: u32,
+    ) -> Result<Option<GameSessionDetails>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let Some(game_session) = game_sessions::table
+            .find(id)
+            .select(GameSessionDbQ::as_select())
+            .first(&mut connection)
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let players = player_sessions::table
+            .inner_join(game_sessions::table)
+            .inner_join(unique_players::table)
+            .filter(player_sessions::game_session_id.eq(id))
+            .select((
+                PlayerSessionDbQ::as_select(),
+                unique_players::name,
+                game_sessions::map_name,
+                game_sessions::difficulty,
+            ))
+            .order(player_sessions::kills.desc())
+            .load::<(PlayerSessionDbQ, String, String, String)>(&mut connection)?
+            .into_iter()
+            .map(PlayerSessionRow::from)
+            .collect();
+        let waves = wave_sessions::table
+            .filter(wave_sessions::game_session_id.eq(id))
+            .select(WaveSessionDbQ::as_select())
+            .order(wave_sessions::started_at.asc())
+            .load(&mut connection)?;
+        Ok(Some(GameSessionDetails {
+            game_session,
+            players,
+            waves,
+        }))
+    }
+
+    /// Players ranked by total kills, games played or the highest wave
+    /// reached, counting only the player sessions that match the filter.
+    pub(crate) fn query_leaderboard(
+        &self,
+        order: LeaderboardOrder,
+        filter: &QueryFilter,
+        pagination: &Pagination,
+    ) -> Result<Page<LeaderboardRow>, Box<dyn Error>> {
+        let mut connection = self.get_connection()?;
+        let total = player_session_query(filter)
+            .select(diesel::dsl::count_distinct(player_sessions::steam_id))
+            .get_result(&mut connection)?;
+        let order_by = match order {
+            LeaderboardOrder::Kills => "SUM(player_sessions.kills) DESC",
+            LeaderboardOrder::Games => "COUNT(player_sessions.id) DESC",
+            LeaderboardOrder::Waves => "MAX(game_sessions.reached_wave) DESC",
+        };
+        let query = player_sessions::table
+            .inner_join(game_sessions::table)
+            .inner_join(unique_players::table)
+            .group_by((player_sessions::steam_id, unique_players::name))
+            .select((
+                player_sessions::steam_id,
+                unique_players::name,
+                sql::<Unsigned<BigInt>>("CAST(SUM(player_sessions.kills) AS UNSIGNED)"),
+                count(player_sessions::id),
+                diesel::dsl::max(game_sessions::reached_wave),
+            ))
+            .into_boxed();
+        let rows = filter_player_sessions!(query, filter)
+            .order(sql::<BigInt>(order_by))
+            .then_order_by(player_sessions::steam_id.asc())
+            .limit(pagination.limit())
+            .offset(pagination.offset())
+            .load::<LeaderboardRow>(&mut connection)?;
+        Ok(Page::new(rows, pagination, total))
+    }
+}
+
+#[cfg(test)]
+mod tests_queries {
+    use super::*;
+
+    #[test]
+    fn test_pagination_defaults() {
+        let pagination = Pagination::default();
+        assert_eq!(pagination.limit(), 50);
+        assert_eq!(pagination.offset(), 0);
+    }
+
+    #[test]
+    fn test_pagination_offset() {
+        let pagination = Pagination {
+            page: Some(3),
+            per_page: Some(20),
+        };
+        assert_eq!(pagination.limit(), 20);
+        assert_eq!(pagination.offset(), 40);
+    }
+
+    #[test]
+    fn test_pagination_limits() {
+        let pagination = Pagination {
+            page: Some(0),
+            per_page: Some(100_000),
+        };
+        assert_eq!(pagination.page(), 1);
+        assert_eq!(pagination.limit(), 500);
+        assert_eq!(pagination.offset(), 0);
+    }
+
+    #[test]
+    fn test_filter_date_range() {
+        let filter = QueryFilter {
+            from: NaiveDate::from_ymd_opt(2023, 10, 1),
+            to: NaiveDate::from_ymd_opt(2023, 10, 31),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.start_time().unwrap().to_string(),
+            "2023-10-01 00:00:00"
+        );
+        assert_eq!(
+            filter.until_time().unwrap().to_string(),
+            "2023-11-01 00:00:00"
+        );
+    }
+
+    #[test]
+    fn test_player_session_query_filters() {
+        let filter = QueryFilter {
+            map: Some(String::from("KF-Outpost")),
+            game_mode: Some(String::from("Endless")),
+            perk: Some(String::from("Medic")),
+            from: NaiveDate::from_ymd_opt(2023, 10, 1),
+            ..Default::default()
+        };
+        let sql = diesel::debug_query::<Mysql, _>(&player_session_query(&filter)).to_string();
+        assert!(sql.contains("INNER JOIN `game_sessions`"));
+        assert!(sql.contains("`game_sessions`.`map_name` = ?"));
+        assert!(sql.contains("`game_sessions`.`game_mode` = ?"));
+        assert!(sql.contains("`player_sessions`.`perk` = ?"));
+        assert!(sql.contains("`player_sessions`.`started_at` >= ?"));
+        assert!(!sql.contains("`game_sessions`.`difficulty` = ?"));
+    }
+
+    #[test]
+    fn test_game_session_query_perk_filter() {
+        let filter = QueryFilter {
+            game_mode: Some(String::from("Weekly")),
+            perk: Some(String::from("Berserker")),
+            ..Default::default()
+        };
+        let sql = diesel::debug_query::<Mysql, _>(&game_session_query(&filter)).to_string();
+        assert!(sql.contains("`game_sessions`.`game_mode` = ?"));
+        assert!(sql.contains("`game_sessions`.`id` IN (SELECT `player_sessions`.`game_session_id`"));
+    }
+}
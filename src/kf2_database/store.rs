@@ -0,0 +1,157 @@
+use super::management::KfDbManager;
+use crate::kf2_log::logger::afk::AfkPeriod;
+use crate::kf2_log::logger::control::ControlRecord;
+use crate::kf2_log::logger::{GameSession, PlayerSession, WaveSession};
+use crate::kf2_metrics;
+use crate::kf2_scrape::models::{BanInfo, PlayerInGame, PlayerInfo};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::IpAddr;
+use tokio::time::Instant;
+
+/// Storage used by the logger. The MySQL database is the only backend the
+/// binary uses; tests run the logger against an in-memory store.
+pub(crate) trait Kf2Store {
+    /// Returns the Steam ids of players seen for the first time.
+    async fn log_unique_players(
+        &mut self,
+        players: Vec<PlayerInfo>,
+    ) -> Result<Vec<u64>, Box<dyn Error>>;
+
+    async fn log_in_game_players(
+        &mut self,
+        players: Vec<PlayerInGame>,
+    ) -> Result<(), Box<dyn Error>>;
+
+    async fn log_game_session(&mut self, game_info: GameSession) -> Result<u32, Box<dyn Error>>;
+
+    async fn log_wave_session(&mut self, wave_session: WaveSession) -> Result<u32, Box<dyn Error>>;
+
+    async fn log_player_sessions(
+        &mut self,
+        players: Vec<PlayerSession>,
+    ) -> Result<Vec<PlayerSession>, Box<dyn Error>>;
+
+    async fn log_control_action(&mut self, record: ControlRecord) -> Result<(), Box<dyn Error>>;
+
+    async fn log_afk_period(&mut self, period: AfkPeriod) -> Result<(), Box<dyn Error>>;
+
+    /// `server` tells apart the bans of servers sharing the database.
+    async fn log_bans(&mut self, server: &str, bans: Vec<BanInfo>) -> Result<(), Box<dyn Error>>;
+
+    /// Addresses denied on the server by the ban sync.
+    async fn ip_bans(&mut self, server: &str) -> Result<Vec<IpAddr>, Box<dyn Error>>;
+
+    async fn log_ip_ban(&mut self, server: &str, ip: IpAddr) -> Result<(), Box<dyn Error>>;
+
+    /// Stores the webadmin settings when they changed since the last
+    /// version. Returns true when a new version was stored.
+    async fn log_server_config(
+        &mut self,
+        server: &str,
+        config: BTreeMap<String, String>,
+    ) -> Result<bool, Box<dyn Error>>;
+
+    /// Addresses the player has been seen with.
+    async fn player_ip_addresses(&mut self, steam_id: u64) -> Result<Vec<IpAddr>, Box<dyn Error>>;
+
+    /// Maps the player has played before the current one.
+    async fn player_maps_played(&mut self, steam_id: u64) -> Result<u32, Box<dyn Error>>;
+}
+
+impl Kf2Store for KfDbManager {
+    async fn log_unique_players(
+        &mut self,
+        players: Vec<PlayerInfo>,
+    ) -> Result<Vec<u64>, Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_unique_players(self, players).await;
+        kf2_metrics::observe_db_write("unique_players", start.elapsed());
+        result
+    }
+
+    async fn log_in_game_players(
+        &mut self,
+        players: Vec<PlayerInGame>,
+    ) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_in_game_players(self, players).await;
+        kf2_metrics::observe_db_write("current_players", start.elapsed());
+        result
+    }
+
+    async fn log_game_session(&mut self, game_info: GameSession) -> Result<u32, Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_game_session(self, game_info).await;
+        kf2_metrics::observe_db_write("game_session", start.elapsed());
+        result
+    }
+
+    async fn log_wave_session(&mut self, wave_session: WaveSession) -> Result<u32, Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_wave_session(self, wave_session).await;
+        kf2_metrics::observe_db_write("wave_session", start.elapsed());
+        result
+    }
+
+    async fn log_player_sessions(
+        &mut self,
+        players: Vec<PlayerSession>,
+    ) -> Result<Vec<PlayerSession>, Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_player_sessions(self, players).await;
+        kf2_metrics::observe_db_write("player_sessions", start.elapsed());
+        result
+    }
+
+    async fn log_control_action(&mut self, record: ControlRecord) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_control_action(self, record).await;
+        kf2_metrics::observe_db_write("control_actions", start.elapsed());
+        result
+    }
+
+    async fn log_afk_period(&mut self, period: AfkPeriod) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_afk_period(self, period).await;
+        kf2_metrics::observe_db_write("afk_periods", start.elapsed());
+        result
+    }
+
+    async fn log_bans(&mut self, server: &str, bans: Vec<BanInfo>) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_bans(self, server, bans).await;
+        kf2_metrics::observe_db_write("bans", start.elapsed());
+        result
+    }
+
+    async fn ip_bans(&mut self, server: &str) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+        KfDbManager::ip_bans(self, server).await
+    }
+
+    async fn log_ip_ban(&mut self, server: &str, ip: IpAddr) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_ip_ban(self, server, ip).await;
+        kf2_metrics::observe_db_write("ip_bans", start.elapsed());
+        result
+    }
+
+    async fn log_server_config(
+        &mut self,
+        server: &str,
+        config: BTreeMap<String, String>,
+    ) -> Result<bool, Box<dyn Error>> {
+        let start = Instant::now();
+        let result = KfDbManager::log_server_config(self, server, config).await;
+        kf2_metrics::observe_db_write("server_config_versions", start.elapsed());
+        result
+    }
+
+    async fn player_ip_addresses(&mut self, steam_id: u64) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+        KfDbManager::player_ip_addresses(self, steam_id).await
+    }
+
+    async fn player_maps_played(&mut self, steam_id: u64) -> Result<u32, Box<dyn Error>> {
+        KfDbManager::player_maps_played(self, steam_id).await
+    }
+}
@@ -0,0 +1,243 @@
+pub(super) mod file;
+
+use crate::kf2_api::live::{LiveGame, LivePlayer};
+use log::warn;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Events buffered for each sink before a slow sink starts missing them.
+const CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) enum GameOutcome {
+    Won,
+    Lost,
+    Abandoned,
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameOutcome::Won => write!(f, "Won"),
+            GameOutcome::Lost => write!(f, "Lost"),
+            GameOutcome::Abandoned => write!(f, "Abandoned"),
+        }
+    }
+}
+
+/// What the tracker decided happened on the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum EventKind {
+    GameStarted,
+    /// `boss` is set when the wave is the boss wave.
+    WaveAdvanced {
+        wave: u16,
+        boss: Option<String>,
+    },
+    GameEnded {
+        outcome: GameOutcome,
+    },
+    /// `first_seen` is set for players never seen on the server before.
+    PlayerJoined {
+        steam_id: u64,
+        name: String,
+        first_seen: bool,
+    },
+    PlayerLeft {
+        steam_id: u64,
+        name: String,
+    },
+    PerkChanged {
+        steam_id: u64,
+        name: String,
+        from: String,
+        to: String,
+    },
+    ServerEmpty,
+    ServerFull,
+    WebAdminUnreachable {
+        error: String,
+    },
+    /// A map loaded in the server output. The game on it starts with
+    /// `GameStarted` once it is played.
+    MapLoaded {
+        map: String,
+    },
+    /// What the logger scraped on a tick, published after every tick.
+    Snapshot,
+}
+
+impl EventKind {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            EventKind::GameStarted => "game_started",
+            EventKind::WaveAdvanced { .. } => "wave_advanced",
+            EventKind::GameEnded { .. } => "game_ended",
+            EventKind::PlayerJoined { .. } => "player_joined",
+            EventKind::PlayerLeft { .. } => "player_left",
+            EventKind::PerkChanged { .. } => "perk_changed",
+            EventKind::ServerEmpty => "server_empty",
+            EventKind::ServerFull => "server_full",
+            EventKind::WebAdminUnreachable { .. } => "web_admin_unreachable",
+            EventKind::MapLoaded { .. } => "map_loaded",
+            EventKind::Snapshot => "snapshot",
+        }
+    }
+}
+
+/// An event with the game and players as they were when it happened.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct TrackerEvent {
+    #[serde(flatten)]
+    pub(crate) kind: EventKind,
+    pub(crate) timestamp: chrono::NaiveDateTime,
+    pub(crate) game_session: Option<LiveGame>,
+    pub(crate) players: Vec<LivePlayer>,
+}
+
+/// Receives every event of the tracker, in order.
+pub(crate) trait EventSink: Send + 'static {
+    fn handle(&mut self, event: &TrackerEvent);
+}
+
+/// Channel the logger publishes events on. Each subscriber gets every event.
+#[derive(Clone)]
+pub(crate) struct EventBus {
+    sender: broadcast::Sender<Arc<TrackerEvent>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+}
+
+impl EventBus {
+    pub(crate) fn publish(&self, event: TrackerEvent) {
+        // Nobody listening is not an error
+        let _ = self.sender.send(Arc::new(event));
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Arc<TrackerEvent>> {
+        self.sender.subscribe()
+    }
+
+    /// Runs the sink in its own task until the bus is dropped.
+    pub(crate) fn spawn_sink<S: EventSink>(&self, mut sink: S) -> tokio::task::JoinHandle<()> {
+        let mut receiver = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => sink.handle(&event),
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Event sink fell behind, {} events skipped", missed)
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Runs a sink that blocks, like one writing files, on a thread of its
+    /// own until the bus is dropped, so it does not hold up a runtime worker.
+    pub(crate) fn spawn_thread_sink<S: EventSink>(
+        &self,
+        name: &str,
+        mut sink: S,
+    ) -> std::thread::JoinHandle<()> {
+        let mut receiver = self.subscribe();
+        std::thread::Builder::new()
+            .name(String::from(name))
+            .spawn(move || loop {
+                match receiver.blocking_recv() {
+                    Ok(event) => sink.handle(&event),
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Event sink fell behind, {} events skipped", missed)
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            })
+            .expect("Event sink thread starts")
+    }
+}
+
+#[cfg(test)]
+mod tests_event {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Collect(Arc<Mutex<Vec<&'static str>>>);
+
+    impl EventSink for Collect {
+        fn handle(&mut self, event: &TrackerEvent) {
+            self.0.lock().unwrap().push(event.kind.name());
+        }
+    }
+
+    fn event(kind: EventKind) -> TrackerEvent {
+        TrackerEvent {
+            kind,
+            timestamp: chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(18, 0, 0)
+                .unwrap(),
+            game_session: None,
+            players: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_sink_gets_every_event() {
+        let bus = EventBus::default();
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+        let handles = vec![
+            bus.spawn_sink(Collect(first.clone())),
+            bus.spawn_sink(Collect(second.clone())),
+        ];
+        bus.publish(event(EventKind::GameStarted));
+        bus.publish(event(EventKind::WaveAdvanced {
+            wave: 2,
+            boss: None,
+        }));
+        drop(bus);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(
+            *first.lock().unwrap(),
+            vec!["game_started", "wave_advanced"]
+        );
+        assert_eq!(*first.lock().unwrap(), *second.lock().unwrap());
+    }
+
+    #[test]
+    fn test_thread_sink() {
+        let bus = EventBus::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let handle = bus.spawn_thread_sink("event-log", Collect(events.clone()));
+        bus.publish(event(EventKind::GameStarted));
+        drop(bus);
+        handle.join().unwrap();
+        assert_eq!(*events.lock().unwrap(), vec!["game_started"]);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let json = serde_json::to_value(event(EventKind::PerkChanged {
+            steam_id: 76561198039614177,
+            name: String::from("koira"),
+            from: String::from("Berserker"),
+            to: String::from("Medic"),
+        }))
+        .unwrap();
+        assert_eq!(json["event"], "perk_changed");
+        assert_eq!(json["to"], "Medic");
+        assert_eq!(json["timestamp"], "2024-05-01T18:00:00");
+        assert!(json["game_session"].is_null());
+    }
+}
@@ -0,0 +1,187 @@
+use super::{EventSink, TrackerEvent};
+use crate::args::Kf2EventLogArgs;
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{error, info};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "events.jsonl";
+
+/// Event sink writing every event as a JSON line to `events.jsonl`. The file
+/// is rotated when it would grow past the size limit or the day changes, and
+/// the old file is gzipped into `compressed/`.
+pub(crate) struct EventLog {
+    directory: PathBuf,
+    max_size: u64,
+    file: Option<File>,
+    size: u64,
+    opened_on: NaiveDate,
+}
+
+impl EventLog {
+    pub(crate) fn new(args: Kf2EventLogArgs) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(args.path.join("compressed"))?;
+        Ok(Self {
+            directory: args.path,
+            max_size: args.max_size,
+            file: None,
+            size: 0,
+            opened_on: Utc::now().date_naive(),
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        self.directory.join(FILE_NAME)
+    }
+
+    /// Opens the current file, continuing one left by an earlier run.
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path())?;
+            let metadata = file.metadata()?;
+            self.size = metadata.len();
+            self.opened_on = match metadata.modified() {
+                Ok(modified) if self.size > 0 => DateTime::<Utc>::from(modified).date_naive(),
+                _ => Utc::now().date_naive(),
+            };
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("Event log file was just opened"))
+    }
+
+    /// Moves the current file to `compressed/events-<date>.<n>.jsonl.gz`.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let path = self.path();
+        let compressed = (1..)
+            .map(|n| {
+                self.directory.join("compressed").join(format!(
+                    "events-{}.{}.jsonl.gz",
+                    self.opened_on.format("%Y-%m-%d"),
+                    n
+                ))
+            })
+            .find(|compressed| !compressed.exists())
+            .expect("Ran out of file names");
+        compress(&path, &compressed)?;
+        fs::remove_file(&path)?;
+        info!("Event log rotated to {}", compressed.display());
+        Ok(())
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        self.open()?;
+        let line_size = line.len() as u64 + 1;
+        let new_day = Utc::now().date_naive() != self.opened_on;
+        if self.size > 0 && (new_day || self.size + line_size > self.max_size) {
+            self.rotate()?;
+        }
+        let file = self.open()?;
+        writeln!(file, "{}", line)?;
+        self.size += line_size;
+        Ok(())
+    }
+}
+
+fn compress(from: &Path, to: &Path) -> io::Result<()> {
+    let mut input = File::open(from)?;
+    let mut output = GzEncoder::new(File::create(to)?, Compression::default());
+    io::copy(&mut input, &mut output)?;
+    output.finish()?.sync_all()
+}
+
+impl EventSink for EventLog {
+    fn handle(&mut self, event: &TrackerEvent) {
+        let line = match serde_json::to_string(event) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Could not serialize {} event: {}", event.kind.name(), e);
+                return;
+            }
+        };
+        if let Err(e) = self.write(&line) {
+            error!("Could not write event log: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_file {
+    use super::*;
+    use crate::kf2_event::EventKind;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn event_log(max_size: u64) -> EventLog {
+        let path = std::env::temp_dir().join(format!("kf2-event-log-{}", rand::random::<u64>()));
+        EventLog::new(Kf2EventLogArgs { path, max_size }).unwrap()
+    }
+
+    fn event(wave: u16) -> TrackerEvent {
+        TrackerEvent {
+            kind: EventKind::WaveAdvanced { wave, boss: None },
+            timestamp: Utc::now().date_naive().and_hms_opt(18, 0, 0).unwrap(),
+            game_session: None,
+            players: Vec::new(),
+        }
+    }
+
+    fn waves(text: &str) -> Vec<u64> {
+        text.lines()
+            .map(|line| {
+                let event: serde_json::Value = serde_json::from_str(line).unwrap();
+                event["wave"].as_u64().unwrap()
+            })
+            .collect()
+    }
+
+    fn read_gz(path: &Path) -> String {
+        let mut text = String::new();
+        GzDecoder::new(File::open(path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_rotate_by_size() {
+        let line_size = serde_json::to_string(&event(1)).unwrap().len() as u64 + 1;
+        let mut log = event_log(line_size * 5 / 2);
+        for wave in 1..=5 {
+            log.handle(&event(wave));
+        }
+
+        let date = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+        let compressed = log.directory.join("compressed");
+        let first = read_gz(&compressed.join(format!("events-{}.1.jsonl.gz", date)));
+        let second = read_gz(&compressed.join(format!("events-{}.2.jsonl.gz", date)));
+        assert_eq!(waves(&first), vec![1, 2]);
+        assert_eq!(waves(&second), vec![3, 4]);
+        assert_eq!(waves(&fs::read_to_string(log.path()).unwrap()), vec![5]);
+        fs::remove_dir_all(&log.directory).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_by_day() {
+        let mut log = event_log(1 << 20);
+        log.handle(&event(1));
+        let yesterday = log.opened_on.pred_opt().unwrap();
+        log.opened_on = yesterday;
+        log.handle(&event(2));
+
+        let compressed = log.directory.join("compressed").join(format!(
+            "events-{}.1.jsonl.gz",
+            yesterday.format("%Y-%m-%d")
+        ));
+        assert_eq!(waves(&read_gz(&compressed)), vec![1]);
+        assert_eq!(waves(&fs::read_to_string(log.path()).unwrap()), vec![2]);
+        fs::remove_dir_all(&log.directory).unwrap();
+    }
+}
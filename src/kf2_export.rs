@@ -0,0 +1,249 @@
+use crate::args::Kf2ExportArgs;
+use crate::kf2_database::management::KfDbManager;
+use crate::kf2_database::models::{IpAddressDbQ, IpAddressRow};
+use hmac::{Hmac, Mac};
+use log::info;
+use serde::Serialize;
+use sha2::Sha256;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExportTable {
+    UniquePlayers,
+    GameSessions,
+    PlayerSessions,
+    IpAddresses,
+}
+
+impl ExportTable {
+    pub(crate) const ALL: [ExportTable; 4] = [
+        ExportTable::UniquePlayers,
+        ExportTable::GameSessions,
+        ExportTable::PlayerSessions,
+        ExportTable::IpAddresses,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ExportTable::UniquePlayers => "unique_players",
+            ExportTable::GameSessions => "game_sessions",
+            ExportTable::PlayerSessions => "player_sessions",
+            ExportTable::IpAddresses => "ip_addresses",
+        }
+    }
+}
+
+impl FromStr for ExportTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ExportTable::ALL
+            .into_iter()
+            .find(|table| table.name() == s)
+            .ok_or_else(|| format!("Unknown table {}", s))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("Unknown format {}", s)),
+        }
+    }
+}
+
+/// How IP addresses are exported. Hashes are keyed, so the same address gets
+/// the same hash within an export but cannot be brute forced back without
+/// the key.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IpMode {
+    #[default]
+    Omit,
+    Hash,
+    Plain,
+}
+
+impl FromStr for IpMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "omit" => Ok(IpMode::Omit),
+            "hash" => Ok(IpMode::Hash),
+            "plain" => Ok(IpMode::Plain),
+            _ => Err(format!("Unknown IP mode {}", s)),
+        }
+    }
+}
+
+fn hash_ip(key: &str, ip_address: &IpAddressDbQ) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key");
+    mac.update(ip_address.ip().to_string().as_bytes());
+    mac.finalize().into_bytes()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn ip_rows(ip_addresses: &[IpAddressDbQ], mode: IpMode, key: &str) -> Vec<IpAddressRow> {
+    ip_addresses
+        .iter()
+        .map(|ip_address| {
+            let exported_ip = match mode {
+                IpMode::Omit => None,
+                IpMode::Hash => Some(hash_ip(key, ip_address)),
+                IpMode::Plain => Some(ip_address.ip().to_string()),
+            };
+            IpAddressRow::new(ip_address, exported_ip)
+        })
+        .collect()
+}
+
+fn write_rows<T: Serialize>(
+    path: &Path,
+    format: ExportFormat,
+    rows: &[T],
+) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(file);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            let mut writer = BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut writer, rows)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the selected tables to `<output>/<table>.<format>`.
+pub(crate) fn run(args: Kf2ExportArgs, db: &KfDbManager) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&args.output)?;
+    let key = args
+        .ip_key
+        .clone()
+        .unwrap_or_else(|| format!("{:032x}", rand::random::<u128>()));
+    for table in &args.tables {
+        let path = args
+            .output
+            .join(format!("{}.{}", table.name(), args.format.extension()));
+        let rows = match table {
+            ExportTable::UniquePlayers => {
+                let rows = db.export_unique_players(&args.filter)?;
+                write_rows(&path, args.format, &rows)?;
+                rows.len()
+            }
+            ExportTable::GameSessions => {
+                let rows = db.export_game_sessions(&args.filter)?;
+                write_rows(&path, args.format, &rows)?;
+                rows.len()
+            }
+            ExportTable::PlayerSessions => {
+                let rows = db.export_player_sessions(&args.filter)?;
+                write_rows(&path, args.format, &rows)?;
+                rows.len()
+            }
+            ExportTable::IpAddresses => {
+                let rows = ip_rows(&db.export_ip_addresses(&args.filter)?, args.ips, &key);
+                write_rows(&path, args.format, &rows)?;
+                rows.len()
+            }
+        };
+        info!("Exported {} rows to {}", rows, path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_export {
+    use super::*;
+
+    fn ip_addresses() -> Vec<IpAddressDbQ> {
+        let created = chrono::NaiveDate::from_ymd_opt(2023, 10, 1)
+            .unwrap()
+            .and_hms_opt(20, 0, 0)
+            .unwrap();
+        vec![
+            IpAddressDbQ::new(1, 76561198039614177, [10, 0, 0, 1].into(), created),
+            IpAddressDbQ::new(2, 76561198000740875, [10, 0, 0, 1].into(), created),
+        ]
+    }
+
+    fn export_csv(mode: IpMode) -> String {
+        let path = std::env::temp_dir().join(format!("kf2-export-{}.csv", rand::random::<u64>()));
+        let rows = ip_rows(&ip_addresses(), mode, "kissa123");
+        write_rows(&path, ExportFormat::Csv, &rows).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        csv
+    }
+
+    #[test]
+    fn test_ip_omitted() {
+        let csv = export_csv(IpMode::Omit);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("id,steam_id,created"));
+        assert_eq!(
+            lines.next(),
+            Some("1,76561198039614177,2023-10-01T20:00:00")
+        );
+        assert!(!csv.contains("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_ip_hashed() {
+        let csv = export_csv(IpMode::Hash);
+        assert!(csv.starts_with("id,steam_id,ip_address,created"));
+        assert!(!csv.contains("10.0.0.1"));
+        let hashes: Vec<&str> = csv
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').nth(2).unwrap())
+            .collect();
+        // Players sharing an address share the hash
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[0].len(), 16);
+        assert_ne!(hashes[0], hash_ip("koira", &ip_addresses()[0]));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(
+            "player_sessions".parse::<ExportTable>(),
+            Ok(ExportTable::PlayerSessions)
+        );
+        assert_eq!("json".parse::<ExportFormat>(), Ok(ExportFormat::Json));
+        assert!("xml".parse::<ExportFormat>().is_err());
+        assert!("mask".parse::<IpMode>().is_err());
+    }
+}
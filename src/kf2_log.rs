@@ -1 +1,5 @@
 pub(super) mod logger;
+mod matching;
+#[cfg(test)]
+pub(super) mod mock;
+mod tests;
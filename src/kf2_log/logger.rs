@@ -1,40 +1,76 @@
+pub(crate) mod afk;
+mod announce;
+mod bans;
+pub(crate) mod control;
+mod ping;
+mod query;
+mod server_log;
+mod settings;
+mod welcome;
+
 use crate::args::Kf2ServerArgs;
-use crate::kf2_database::management::KfDbManager;
+use crate::kf2_api::live::{LiveGame, LivePlayer};
 use crate::kf2_database::models::PlayerSessionDbU;
-use crate::kf2_scrape::models::{GameInfo, KfDifficulty, PlayerInGame, PlayerInfo};
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_event::{EventBus, EventKind, GameOutcome, TrackerEvent};
+use crate::kf2_metrics;
+use crate::kf2_scrape::models::{
+    weekly_outbreak, GameInfo, GameMode, KfDifficulty, PlayerInGame, PlayerInfo, VersusTeam,
+    WaveInfo,
+};
 use crate::kf2_scrape::parse::{DocumentExtractor, HeaderExtractor};
-use log::info;
-use reqwest::{Client, ClientBuilder};
+
+use super::matching::match_players;
+use log::{info, warn};
+use reqwest::{Client, ClientBuilder, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
+use tokio::time::Instant;
 use url::Url;
 
-#[repr(u8)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum Boss {
-    HansVolter = 0,
-    Patriarch = 1,
-    KingFleshpound = 2,
-    Matriarch = 3,
-    Abomination = 4,
-    Undefined = 5,
+    HansVolter,
+    Patriarch,
+    KingFleshpound,
+    Matriarch,
+    Abomination,
+    Undefined,
+    Unknown(u8),
 }
 
 impl Boss {
-    pub(crate) fn map(input: &u8) -> Result<Self, Box<dyn Error>> {
+    /// Index stored for `Undefined`, outside the indices the game uses.
+    const UNDEFINED_INDEX: u8 = u8::MAX;
+
+    /// Maps `KFGameReplicationInfo.BossIndex`. Indices of bosses added by
+    /// mods are kept as `Unknown`.
+    pub(crate) fn map(input: &u8) -> Self {
         match input {
-            0 => Ok(Boss::HansVolter),
-            1 => Ok(Boss::Patriarch),
-            2 => Ok(Boss::KingFleshpound),
-            3 => Ok(Boss::Matriarch),
-            4 => Ok(Boss::Abomination),
-            _ => Err(format!("Unknown boss {}", input).into()),
+            0 => Boss::HansVolter,
+            1 => Boss::Patriarch,
+            2 => Boss::KingFleshpound,
+            3 => Boss::Matriarch,
+            4 => Boss::Abomination,
+            &Self::UNDEFINED_INDEX => Boss::Undefined,
+            index => {
+                log::warn!("Unknown boss {}", index);
+                Boss::Unknown(*index)
+            }
         }
     }
 
     pub(crate) fn value(&self) -> u8 {
-        self.clone() as u8
+        match self {
+            Boss::HansVolter => 0,
+            Boss::Patriarch => 1,
+            Boss::KingFleshpound => 2,
+            Boss::Matriarch => 3,
+            Boss::Abomination => 4,
+            Boss::Undefined => Self::UNDEFINED_INDEX,
+            Boss::Unknown(index) => *index,
+        }
     }
 
     pub(crate) fn to_string(&self) -> String {
@@ -45,6 +81,7 @@ impl Boss {
             Boss::Matriarch => String::from("Matriarch"),
             Boss::Abomination => String::from("Abomination"),
             Boss::Undefined => String::from("Undefined"),
+            Boss::Unknown(index) => format!("Unknown ({})", index),
         }
     }
 }
@@ -56,6 +93,7 @@ pub(crate) struct PlayerSession {
     pub(crate) steam_id: u64,
     pub(crate) perk: String,
     pub(crate) kills: u32,
+    pub(crate) team: Option<String>,
     pub(crate) started_at: chrono::NaiveDateTime,
     pub(crate) ended_at: chrono::NaiveDateTime,
 }
@@ -68,6 +106,7 @@ impl Into<PlayerSession> for PlayerSessionDbU {
             steam_id: self.steam_id,
             perk: self.perk,
             kills: self.kills,
+            team: self.team,
             started_at: self.started_at,
             ended_at: self.ended_at,
         }
@@ -81,22 +120,170 @@ pub(crate) enum SessionStatus {
     Ended,
 }
 
+/// A game on one map. `max_waves` is zero for Endless, which has no last wave.
 #[derive(Debug, Clone)]
 pub(crate) struct GameSession {
     pub(crate) db_id: Option<u32>,
+    /// Server id of the logger, see `Kf2ServerArgs`.
+    pub(crate) server: String,
     pub(crate) max_waves: u16,
+    pub(crate) current_wave: u16,
     pub(crate) reached_wave: u16,
     pub(crate) max_players: u16,
     pub(crate) players_at_most: u16,
     pub(crate) map_name: String,
     pub(crate) difficulty: KfDifficulty,
     pub(crate) game_type: String,
+    pub(crate) game_mode: GameMode,
     pub(crate) boss: Boss,
+    pub(crate) weekly_outbreak: Option<String>,
+    pub(crate) objective: Option<String>,
+    pub(crate) objectives: u16,
+    pub(crate) versus_round: u16,
+    pub(crate) started_at: chrono::NaiveDateTime,
+    pub(crate) ended_at: Option<chrono::NaiveDateTime>,
+    pub(crate) status: SessionStatus,
+}
+
+impl GameSession {
+    fn new(server: &str, game_info: GameInfo) -> Self {
+        let max_waves = match game_info.game_mode {
+            GameMode::Endless => 0,
+            _ => game_info.max_waves,
+        };
+        Self {
+            db_id: None,
+            server: server.to_string(),
+            max_waves,
+            current_wave: game_info.current_wave,
+            reached_wave: game_info.current_wave,
+            max_players: game_info.max_players,
+            players_at_most: game_info.current_players,
+            map_name: game_info.map_name,
+            difficulty: game_info.difficulty,
+            game_type: game_info.game_type,
+            game_mode: game_info.game_mode,
+            boss: game_info.boss,
+            weekly_outbreak: game_info.weekly_outbreak,
+            objectives: u16::from(game_info.objective.is_some()),
+            objective: game_info.objective,
+            versus_round: 1,
+            started_at: chrono::Utc::now().naive_utc(),
+            ended_at: None,
+            status: SessionStatus::New,
+        }
+    }
+
+    /// Checks whether the scraped game info still belongs to this game. A
+    /// different map, mode or difficulty or the wave going backwards means a
+    /// new game, except in Versus where teams
+    /// swap sides and the second round starts again from the first wave.
+    /// Endless and Weekly can change boss during the game.
+    fn is_same_game(&self, game_info: &GameInfo) -> bool {
+        if self.map_name != game_info.map_name
+            || self.game_mode != game_info.game_mode
+            || self.difficulty != game_info.difficulty
+        {
+            return false;
+        }
+        let wave_reset = game_info.current_wave < self.current_wave;
+        match self.game_mode {
+            GameMode::Versus => !wave_reset || self.versus_round < 2,
+            GameMode::Endless => !wave_reset,
+            GameMode::Weekly => !wave_reset && self.weekly_outbreak == game_info.weekly_outbreak,
+            _ => !wave_reset && self.boss == game_info.boss,
+        }
+    }
+
+    /// Event of the game reaching the wave.
+    pub(super) fn wave_advanced(&self, wave: u16) -> EventKind {
+        let boss_wave = self.max_waves > 0 && wave > self.max_waves;
+        EventKind::WaveAdvanced {
+            wave,
+            boss: boss_wave.then(|| self.boss.to_string()),
+        }
+    }
+
+    fn update(&mut self, game_info: &GameInfo) {
+        if self.game_mode == GameMode::Versus && game_info.current_wave < self.current_wave {
+            self.versus_round += 1;
+            info!("Versus teams swapped, round {}", self.versus_round);
+        }
+        if let Some(objective) = &game_info.objective {
+            if self.objective.as_ref() != Some(objective) {
+                self.objectives += 1;
+                self.objective = Some(objective.clone());
+            }
+        }
+        self.current_wave = game_info.current_wave;
+        self.reached_wave = self.reached_wave.max(game_info.current_wave);
+        self.players_at_most = self.players_at_most.max(game_info.current_players);
+        self.boss = game_info.boss.clone();
+        self.ended_at = Some(chrono::Utc::now().naive_utc());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct WaveSession {
+    pub(crate) db_id: Option<u32>,
+    pub(crate) game_session_id: u32,
+    pub(crate) wave: u16,
+    pub(crate) ai_remaining: u32,
+    pub(crate) ai_remaining_changed_at: chrono::NaiveDateTime,
+    pub(crate) longest_stall: u32,
     pub(crate) started_at: chrono::NaiveDateTime,
+    pub(crate) trader_opened_at: Option<chrono::NaiveDateTime>,
     pub(crate) ended_at: Option<chrono::NaiveDateTime>,
     pub(crate) status: SessionStatus,
 }
 
+impl WaveSession {
+    fn new(game_session_id: u32, wave_info: &WaveInfo) -> Self {
+        let now = chrono::Utc::now().naive_utc();
+        Self {
+            db_id: None,
+            game_session_id,
+            wave: wave_info.wave,
+            ai_remaining: wave_info.ai_remaining,
+            ai_remaining_changed_at: now,
+            longest_stall: 0,
+            started_at: now,
+            trader_opened_at: if wave_info.trader_open {
+                Some(now)
+            } else {
+                None
+            },
+            ended_at: None,
+            status: SessionStatus::New,
+        }
+    }
+
+    /// Whether the sample belongs to this wave. A new game, as after a
+    /// restart, or another wave number ends it.
+    fn is_same_wave(&self, game_session_id: u32, wave_info: &WaveInfo) -> bool {
+        self.game_session_id == game_session_id && self.wave == wave_info.wave
+    }
+
+    /// Updates the wave with a new sample. Zeds remaining that does not change
+    /// while the wave is in progress is counted as a stall.
+    fn update(&mut self, wave_info: &WaveInfo) {
+        let now = chrono::Utc::now().naive_utc();
+        if wave_info.trader_open && self.trader_opened_at.is_none() {
+            info!("Trader opened after wave {}", self.wave);
+            self.trader_opened_at = Some(now);
+        }
+        if wave_info.ai_remaining != self.ai_remaining {
+            self.ai_remaining = wave_info.ai_remaining;
+            self.ai_remaining_changed_at = now;
+        } else if !wave_info.trader_open && wave_info.ai_remaining > 0 {
+            let stall = (now - self.ai_remaining_changed_at).num_seconds() as u32;
+            if stall > self.longest_stall {
+                self.longest_stall = stall;
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub(super) struct AuthForm {
     pub token: String,
@@ -112,18 +299,41 @@ pub(super) struct Kf2Url {
     pub(super) info: Url,
     pub(super) players: Url,
     pub(super) console: Url,
+    pub(super) change: Url,
+    pub(super) chat: Url,
+    pub(super) bans: Url,
+    pub(super) policy: Url,
+    pub(super) general: Url,
+    pub(super) map_cycle: Url,
+    pub(super) mutators: Url,
 }
 
-pub(crate) struct Kf2Logger {
+pub(crate) struct Kf2Logger<S: Kf2Store> {
     url: Kf2Url,
+    /// Key of the server in the tables servers share.
+    server_id: String,
     session: Client,
-    db_connection: KfDbManager,
+    db_connection: S,
+    username: String,
+    password: String,
     session_id: String,
     auth_cred: Option<String>,
     game_session: Option<GameSession>,
     in_game_players: Option<Vec<PlayerInGame>>,
     unique_players: Option<Vec<PlayerInfo>>,
     player_sessions: Option<Vec<PlayerSession>>,
+    wave_session: Option<WaveSession>,
+    events: EventBus,
+    player_count: Option<u16>,
+    web_admin_reachable: bool,
+    ping_kick: Option<ping::PingKick>,
+    afk: Option<afk::AfkTracker>,
+    ban_sync: Option<bans::BanSync>,
+    announcer: Option<announce::Announcer>,
+    greeter: Option<welcome::Greeter>,
+    query: Option<query::QueryCheck>,
+    config_history: Option<settings::ConfigHistory>,
+    log_events: server_log::LogEvents,
 }
 
 impl Kf2Url {
@@ -133,6 +343,13 @@ impl Kf2Url {
         let info = web_admin.join("current/info")?;
         let players = web_admin.join("current/players")?;
         let console = web_admin.join("console")?;
+        let change = web_admin.join("current/change")?;
+        let chat = web_admin.join("current/chat+frame+data")?;
+        let bans = web_admin.join("policy/bans")?;
+        let policy = web_admin.join("policy")?;
+        let general = web_admin.join("settings/general")?;
+        let map_cycle = web_admin.join("settings/maplist")?;
+        let mutators = web_admin.join("settings/mutators")?;
 
         Ok(Self {
             base,
@@ -140,24 +357,105 @@ impl Kf2Url {
             info,
             players,
             console,
+            change,
+            chat,
+            bans,
+            policy,
+            general,
+            map_cycle,
+            mutators,
         })
     }
 }
 
-impl Kf2Logger {
+impl<S: Kf2Store> Kf2Logger<S> {
     pub(crate) async fn new_session(
         args: Kf2ServerArgs,
-        db_connection: KfDbManager,
+        db_connection: S,
     ) -> Result<Self, Box<dyn Error>> {
+        let server_id = args.server_id().to_string();
         let (ip_addr, username, password) = args.get();
         let url = Kf2Url::new(ip_addr)?;
         let client = ClientBuilder::new().cookie_store(true).build()?;
+        let (session_id, auth_cred) = Self::login(&client, &url, &username, &password).await?;
+        let session = client;
+
+        Ok(Self {
+            url,
+            server_id,
+            session,
+            username,
+            password,
+            session_id,
+            auth_cred,
+            db_connection,
+            game_session: None,
+            in_game_players: None,
+            unique_players: None,
+            player_sessions: None,
+            wave_session: None,
+            events: EventBus::default(),
+            player_count: None,
+            web_admin_reachable: true,
+            ping_kick: None,
+            afk: None,
+            ban_sync: None,
+            announcer: None,
+            greeter: None,
+            query: None,
+            config_history: None,
+            log_events: server_log::LogEvents::default(),
+        })
+    }
+
+    fn tracker_event(&self, kind: EventKind) -> TrackerEvent {
+        TrackerEvent {
+            kind,
+            timestamp: chrono::Utc::now().naive_utc(),
+            game_session: self.game_session.as_ref().map(LiveGame::from),
+            players: self
+                .current_players()
+                .iter()
+                .map(LivePlayer::from)
+                .collect(),
+        }
+    }
+
+    fn emit(&self, kind: EventKind) {
+        info!("Event: {}", kind.name());
+        self.events.publish(self.tracker_event(kind));
+    }
+
+    /// Publishes the game and players scraped on this tick.
+    pub(crate) fn publish_snapshot(&self) {
+        self.events.publish(self.tracker_event(EventKind::Snapshot));
+    }
+
+    async fn login(
+        client: &Client,
+        url: &Kf2Url,
+        username: &str,
+        password: &str,
+    ) -> Result<(String, Option<String>), Box<dyn Error>> {
+        let start = Instant::now();
+        let result = Self::send_login(client, url, username, password).await;
+        kf2_metrics::observe_scrape("login", start.elapsed());
+        kf2_metrics::login_attempt(result.is_ok());
+        result
+    }
+
+    async fn send_login(
+        client: &Client,
+        url: &Kf2Url,
+        username: &str,
+        password: &str,
+    ) -> Result<(String, Option<String>), Box<dyn Error>> {
         let get_response = client.get(url.web_admin.as_str()).send().await?;
         let headers = HeaderExtractor::new(get_response.headers().to_owned());
         let text = get_response.text().await?;
-        let document = DocumentExtractor::new(&text);
-        drop(text);
-        let token = document.parse_form_token()?;
+        let token = DocumentExtractor::new(&text)
+            .parse_form_token()
+            .inspect_err(|_| kf2_metrics::parse_failure("login"))?;
         let session_id = headers
             .get_cookie("sessionid")
             .ok_or("Session id not found")?;
@@ -165,8 +463,8 @@ impl Kf2Logger {
         let form = AuthForm {
             token,
             password_hash: "".to_string(),
-            username,
-            password,
+            username: username.to_string(),
+            password: password.to_string(),
             remember: "-1".to_string(),
         };
 
@@ -177,42 +475,121 @@ impl Kf2Logger {
             .await?;
         let headers = HeaderExtractor::new(post_response.headers().to_owned());
         let auth_cred = headers.get_cookie("authcred");
-        let session = client;
+        let text = post_response.text().await?;
+        if DocumentExtractor::new(&text).is_login_page() {
+            return Err("Webadmin login failed, check the username and password".into());
+        }
+        Ok((session_id, auth_cred))
+    }
 
-        Ok(Self {
-            url,
-            session,
-            session_id,
-            auth_cred,
-            db_connection,
-            game_session: None,
-            in_game_players: None,
-            unique_players: None,
-            player_sessions: None,
-        })
+    /// Sends a webadmin request. Webadmin sessions do not survive a server
+    /// restart, so when the login page is returned the logger logs in again
+    /// and repeats the request.
+    async fn fetch(
+        &mut self,
+        page: &str,
+        request: RequestBuilder,
+    ) -> Result<String, Box<dyn Error>> {
+        let retry = request
+            .try_clone()
+            .ok_or("Webadmin request cannot be repeated")?;
+        let text = self.send(page, request).await?;
+        if !DocumentExtractor::new(&text).is_login_page() {
+            return Ok(text);
+        }
+        warn!("Webadmin session expired, logging in again");
+        let (session_id, auth_cred) =
+            Self::login(&self.session, &self.url, &self.username, &self.password).await?;
+        self.session_id = session_id;
+        self.auth_cred = auth_cred;
+        self.send(page, retry).await
     }
 
-    async fn get_unique_players(&self) -> Result<Vec<PlayerInfo>, Box<dyn Error>> {
-        let response = self.session.get(self.url.players.as_str()).send().await?;
+    /// Sends the request and reads the body. Failing to reach the webadmin
+    /// is emitted once per outage.
+    async fn send(
+        &mut self,
+        page: &str,
+        request: RequestBuilder,
+    ) -> Result<String, Box<dyn Error>> {
+        let start = Instant::now();
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                if self.web_admin_reachable {
+                    self.web_admin_reachable = false;
+                    self.emit(EventKind::WebAdminUnreachable {
+                        error: e.to_string(),
+                    });
+                }
+                return Err(e.into());
+            }
+        };
+        if !self.web_admin_reachable {
+            info!("Webadmin reachable again");
+            self.web_admin_reachable = true;
+        }
         let text = response.text().await?;
+        kf2_metrics::observe_scrape(page, start.elapsed());
+        Ok(text)
+    }
+
+    async fn get_unique_players(&mut self) -> Result<Vec<PlayerInfo>, Box<dyn Error>> {
+        let request = self.session.get(self.url.players.as_str());
+        let text = self.fetch("players", request).await?;
         let document = DocumentExtractor::new(&text);
         Ok(document.parse_steam_player_info())
     }
 
     pub(crate) async fn log_unique_players(&mut self) -> Result<(), Box<dyn Error>> {
         let players_steam = self.get_unique_players().await?;
+        let previous_players = self.unique_players.take().unwrap_or_default();
+        let log_joined = self.log_events.players_scraped(&players_steam);
+        for player in previous_players
+            .iter()
+            .filter(|player| !players_steam.iter().any(|p| p.steam_id == player.steam_id))
+        {
+            self.emit(EventKind::PlayerLeft {
+                steam_id: player.steam_id,
+                name: player.name.clone(),
+            });
+        }
         if players_steam.is_empty() {
-            self.unique_players = None;
+            self.welcome_players(Vec::new()).await;
             return Ok(());
         }
         self.unique_players = Some(players_steam.clone());
-        self.db_connection.log_unique_players(players_steam).await?;
+        let new_players = self
+            .db_connection
+            .log_unique_players(players_steam.clone())
+            .await?;
+        let mut joined = Vec::new();
+        for player in players_steam.into_iter().filter(|player| {
+            !previous_players
+                .iter()
+                .any(|p| p.steam_id == player.steam_id)
+        }) {
+            let first_seen = new_players.contains(&player.steam_id);
+            if !log_joined.contains(&player.steam_id) {
+                self.emit(EventKind::PlayerJoined {
+                    first_seen,
+                    steam_id: player.steam_id,
+                    name: player.name.clone(),
+                });
+            }
+            joined.push(welcome::JoinedPlayer {
+                steam_id: player.steam_id,
+                name: player.name,
+                first_seen,
+            });
+        }
+        self.welcome_players(joined).await;
         Ok(())
     }
 
-    async fn get_in_game_players(&self) -> Result<Vec<PlayerInGame>, Box<dyn Error>> {
-        let response = self.session.get(self.url.info.as_str()).send().await?;
-        let text = response.text().await?;
+    async fn get_in_game_players(&mut self) -> Result<Vec<PlayerInGame>, Box<dyn Error>> {
+        let request = self.session.get(self.url.info.as_str());
+        let text = self.fetch("info", request).await?;
         let document = DocumentExtractor::new(&text);
         Ok(document.parse_in_game_player_info())
     }
@@ -224,65 +601,131 @@ impl Kf2Logger {
         } else {
             Some(players_in_game.clone())
         };
+        kf2_metrics::set_current_players(players_in_game.len());
         self.db_connection
             .log_in_game_players(players_in_game)
             .await?;
         Ok(())
     }
 
-    async fn get_boss_info(&self) -> Result<Boss, Box<dyn Error>> {
-        let form_data = HashMap::from([(
-            String::from("command"),
-            String::from("getall KFGameReplicationInfo BossIndex"),
-        )]);
-        let response = self
+    async fn run_console_command(&mut self, command: &str) -> Result<String, Box<dyn Error>> {
+        let form_data = HashMap::from([(String::from("command"), String::from(command))]);
+        let request = self
             .session
             .post(self.url.console.as_str())
-            .form(&form_data)
-            .send()
+            .form(&form_data);
+        self.fetch("console", request).await
+    }
+
+    async fn get_boss_info(&mut self) -> Result<Boss, Box<dyn Error>> {
+        let text = self
+            .run_console_command("getall KFGameReplicationInfo BossIndex")
             .await?;
-        let text = response.text().await?;
         let document = DocumentExtractor::new(&text);
-        let boss_index = document.parse_current_boss_info()?;
-        Ok(Boss::map(&boss_index)?)
+        let boss_index = document
+            .parse_current_boss_info()
+            .inspect_err(|_| kf2_metrics::parse_failure("console"))?;
+        Ok(Boss::map(&boss_index))
     }
 
-    async fn get_game_session(&self) -> Result<GameInfo, Box<dyn Error>> {
-        let response = self.session.get(self.url.info.as_str()).send().await?;
-        let text = response.text().await?;
+    async fn get_wave_info(&mut self) -> Result<WaveInfo, Box<dyn Error>> {
+        // One request per tick, the results of chained commands share a page
+        let text = self
+            .run_console_command(
+                "getall KFGameReplicationInfo WaveNum\
+                 |getall KFGameReplicationInfo bTraderIsOpen\
+                 |getall KFGameReplicationInfo AIRemaining",
+            )
+            .await?;
+        let extractor = DocumentExtractor::new(&text);
+        let wave_info = || -> Result<WaveInfo, Box<dyn Error>> {
+            Ok(WaveInfo {
+                wave: extractor.parse_wave_num()?,
+                trader_open: extractor.parse_trader_open()?,
+                ai_remaining: extractor.parse_ai_remaining()?,
+            })
+        };
+        wave_info().inspect_err(|_| kf2_metrics::parse_failure("console"))
+    }
+
+    async fn get_weekly_outbreak(&mut self) -> Result<String, Box<dyn Error>> {
+        let text = self
+            .run_console_command("getall KFGameReplicationInfo CurrentWeeklyIndex")
+            .await?;
+        let weekly_index = DocumentExtractor::new(&text)
+            .parse_weekly_index()
+            .inspect_err(|_| kf2_metrics::parse_failure("console"))?;
+        Ok(weekly_outbreak(weekly_index))
+    }
+
+    async fn get_current_objective(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+        let text = self
+            .run_console_command("getall KFGameReplicationInfo CurrentObjective")
+            .await?;
+        DocumentExtractor::new(&text)
+            .parse_current_objective()
+            .inspect_err(|_| kf2_metrics::parse_failure("console"))
+    }
+
+    /// Returns the Versus team of each player by name.
+    async fn get_versus_teams(&mut self) -> Result<HashMap<String, VersusTeam>, Box<dyn Error>> {
+        let text = self
+            .run_console_command("getall KFPlayerReplicationInfo PlayerName")
+            .await?;
+        let names = DocumentExtractor::new(&text).parse_console_results("PlayerName")?;
+        let text = self
+            .run_console_command("getall KFPlayerReplicationInfo Team")
+            .await?;
+        let teams: HashMap<String, String> = DocumentExtractor::new(&text)
+            .parse_console_results("Team")?
+            .into_iter()
+            .collect();
+        Ok(names
+            .into_iter()
+            .filter_map(|(object, name)| {
+                let team = VersusTeam::map(teams.get(&object)?)?;
+                Some((name, team))
+            })
+            .collect())
+    }
+
+    async fn get_game_session(&mut self) -> Result<GameInfo, Box<dyn Error>> {
+        let request = self.session.get(self.url.info.as_str());
+        let text = self.fetch("info", request).await?;
         let document = DocumentExtractor::new(&text);
-        Ok(document.parse_current_map_info()?)
+        document
+            .parse_current_map_info()
+            .inspect_err(|_| kf2_metrics::parse_failure("info"))
     }
 
     pub(crate) async fn log_game_session(&mut self) -> Result<(), Box<dyn Error>> {
         let mut game_info = self.get_game_session().await?;
         game_info.boss = self.get_boss_info().await?;
+        kf2_metrics::set_game(game_info.current_wave, game_info.max_players);
+        self.check_player_count(&game_info);
         if game_info.current_players == 0 {
-            self.game_session = None;
+            self.end_game_session(true);
             return Ok(());
         }
+        match game_info.game_mode {
+            GameMode::Weekly => game_info.weekly_outbreak = Some(self.get_weekly_outbreak().await?),
+            GameMode::Objective => game_info.objective = self.get_current_objective().await?,
+            _ => (),
+        }
         if let Some(game_session) = &mut self.game_session {
-            if game_session.map_name != game_info.map_name
-                || game_session.boss != game_info.boss
-                || game_session.reached_wave > game_info.current_wave
-            {
-                self.game_session = None;
+            if !game_session.is_same_game(&game_info) {
+                self.end_game_session(false);
             }
         }
+        let mut events = Vec::new();
         if let Some(game_session) = &mut self.game_session {
-            let reached_wave = if game_info.current_wave > game_session.reached_wave {
-                game_info.current_wave
-            } else {
-                game_session.reached_wave
-            };
-            let players_at_most = if game_info.current_players > game_session.players_at_most {
-                game_info.current_players
-            } else {
-                game_session.players_at_most
-            };
-            game_session.reached_wave = reached_wave;
-            game_session.players_at_most = players_at_most;
-            game_session.ended_at = Some(chrono::Utc::now().naive_utc());
+            let previous_wave = game_session.current_wave;
+            game_session.update(&game_info);
+            if game_session.status == SessionStatus::InProgress
+                && game_session.current_wave > previous_wave
+            {
+                events.push(game_session.wave_advanced(game_session.current_wave));
+            }
 
             if let Some(players) = &self.in_game_players {
                 if players.iter().any(|p| p.kills > 0) {
@@ -291,6 +734,13 @@ impl Kf2Logger {
                             .log_game_session(game_session.clone())
                             .await?,
                     );
+                    if game_session.status == SessionStatus::New {
+                        kf2_metrics::game_session_started(
+                            &game_session.map_name,
+                            &game_session.difficulty.to_string(),
+                        );
+                        events.push(EventKind::GameStarted);
+                    }
                     game_session.status = SessionStatus::InProgress;
                 } else {
                     info!("Game session not saved to database. Game session exist, but no in game players that have kills.");
@@ -299,25 +749,107 @@ impl Kf2Logger {
                 info!("Game session not saved to database. Game session exist, but no in game players.");
             }
         } else {
-            let game_session = GameSession {
-                db_id: None,
-                max_waves: game_info.max_waves,
-                reached_wave: game_info.current_wave,
-                max_players: game_info.max_players,
-                players_at_most: game_info.current_players,
-                map_name: game_info.map_name,
-                difficulty: game_info.difficulty,
-                game_type: game_info.game_type,
-                boss: game_info.boss,
-                started_at: chrono::Utc::now().naive_utc(),
-                ended_at: None,
-                status: SessionStatus::New,
-            };
-            self.game_session = Some(game_session);
+            self.game_session = Some(GameSession::new(&self.server_id, game_info));
+        }
+        for event in events {
+            self.emit(event);
         }
         Ok(())
     }
 
+    /// Emits an event when the server empties or fills up.
+    fn check_player_count(&mut self, game_info: &GameInfo) {
+        let players = game_info.current_players;
+        let Some(previous) = self.player_count.replace(players) else {
+            return;
+        };
+        if previous > 0 && players == 0 {
+            self.emit(EventKind::ServerEmpty);
+        } else if game_info.max_players > 0
+            && previous < game_info.max_players
+            && players >= game_info.max_players
+        {
+            self.emit(EventKind::ServerFull);
+        }
+    }
+
+    /// The game is won when the boss wave was reached and cleared. A game
+    /// left by everyone is abandoned, any other end is a loss.
+    fn game_outcome(&self, game_session: &GameSession, abandoned: bool) -> GameOutcome {
+        let boss_cleared = game_session.max_waves > 0
+            && game_session.reached_wave > game_session.max_waves
+            && self.wave_session.as_ref().is_some_and(|wave_session| {
+                wave_session.wave > game_session.max_waves && wave_session.ai_remaining == 0
+            });
+        if boss_cleared {
+            GameOutcome::Won
+        } else if abandoned {
+            GameOutcome::Abandoned
+        } else {
+            GameOutcome::Lost
+        }
+    }
+
+    /// Drops the current game. Games that were saved count as finished.
+    fn end_game_session(&mut self, abandoned: bool) {
+        if let Some(game_session) = &self.game_session {
+            if game_session.db_id.is_some() {
+                kf2_metrics::game_session_finished(
+                    &game_session.map_name,
+                    &game_session.difficulty.to_string(),
+                );
+                let outcome = self.game_outcome(game_session, abandoned);
+                self.emit(EventKind::GameEnded { outcome });
+            }
+        }
+        self.game_session = None;
+    }
+
+    pub(crate) async fn log_wave_session(&mut self) -> Result<(), Box<dyn Error>> {
+        let game_session_id = match &self.game_session {
+            Some(game_session) => game_session.db_id,
+            None => None,
+        };
+        let Some(game_session_id) = game_session_id else {
+            self.wave_session = None;
+            return Ok(());
+        };
+        let wave_info = self.get_wave_info().await?;
+
+        if let Some(wave_session) = self.wave_session.as_mut() {
+            if !wave_session.is_same_wave(game_session_id, &wave_info) {
+                // The next wave starts when the trader closes
+                wave_session.ended_at = Some(chrono::Utc::now().naive_utc());
+                if wave_session.db_id.is_some() {
+                    self.db_connection
+                        .log_wave_session(wave_session.clone())
+                        .await?;
+                }
+                info!(
+                    "Wave {} ended, longest stall {}s",
+                    wave_session.wave, wave_session.longest_stall
+                );
+                self.wave_session = None;
+            }
+        }
+
+        let mut wave_session = match self.wave_session.take() {
+            Some(mut wave_session) => {
+                wave_session.update(&wave_info);
+                wave_session
+            }
+            None => WaveSession::new(game_session_id, &wave_info),
+        };
+        wave_session.db_id = Some(
+            self.db_connection
+                .log_wave_session(wave_session.clone())
+                .await?,
+        );
+        wave_session.status = SessionStatus::InProgress;
+        self.wave_session = Some(wave_session);
+        Ok(())
+    }
+
     async fn create_new_players_sessions(&mut self) -> Result<Vec<PlayerSession>, Box<dyn Error>> {
         if self.game_session.is_none()
             && self.unique_players.is_none()
@@ -325,45 +857,53 @@ impl Kf2Logger {
         {
             return Ok(vec![]);
         }
+        let mut versus_teams = HashMap::new();
         if let Some(game_session) = &self.game_session {
             if game_session.db_id.is_none() {
                 return Ok(vec![]);
             }
+            if game_session.game_mode == GameMode::Versus {
+                versus_teams = self.get_versus_teams().await?;
+            }
         }
-        let player_sessions;
         let game_session = self
             .game_session
             .as_ref()
             .ok_or("Game session not found, when trying to build players sessions")?;
         let unique_players = self
             .unique_players
-            .as_mut()
+            .as_ref()
             .ok_or("Unique players not found, when trying to build players sessions")?;
         let in_game_players = self
             .in_game_players
-            .as_mut()
+            .as_ref()
             .ok_or("In game players not found, when trying to build players sessions")?;
 
-        unique_players.sort_by(|a, b| a.name.cmp(&b.name));
-        in_game_players.sort_by(|a, b| a.name.cmp(&b.name));
-
-        if unique_players.len() == in_game_players.len() {
-            player_sessions = unique_players
-                .into_iter()
-                .zip(in_game_players)
-                .map(|(unique_player, in_game_player)| PlayerSession {
-                    db_id: None,
-                    game_session_id: game_session.db_id.expect("Game session id not found!"),
-                    steam_id: unique_player.steam_id,
-                    perk: in_game_player.perk.to_string(),
-                    kills: in_game_player.kills,
-                    started_at: chrono::Utc::now().naive_utc(),
-                    ended_at: chrono::Utc::now().naive_utc(),
-                })
-                .collect()
-        } else {
-            return Err("Unique players and in game players are not the same size".into());
+        let matched_players = match_players(unique_players, in_game_players);
+        let player_count = unique_players.len().max(in_game_players.len());
+        if matched_players.len() < player_count {
+            warn!(
+                "Matched {} of {} players between the players and info pages",
+                matched_players.len(),
+                player_count
+            );
         }
+
+        let player_sessions = matched_players
+            .into_iter()
+            .map(|(unique_player, in_game_player)| PlayerSession {
+                db_id: None,
+                game_session_id: game_session.db_id.expect("Game session id not found!"),
+                steam_id: unique_player.steam_id,
+                perk: in_game_player.perk.to_string(),
+                kills: in_game_player.kills,
+                team: versus_teams
+                    .get(&in_game_player.name)
+                    .map(|team| team.to_string()),
+                started_at: chrono::Utc::now().naive_utc(),
+                ended_at: chrono::Utc::now().naive_utc(),
+            })
+            .collect();
         Ok(player_sessions)
     }
 
@@ -371,6 +911,7 @@ impl Kf2Logger {
         &mut self,
         new_player_sessions: Vec<PlayerSession>,
     ) -> Result<Vec<PlayerSession>, Box<dyn Error>> {
+        self.emit_perk_changes(&new_player_sessions);
         let mut updated_player_sessions = vec![];
         if let Some(old_player_sessions) = self.player_sessions.as_mut() {
             // Add new player sessions to the sessions list
@@ -402,6 +943,7 @@ impl Kf2Logger {
                             old_player_session.ended_at = chrono::Utc::now().naive_utc();
                             old_player_session.kills = new_player_session.kills;
                             old_player_session.perk = new_player_session.perk;
+                            old_player_session.team = new_player_session.team;
                             Some(old_player_session)
                         } else {
                             Some(new_player_session)
@@ -417,6 +959,37 @@ impl Kf2Logger {
         Ok(updated_player_sessions)
     }
 
+    /// Emits a perk change for players that switched perk during the game.
+    fn emit_perk_changes(&self, new_player_sessions: &[PlayerSession]) {
+        let Some(old_player_sessions) = &self.player_sessions else {
+            return;
+        };
+        for old_player_session in old_player_sessions {
+            let Some(new_player_session) = new_player_sessions.iter().find(|new_player_session| {
+                new_player_session.steam_id == old_player_session.steam_id
+                    && new_player_session.game_session_id == old_player_session.game_session_id
+            }) else {
+                continue;
+            };
+            if new_player_session.perk == old_player_session.perk {
+                continue;
+            }
+            let name = self
+                .unique_players
+                .iter()
+                .flatten()
+                .find(|player| player.steam_id == old_player_session.steam_id)
+                .map(|player| player.name.clone())
+                .unwrap_or_default();
+            self.emit(EventKind::PerkChanged {
+                steam_id: old_player_session.steam_id,
+                name,
+                from: old_player_session.perk.clone(),
+                to: new_player_session.perk.clone(),
+            });
+        }
+    }
+
     pub(crate) async fn log_player_sessions(&mut self) -> Result<(), Box<dyn Error>> {
         let player_sessions = self.create_new_players_sessions().await?;
         if player_sessions.is_empty() {
@@ -432,3 +1005,164 @@ impl Kf2Logger {
         Ok(())
     }
 }
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn current_game(&self) -> Option<&GameSession> {
+        self.game_session.as_ref()
+    }
+
+    /// Events of the tracker. Sinks subscribe to this.
+    pub(crate) fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub(crate) fn current_players(&self) -> &[PlayerInGame] {
+        self.in_game_players.as_deref().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(super) fn store(&self) -> &S {
+        &self.db_connection
+    }
+}
+
+#[cfg(test)]
+mod tests_game_session {
+    use super::*;
+
+    fn game_info(game_mode: GameMode, current_wave: u16) -> GameInfo {
+        GameInfo {
+            max_waves: 7,
+            current_wave,
+            max_players: 6,
+            current_players: 2,
+            map_name: String::from("KF-Outpost"),
+            difficulty: KfDifficulty::Hard,
+            game_type: game_mode.to_string(),
+            game_mode,
+            boss: Boss::HansVolter,
+            weekly_outbreak: None,
+            objective: None,
+        }
+    }
+
+    #[test]
+    fn test_is_same_game() {
+        let game_session = GameSession::new("kf2-eu-1", game_info(GameMode::Survival, 3));
+        assert!(game_session.is_same_game(&game_info(GameMode::Survival, 3)));
+        assert!(game_session.is_same_game(&game_info(GameMode::Survival, 4)));
+        assert!(!game_session.is_same_game(&game_info(GameMode::Survival, 1)));
+        assert!(!game_session.is_same_game(&game_info(GameMode::Endless, 3)));
+
+        let mut other_map = game_info(GameMode::Survival, 3);
+        other_map.map_name = String::from("KF-BurningParis");
+        assert!(!game_session.is_same_game(&other_map));
+        let mut other_difficulty = game_info(GameMode::Survival, 3);
+        other_difficulty.difficulty = KfDifficulty::Suicidal;
+        assert!(!game_session.is_same_game(&other_difficulty));
+        let mut other_boss = game_info(GameMode::Survival, 3);
+        other_boss.boss = Boss::Abomination;
+        assert!(!game_session.is_same_game(&other_boss));
+    }
+
+    #[test]
+    fn test_update_versus_rounds() {
+        let mut game_session = GameSession::new("kf2-eu-1", game_info(GameMode::Versus, 4));
+        game_session.update(&game_info(GameMode::Versus, 5));
+        assert_eq!(game_session.versus_round, 1);
+
+        // Teams swap sides and the second round starts from the first wave
+        assert!(game_session.is_same_game(&game_info(GameMode::Versus, 1)));
+        game_session.update(&game_info(GameMode::Versus, 1));
+        assert_eq!(game_session.versus_round, 2);
+        assert_eq!(game_session.current_wave, 1);
+        assert_eq!(game_session.reached_wave, 5);
+        assert!(!game_session.is_same_game(&game_info(GameMode::Versus, 0)));
+    }
+
+    #[test]
+    fn test_update_progress() {
+        let mut game_session = GameSession::new("kf2-eu-1", game_info(GameMode::Objective, 2));
+        let mut next = game_info(GameMode::Objective, 3);
+        next.current_players = 5;
+        next.objective = Some(String::from("Defend the area"));
+        game_session.update(&next);
+        game_session.update(&next);
+        next.current_players = 4;
+        next.objective = Some(String::from("Deliver the cargo"));
+        game_session.update(&next);
+        assert_eq!(game_session.current_wave, 3);
+        assert_eq!(game_session.players_at_most, 5);
+        assert_eq!(game_session.objectives, 2);
+        assert_eq!(game_session.versus_round, 1);
+        assert!(game_session.ended_at.is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests_wave_session {
+    use super::*;
+
+    fn wave_info(wave: u16, trader_open: bool, ai_remaining: u32) -> WaveInfo {
+        WaveInfo {
+            wave,
+            trader_open,
+            ai_remaining,
+        }
+    }
+
+    #[test]
+    fn test_trader_open_close() {
+        let mut wave_session = WaveSession::new(1, &wave_info(2, false, 40));
+        assert!(wave_session.trader_opened_at.is_none());
+        wave_session.update(&wave_info(2, false, 0));
+        assert!(wave_session.trader_opened_at.is_none());
+        wave_session.update(&wave_info(2, true, 0));
+        let opened_at = wave_session.trader_opened_at;
+        assert!(opened_at.is_some());
+        wave_session.update(&wave_info(2, true, 0));
+        assert_eq!(wave_session.trader_opened_at, opened_at);
+
+        // The trader closing starts the next wave
+        assert!(wave_session.is_same_wave(1, &wave_info(2, true, 0)));
+        assert!(!wave_session.is_same_wave(1, &wave_info(3, false, 48)));
+        let next = WaveSession::new(1, &wave_info(3, false, 48));
+        assert!(next.trader_opened_at.is_none());
+    }
+
+    #[test]
+    fn test_wave_stall() {
+        let mut wave_session = WaveSession::new(1, &wave_info(4, false, 3));
+        wave_session.ai_remaining_changed_at -= chrono::Duration::seconds(90);
+        wave_session.update(&wave_info(4, false, 3));
+        assert!(wave_session.longest_stall >= 90);
+
+        // Killing a zed ends the stall but keeps the longest one
+        wave_session.update(&wave_info(4, false, 2));
+        assert_eq!(wave_session.ai_remaining, 2);
+        wave_session.ai_remaining_changed_at -= chrono::Duration::seconds(30);
+        wave_session.update(&wave_info(4, false, 2));
+        assert!(wave_session.longest_stall >= 90 && wave_session.longest_stall < 120);
+    }
+
+    #[test]
+    fn test_no_stall_with_trader_open() {
+        let mut wave_session = WaveSession::new(1, &wave_info(4, true, 0));
+        wave_session.ai_remaining_changed_at -= chrono::Duration::seconds(60);
+        wave_session.update(&wave_info(4, true, 0));
+        assert_eq!(wave_session.longest_stall, 0);
+    }
+
+    #[test]
+    fn test_restart() {
+        let wave_session = WaveSession::new(1, &wave_info(5, false, 20));
+        // The restarted server logs a new game from the first wave
+        assert!(!wave_session.is_same_wave(2, &wave_info(5, false, 20)));
+        assert!(!wave_session.is_same_wave(1, &wave_info(1, false, 20)));
+        let restarted = WaveSession::new(2, &wave_info(1, false, 20));
+        assert_eq!(restarted.longest_stall, 0);
+        assert_eq!(restarted.status, SessionStatus::New);
+    }
+}
@@ -0,0 +1,317 @@
+use super::control::ControlAction;
+use super::Kf2Logger;
+use crate::args::Kf2AfkArgs;
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_log::matching::match_players;
+use chrono::NaiveDateTime;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+
+const SOURCE: &str = "afk";
+
+/// What is done to AFK players when the server is full.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AfkAction {
+    /// Only record the AFK periods.
+    #[default]
+    Record,
+    Warn,
+    Kick,
+}
+
+impl FromStr for AfkAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "record" => Ok(AfkAction::Record),
+            "warn" => Ok(AfkAction::Warn),
+            "kick" => Ok(AfkAction::Kick),
+            _ => Err(format!("Unknown AFK action {}", s)),
+        }
+    }
+}
+
+/// A finished AFK period of a player session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AfkPeriod {
+    pub(crate) player_session_id: u32,
+    pub(crate) started_at: NaiveDateTime,
+    pub(crate) ended_at: NaiveDateTime,
+}
+
+/// A player on the info page, matched to the players page and the session.
+#[derive(Debug)]
+struct AfkPlayer {
+    steam_id: u64,
+    player_session_id: Option<u32>,
+    name: String,
+    kills: u32,
+    dosh: u32,
+    health: u32,
+}
+
+#[derive(Debug)]
+struct AfkState {
+    name: String,
+    player_session_id: Option<u32>,
+    values: (u32, u32, u32),
+    changed_wave: u16,
+    changed_at: NaiveDateTime,
+    afk_since: Option<NaiveDateTime>,
+    handled: bool,
+}
+
+impl AfkState {
+    fn end(&mut self, now: NaiveDateTime) -> Option<AfkPeriod> {
+        let started_at = self.afk_since.take()?;
+        self.handled = false;
+        Some(AfkPeriod {
+            player_session_id: self.player_session_id?,
+            started_at,
+            ended_at: now,
+        })
+    }
+}
+
+/// Flags players whose kills, dosh and health stay the same for the
+/// configured number of waves. The AFK period starts from the last change
+/// and ends when the player does something, leaves or the game ends.
+pub(crate) struct AfkTracker {
+    args: Kf2AfkArgs,
+    game_session_id: Option<u32>,
+    players: HashMap<u64, AfkState>,
+}
+
+impl AfkTracker {
+    pub(crate) fn new(args: Kf2AfkArgs) -> Self {
+        Self {
+            args,
+            game_session_id: None,
+            players: HashMap::new(),
+        }
+    }
+
+    /// Returns the periods that ended. Periods of players without a player
+    /// session cannot be recorded and are left out.
+    fn check(
+        &mut self,
+        game_session_id: Option<u32>,
+        wave: u16,
+        players: &[AfkPlayer],
+        now: NaiveDateTime,
+    ) -> Vec<AfkPeriod> {
+        let mut ended = Vec::new();
+        if game_session_id != self.game_session_id {
+            ended.extend(self.players.values_mut().filter_map(|s| s.end(now)));
+            self.players.clear();
+            self.game_session_id = game_session_id;
+        }
+        if game_session_id.is_none() {
+            return ended;
+        }
+        self.players.retain(|steam_id, state| {
+            let present = players.iter().any(|p| p.steam_id == *steam_id);
+            if !present {
+                ended.extend(state.end(now));
+            }
+            present
+        });
+        for player in players {
+            let values = (player.kills, player.dosh, player.health);
+            let state = self.players.entry(player.steam_id).or_insert(AfkState {
+                name: player.name.clone(),
+                player_session_id: None,
+                values,
+                changed_wave: wave,
+                changed_at: now,
+                afk_since: None,
+                handled: false,
+            });
+            state.player_session_id = player.player_session_id.or(state.player_session_id);
+            if state.values != values {
+                state.values = values;
+                state.changed_wave = wave;
+                state.changed_at = now;
+                if state.afk_since.is_some() {
+                    info!("{} is no longer AFK", state.name);
+                }
+                ended.extend(state.end(now));
+            } else if state.afk_since.is_none()
+                && wave >= state.changed_wave.saturating_add(self.args.waves)
+            {
+                info!("{} is AFK", state.name);
+                state.afk_since = Some(state.changed_at);
+            }
+        }
+        ended
+    }
+
+    /// AFK players not yet warned or kicked during their current period.
+    fn take_unhandled(&mut self) -> Vec<(u64, String)> {
+        self.players
+            .iter_mut()
+            .filter(|(_, state)| state.afk_since.is_some() && !state.handled)
+            .map(|(steam_id, state)| {
+                state.handled = true;
+                (*steam_id, state.name.clone())
+            })
+            .collect()
+    }
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn set_afk(&mut self, args: Kf2AfkArgs) {
+        self.afk = Some(AfkTracker::new(args));
+    }
+
+    fn afk_players(&self, game_session_id: u32) -> Vec<AfkPlayer> {
+        let players_info = self.unique_players.as_deref().unwrap_or_default();
+        match_players(players_info, self.current_players())
+            .into_iter()
+            .map(|(player_info, player_in_game)| AfkPlayer {
+                steam_id: player_info.steam_id,
+                player_session_id: self
+                    .player_sessions
+                    .iter()
+                    .flatten()
+                    .find(|session| {
+                        session.steam_id == player_info.steam_id
+                            && session.game_session_id == game_session_id
+                    })
+                    .and_then(|session| session.db_id),
+                name: player_in_game.name,
+                kills: player_in_game.kills,
+                dosh: player_in_game.dosh,
+                health: player_in_game.health,
+            })
+            .collect()
+    }
+
+    /// Records AFK periods and, when the server is full, warns or kicks AFK
+    /// players once per period.
+    pub(crate) async fn track_afk(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.afk.is_none() {
+            return Ok(());
+        }
+        let game = self
+            .game_session
+            .as_ref()
+            .and_then(|game| Some((game.db_id?, game.current_wave, game.max_players)));
+        let players = match game {
+            Some((game_session_id, ..)) => self.afk_players(game_session_id),
+            None => Vec::new(),
+        };
+        let now = chrono::Utc::now().naive_utc();
+        let Some(afk) = &mut self.afk else {
+            return Ok(());
+        };
+        let periods = afk.check(
+            game.map(|(id, ..)| id),
+            game.map_or(0, |(_, wave, _)| wave),
+            &players,
+            now,
+        );
+        let full = game.is_some_and(|(_, _, max_players)| {
+            max_players > 0 && players.len() >= max_players as usize
+        });
+        let action = afk.args.action;
+        let to_handle = if full && action != AfkAction::Record {
+            afk.take_unhandled()
+        } else {
+            Vec::new()
+        };
+        for period in periods {
+            self.db_connection.log_afk_period(period).await?;
+        }
+        for (steam_id, name) in to_handle {
+            let control = match action {
+                AfkAction::Warn => ControlAction::Broadcast {
+                    message: format!("{}, you are AFK while the server is full.", name),
+                },
+                AfkAction::Kick => ControlAction::Kick { steam_id },
+                AfkAction::Record => continue,
+            };
+            if let Err(e) = self.control(control, SOURCE).await {
+                warn!("Could not handle AFK player {}: {}", name, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_afk {
+    use super::*;
+
+    fn at(minutes: i64) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(18, 0, 0)
+            .unwrap()
+            + chrono::Duration::minutes(minutes)
+    }
+
+    fn player(steam_id: u64, kills: u32) -> AfkPlayer {
+        AfkPlayer {
+            steam_id,
+            player_session_id: Some(steam_id as u32 + 100),
+            name: format!("player{}", steam_id),
+            kills,
+            dosh: 300,
+            health: 100,
+        }
+    }
+
+    fn tracker() -> AfkTracker {
+        AfkTracker::new(Kf2AfkArgs {
+            waves: 2,
+            action: AfkAction::Kick,
+        })
+    }
+
+    #[test]
+    fn test_afk_period() {
+        let mut afk = tracker();
+        assert!(afk
+            .check(Some(1), 1, &[player(1, 5), player(2, 5)], at(0))
+            .is_empty());
+        assert!(afk
+            .check(Some(1), 2, &[player(1, 9), player(2, 5)], at(5))
+            .is_empty());
+        assert!(afk.take_unhandled().is_empty());
+        afk.check(Some(1), 3, &[player(1, 14), player(2, 5)], at(10));
+        assert_eq!(afk.take_unhandled(), vec![(2, String::from("player2"))]);
+        // Handled once per period
+        afk.check(Some(1), 4, &[player(1, 20), player(2, 5)], at(15));
+        assert!(afk.take_unhandled().is_empty());
+
+        let periods = afk.check(Some(1), 4, &[player(1, 20), player(2, 6)], at(20));
+        assert_eq!(
+            periods,
+            vec![AfkPeriod {
+                player_session_id: 102,
+                started_at: at(0),
+                ended_at: at(20),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_afk_period_ends_with_game() {
+        let mut afk = tracker();
+        afk.check(Some(1), 1, &[player(1, 5)], at(0));
+        afk.check(Some(1), 3, &[player(1, 5)], at(10));
+        // Leaving ends the period
+        let periods = afk.check(Some(1), 3, &[], at(12));
+        assert_eq!(periods.len(), 1);
+
+        afk.check(Some(1), 3, &[player(1, 5)], at(13));
+        afk.check(Some(1), 5, &[player(1, 5)], at(20));
+        let periods = afk.check(Some(2), 0, &[player(1, 5)], at(25));
+        assert_eq!(periods[0].started_at, at(13));
+        assert_eq!(periods[0].ended_at, at(25));
+    }
+}
@@ -0,0 +1,344 @@
+use super::control::ControlAction;
+use super::Kf2Logger;
+use crate::args::Kf2AnnounceArgs;
+use crate::kf2_database::store::Kf2Store;
+use chrono::{NaiveDateTime, NaiveTime};
+use log::debug;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+
+const SOURCE: &str = "announce";
+
+/// Placeholders a message can use, e.g. `Now playing {map}`.
+const PLACEHOLDERS: [&str; 9] = [
+    "map",
+    "game_mode",
+    "difficulty",
+    "wave",
+    "max_waves",
+    "players",
+    "max_players",
+    "top_player",
+    "top_kills",
+];
+
+/// How late a message with a fixed time can still be sent, e.g. when a
+/// tick is delayed by a slow webadmin.
+const AT_WINDOW: chrono::Duration = chrono::Duration::minutes(2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Schedule {
+    /// Once a day at the server's local time.
+    At(NaiveTime),
+    Every(Duration),
+}
+
+#[derive(Debug)]
+struct Announcement {
+    schedule: Schedule,
+    template: String,
+    last_sent: Option<NaiveDateTime>,
+}
+
+impl Announcement {
+    /// Messages are only sent when players are present. A repeating message
+    /// is sent as soon as there are players and then once per interval.
+    fn is_due(&self, now: NaiveDateTime) -> bool {
+        match &self.schedule {
+            Schedule::At(time) => {
+                let at = now.date().and_time(*time);
+                now >= at && now - at < AT_WINDOW && self.last_sent.is_none_or(|last| last < at)
+            }
+            Schedule::Every(interval) => self.last_sent.is_none_or(|last| {
+                (now - last)
+                    .to_std()
+                    .is_ok_and(|elapsed| elapsed >= *interval)
+            }),
+        }
+    }
+}
+
+fn parse_schedule(schedule: &str, value: &str) -> Result<Schedule, String> {
+    match schedule {
+        "at" => NaiveTime::parse_from_str(value, "%H:%M")
+            .map(Schedule::At)
+            .map_err(|_| format!("Invalid time {}", value)),
+        "every" => {
+            let invalid = || format!("Invalid interval {}", value);
+            let split = value.len().saturating_sub(1);
+            let (count, unit) = (value.get(..split), value.get(split..));
+            let count: u64 = count
+                .and_then(|count| count.parse().ok())
+                .ok_or_else(invalid)?;
+            let seconds = match unit {
+                Some("s") => Some(count),
+                Some("m") => count.checked_mul(60),
+                Some("h") => count.checked_mul(60 * 60),
+                _ => None,
+            }
+            .ok_or_else(invalid)?;
+            match seconds {
+                0 => Err(invalid()),
+                seconds => Ok(Schedule::Every(Duration::from_secs(seconds))),
+            }
+        }
+        _ => Err(format!("Unknown schedule {}", schedule)),
+    }
+}
+
+/// Names of the `{placeholders}` in the template.
+pub(super) fn placeholders(template: &str) -> Vec<&str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}'))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Fills in the placeholders, or returns `None` when a value is missing.
+/// Values are copied as they are, so a player named `{map}` stays so.
+pub(super) fn render(template: &str, values: &HashMap<&str, String>) -> Option<String> {
+    let mut message = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        message.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.split_once('}') {
+            Some((name, tail)) if !name.contains('{') => {
+                message.push_str(values.get(name)?);
+                rest = tail;
+            }
+            _ => {
+                message.push('{');
+                rest = after;
+            }
+        }
+    }
+    message.push_str(rest);
+    Some(message)
+}
+
+/// Reads announcements, one per line: `every 15m <message>` or
+/// `at 05:50 <message>`. Intervals are given in `s`, `m` or `h`. `#` starts
+/// a comment line.
+fn parse_announcements(text: &str) -> Result<Vec<Announcement>, String> {
+    let mut announcements = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |e: String| format!("Announcement on line {}: {}", number + 1, e);
+        let mut parts = line.splitn(3, char::is_whitespace);
+        let (Some(schedule), Some(value), Some(template)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(error(String::from("Expected a schedule and a message")));
+        };
+        let template = template.trim().to_string();
+        if let Some(name) = placeholders(&template)
+            .into_iter()
+            .find(|name| !PLACEHOLDERS.contains(name))
+        {
+            return Err(error(format!("Unknown placeholder {{{}}}", name)));
+        }
+        announcements.push(Announcement {
+            schedule: parse_schedule(schedule, value).map_err(error)?,
+            template,
+            last_sent: None,
+        });
+    }
+    Ok(announcements)
+}
+
+/// Broadcasts the configured messages while players are on the server.
+pub(crate) struct Announcer {
+    announcements: Vec<Announcement>,
+}
+
+impl Announcer {
+    pub(crate) fn new(args: Kf2AnnounceArgs) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(&args.path)
+            .map_err(|e| format!("Could not read {}: {}", args.path.display(), e))?;
+        Ok(Self {
+            announcements: parse_announcements(&text)?,
+        })
+    }
+
+    /// Indexes and templates of the due messages. They stay due until
+    /// marked sent.
+    fn due(&self, now: NaiveDateTime) -> Vec<(usize, String)> {
+        self.announcements
+            .iter()
+            .enumerate()
+            .filter(|(_, announcement)| announcement.is_due(now))
+            .map(|(index, announcement)| (index, announcement.template.clone()))
+            .collect()
+    }
+
+    fn mark_sent(&mut self, index: usize, now: NaiveDateTime) {
+        if let Some(announcement) = self.announcements.get_mut(index) {
+            announcement.last_sent = Some(now);
+        }
+    }
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn set_announcements(
+        &mut self,
+        args: Kf2AnnounceArgs,
+    ) -> Result<(), Box<dyn Error>> {
+        self.announcer = Some(Announcer::new(args)?);
+        Ok(())
+    }
+
+    /// Values of the placeholders from the game and players scraped on this
+    /// tick.
+    fn announcement_values(&self) -> HashMap<&'static str, String> {
+        let mut values = HashMap::new();
+        let players = self.current_players();
+        values.insert("players", players.len().to_string());
+        if let Some(top) = players.iter().max_by_key(|player| player.kills) {
+            values.insert("top_player", top.name.clone());
+            values.insert("top_kills", top.kills.to_string());
+        }
+        if let Some(game) = self.current_game() {
+            values.insert("map", game.map_name.clone());
+            values.insert("game_mode", game.game_mode.to_string());
+            values.insert("difficulty", game.difficulty.to_string());
+            values.insert("wave", game.current_wave.to_string());
+            values.insert("max_waves", game.max_waves.to_string());
+            values.insert("max_players", game.max_players.to_string());
+        }
+        values
+    }
+
+    /// Broadcasts the due announcements. Nothing is sent to an empty server.
+    pub(crate) async fn announce(&mut self) {
+        if self.current_players().is_empty() {
+            return;
+        }
+        let values = self.announcement_values();
+        let Some(announcer) = &self.announcer else {
+            return;
+        };
+        let now = chrono::Local::now().naive_local();
+        for (index, template) in announcer.due(now) {
+            // A message missing a value is tried again on the next tick
+            let Some(message) = render(&template, &values) else {
+                debug!("Announcement skipped, no values for {}", template);
+                continue;
+            };
+            // Failures are logged and recorded by control
+            let sent = self
+                .control(ControlAction::Broadcast { message }, SOURCE)
+                .await
+                .is_ok();
+            if let Some(announcer) = self.announcer.as_mut().filter(|_| sent) {
+                announcer.mark_sent(index, now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_announce {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_announcements() {
+        let announcements = parse_announcements(
+            "# Rules\n\
+             every 15m Be nice, no team killing\n\
+             at 05:50 Server restarts at 06:00\n",
+        )
+        .unwrap();
+        assert_eq!(
+            announcements[0].schedule,
+            Schedule::Every(Duration::from_secs(900))
+        );
+        assert_eq!(announcements[0].template, "Be nice, no team killing");
+        assert_eq!(
+            announcements[1].schedule,
+            Schedule::At(NaiveTime::from_hms_opt(5, 50, 0).unwrap())
+        );
+
+        assert!(parse_announcements("every 0m Nothing").is_err());
+        assert!(parse_announcements("daily 05:50 Restart").is_err());
+        assert!(parse_announcements("every 5m").is_err());
+        assert_eq!(
+            parse_schedule("every", "18446744073709551615h"),
+            Err(String::from("Invalid interval 18446744073709551615h"))
+        );
+        let error = parse_announcements("\nevery 5m Now on {mapname}").unwrap_err();
+        assert_eq!(
+            error,
+            "Announcement on line 2: Unknown placeholder {mapname}"
+        );
+    }
+
+    /// Templates of the due messages, which are marked sent.
+    fn take_due(announcer: &mut Announcer, now: NaiveDateTime) -> Vec<String> {
+        let due = announcer.due(now);
+        for (index, _) in &due {
+            announcer.mark_sent(*index, now);
+        }
+        due.into_iter().map(|(_, template)| template).collect()
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut announcer = Announcer {
+            announcements: parse_announcements("every 15m Rules\nat 05:50 Restart").unwrap(),
+        };
+        assert_eq!(take_due(&mut announcer, at(5, 40)), vec!["Rules"]);
+        assert!(take_due(&mut announcer, at(5, 45)).is_empty());
+        assert_eq!(take_due(&mut announcer, at(5, 51)), vec!["Restart"]);
+        assert!(take_due(&mut announcer, at(5, 52)).is_empty());
+        assert_eq!(take_due(&mut announcer, at(5, 55)), vec!["Rules"]);
+        // Past the window the fixed time waits for the next day
+        let mut announcer = Announcer {
+            announcements: parse_announcements("at 05:50 Restart").unwrap(),
+        };
+        assert!(take_due(&mut announcer, at(6, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_due_until_sent() {
+        let mut announcer = Announcer {
+            announcements: parse_announcements("every 15m Rules\nat 05:50 Restart").unwrap(),
+        };
+        assert_eq!(announcer.due(at(5, 50)).len(), 2);
+        // A message that was not sent is still due on the next tick
+        announcer.mark_sent(0, at(5, 50));
+        assert_eq!(announcer.due(at(5, 51)), vec![(1, String::from("Restart"))]);
+    }
+
+    #[test]
+    fn test_render() {
+        let values = HashMap::from([
+            ("map", String::from("KF-Outpost")),
+            ("top_player", String::from("koira")),
+            ("top_kills", String::from("42")),
+        ]);
+        assert_eq!(
+            render("{map}: {top_player} leads with {top_kills} kills", &values).as_deref(),
+            Some("KF-Outpost: koira leads with 42 kills")
+        );
+        assert_eq!(render("Wave {wave}", &values), None);
+        assert_eq!(
+            render("{ {map} }", &values).as_deref(),
+            Some("{ KF-Outpost }")
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use super::control::ControlAction;
+use super::Kf2Logger;
+use crate::args::Kf2BanSyncArgs;
+use crate::kf2_database::management::KfDbManager;
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_scrape::models::{normalize_unique_net_id, unique_net_id_steam_id, BanInfo};
+use crate::kf2_scrape::parse::DocumentExtractor;
+use log::{info, warn};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const SOURCE: &str = "ban_sync";
+
+/// Bans shared by all servers.
+enum BanSource {
+    /// One ban per line, a Steam id or a unique net id followed by an
+    /// optional name. `#` starts a comment.
+    File(PathBuf),
+    /// The `bans` table of a database the servers log into, without the
+    /// rows of this server.
+    Database(KfDbManager),
+}
+
+impl BanSource {
+    fn load(&self, server: &str) -> Result<Vec<BanInfo>, Box<dyn Error>> {
+        match self {
+            BanSource::File(path) => Ok(parse_ban_file(&fs::read_to_string(path)?)),
+            BanSource::Database(db) => db.load_bans(server),
+        }
+    }
+}
+
+fn parse_ban_file(text: &str) -> Vec<BanInfo> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (id, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            if id.is_empty() {
+                return None;
+            }
+            let ban = match id.parse() {
+                Ok(steam_id) => BanInfo::from_steam_id(steam_id),
+                Err(_) if id.starts_with("0x") || id.starts_with("0X") => BanInfo {
+                    unique_net_id: normalize_unique_net_id(id),
+                    steam_id: unique_net_id_steam_id(id),
+                    name: None,
+                },
+                Err(_) => {
+                    warn!("Invalid ban {}", id);
+                    return None;
+                }
+            };
+            let name = name.trim();
+            Some(BanInfo {
+                name: Some(name.to_string()).filter(|name| !name.is_empty()),
+                ..ban
+            })
+        })
+        .collect()
+}
+
+/// Keeps the bans page of the server in the `bans` table and adds the
+/// shared bans it is missing. Lifting a ban has to be done on the shared
+/// source too, or the next sync adds it back.
+pub(crate) struct BanSync {
+    interval: Duration,
+    sources: Vec<BanSource>,
+    ips: bool,
+    last_sync: Option<Instant>,
+    /// Addresses denied by the sync, loaded from the store on the first
+    /// sync. The policy page is not read back.
+    banned_ips: Option<HashSet<IpAddr>>,
+}
+
+impl BanSync {
+    pub(crate) fn new(args: Kf2BanSyncArgs) -> Result<Self, Box<dyn Error>> {
+        let mut sources = Vec::new();
+        if let Some(path) = args.file {
+            sources.push(BanSource::File(path));
+        }
+        if let Some(db_args) = args.database {
+            sources.push(BanSource::Database(KfDbManager::new_session(db_args)?));
+        }
+        Ok(Self {
+            interval: args.interval,
+            sources,
+            ips: args.ips,
+            last_sync: None,
+            banned_ips: None,
+        })
+    }
+
+    fn is_due(&self) -> bool {
+        self.last_sync
+            .is_none_or(|last_sync| last_sync.elapsed() >= self.interval)
+    }
+
+    /// Bans of every source, each unique net id once.
+    fn shared_bans(&self, server: &str) -> Vec<BanInfo> {
+        let mut bans: Vec<BanInfo> = Vec::new();
+        for source in &self.sources {
+            match source.load(server) {
+                Ok(source_bans) => {
+                    for ban in source_bans {
+                        if !bans.iter().any(|b| same_id(b, &ban)) {
+                            bans.push(ban);
+                        }
+                    }
+                }
+                Err(e) => warn!("Could not read shared bans: {}", e),
+            }
+        }
+        bans
+    }
+}
+
+fn same_id(a: &BanInfo, b: &BanInfo) -> bool {
+    a.unique_net_id.eq_ignore_ascii_case(&b.unique_net_id)
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn set_ban_sync(&mut self, args: Kf2BanSyncArgs) -> Result<(), Box<dyn Error>> {
+        self.ban_sync = Some(BanSync::new(args)?);
+        Ok(())
+    }
+
+    async fn get_bans(&mut self) -> Result<Vec<BanInfo>, Box<dyn Error>> {
+        let request = self.session.get(self.url.bans.as_str());
+        let text = self.fetch("bans", request).await?;
+        DocumentExtractor::new(&text).parse_bans()
+    }
+
+    /// Runs once per interval. Stores the bans page and adds the shared bans
+    /// missing from it, and the addresses of the banned players when asked.
+    pub(crate) async fn sync_bans(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(ban_sync) = &mut self.ban_sync else {
+            return Ok(());
+        };
+        if !ban_sync.is_due() {
+            return Ok(());
+        }
+        ban_sync.last_sync = Some(Instant::now());
+        let shared = ban_sync.shared_bans(&self.server_id);
+        let ips = ban_sync.ips;
+
+        let mut server_bans = self.get_bans().await?;
+        let missing: Vec<BanInfo> = shared
+            .iter()
+            .filter(|ban| !server_bans.iter().any(|b| same_id(b, ban)))
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            info!("Adding {} shared bans", missing.len());
+        }
+        let mut added = false;
+        for ban in missing {
+            let action = ControlAction::BanId {
+                unique_net_id: ban.unique_net_id,
+            };
+            added |= self.control(action, SOURCE).await.is_ok();
+        }
+        if added {
+            server_bans = self.get_bans().await?;
+        }
+        let server = self.server_id.clone();
+        self.db_connection.log_bans(&server, server_bans).await?;
+
+        if ips {
+            for steam_id in shared.iter().filter_map(|ban| ban.steam_id) {
+                self.ban_player_ips(&server, steam_id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn ban_player_ips(&mut self, server: &str, steam_id: u64) -> Result<(), Box<dyn Error>> {
+        let loaded = self
+            .ban_sync
+            .as_ref()
+            .is_some_and(|ban_sync| ban_sync.banned_ips.is_some());
+        if !loaded {
+            let banned_ips = self.db_connection.ip_bans(server).await?;
+            if let Some(ban_sync) = &mut self.ban_sync {
+                ban_sync.banned_ips = Some(banned_ips.into_iter().collect());
+            }
+        }
+        for ip in self.db_connection.player_ip_addresses(steam_id).await? {
+            let banned = self.ban_sync.as_ref().is_some_and(|ban_sync| {
+                ban_sync
+                    .banned_ips
+                    .as_ref()
+                    .is_some_and(|banned_ips| banned_ips.contains(&ip))
+            });
+            if banned {
+                continue;
+            }
+            if self
+                .control(ControlAction::BanIp { ip }, SOURCE)
+                .await
+                .is_ok()
+            {
+                self.db_connection.log_ip_ban(server, ip).await?;
+                if let Some(banned_ips) = self
+                    .ban_sync
+                    .as_mut()
+                    .and_then(|ban_sync| ban_sync.banned_ips.as_mut())
+                {
+                    banned_ips.insert(ip);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_bans {
+    use super::*;
+
+    #[test]
+    fn test_parse_ban_file() {
+        let bans = parse_ban_file(
+            "# Griefers\n\
+             76561198039614177 koira  # team killing\n\
+             \n\
+             0x00000000deadbeef\n\
+             koira\n",
+        );
+        assert_eq!(
+            bans,
+            vec![
+                BanInfo {
+                    unique_net_id: String::from("0x0110000104BAC2E1"),
+                    steam_id: Some(76561198039614177),
+                    name: Some(String::from("koira")),
+                },
+                BanInfo {
+                    unique_net_id: String::from("0x00000000DEADBEEF"),
+                    steam_id: None,
+                    name: None,
+                },
+            ]
+        );
+    }
+}
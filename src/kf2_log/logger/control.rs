@@ -0,0 +1,243 @@
+use super::Kf2Logger;
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_scrape::models::{GameMode, KfDifficulty};
+use log::{error, info};
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::IpAddr;
+
+/// Something an admin can do to the server through the webadmin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ControlAction {
+    /// Unset game type and difficulty keep the ones of the current game.
+    ChangeMap {
+        map: String,
+        game_type: Option<String>,
+        difficulty: Option<KfDifficulty>,
+    },
+    /// Starts the current map over with the same game type and difficulty.
+    RestartMap,
+    Broadcast {
+        message: String,
+    },
+    Kick {
+        steam_id: u64,
+    },
+    Ban {
+        steam_id: u64,
+    },
+    /// Adds the unique net id to the bans page, also for players who are
+    /// not on the server.
+    BanId {
+        unique_net_id: String,
+    },
+    /// Adds a deny policy for the address.
+    BanIp {
+        ip: IpAddr,
+    },
+}
+
+impl ControlAction {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ControlAction::ChangeMap { .. } => "change_map",
+            ControlAction::RestartMap => "restart_map",
+            ControlAction::Broadcast { .. } => "broadcast",
+            ControlAction::Kick { .. } => "kick",
+            ControlAction::Ban { .. } => "ban",
+            ControlAction::BanId { .. } => "ban_id",
+            ControlAction::BanIp { .. } => "ban_ip",
+        }
+    }
+
+    fn target(&self) -> Option<String> {
+        match self {
+            ControlAction::ChangeMap {
+                map,
+                game_type,
+                difficulty,
+            } => {
+                let mut target = vec![map.clone()];
+                target.extend(game_type.clone());
+                target.extend(difficulty.as_ref().map(KfDifficulty::to_string));
+                Some(target.join(", "))
+            }
+            ControlAction::RestartMap => None,
+            ControlAction::Broadcast { message } => Some(message.clone()),
+            ControlAction::Kick { steam_id } | ControlAction::Ban { steam_id } => {
+                Some(steam_id.to_string())
+            }
+            ControlAction::BanId { unique_net_id } => Some(unique_net_id.clone()),
+            ControlAction::BanIp { ip } => Some(ip.to_string()),
+        }
+    }
+}
+
+/// Row of the audit table. `source` tells who asked for the action, e.g.
+/// `cli`.
+#[derive(Debug, Clone)]
+pub(crate) struct ControlRecord {
+    pub(crate) action: &'static str,
+    pub(crate) target: Option<String>,
+    pub(crate) source: String,
+    pub(crate) error: Option<String>,
+    pub(crate) created: chrono::NaiveDateTime,
+}
+
+/// Game info class of a game type given by the user. Full class names are
+/// passed through for modded game types.
+pub(crate) fn game_type_class(game_type: &str) -> Result<String, String> {
+    if game_type.contains('.') {
+        return Ok(game_type.to_string());
+    }
+    let game_mode = match game_type.to_lowercase().as_str() {
+        "survival" => GameMode::Survival,
+        "endless" => GameMode::Endless,
+        "weekly" => GameMode::Weekly,
+        "objective" => GameMode::Objective,
+        "versus" => GameMode::Versus,
+        _ => return Err(format!("Unknown game type {}", game_type)),
+    };
+    game_mode_class(&game_mode)
+        .map(String::from)
+        .ok_or_else(|| format!("Unknown game type {}", game_type))
+}
+
+fn game_mode_class(game_mode: &GameMode) -> Option<&'static str> {
+    match game_mode {
+        GameMode::Survival => Some("KFGameContent.KFGameInfo_Survival"),
+        GameMode::Endless => Some("KFGameContent.KFGameInfo_Endless"),
+        GameMode::Weekly => Some("KFGameContent.KFGameInfo_WeeklySurvival"),
+        GameMode::Objective => Some("KFGameContent.KFGameInfo_Objective"),
+        GameMode::Versus => Some("KFGameContent.KFGameInfo_VersusSurvival"),
+        GameMode::Other(_) => None,
+    }
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    /// Runs the action and records it in the audit table, whether it
+    /// succeeded or not.
+    pub(crate) async fn control(
+        &mut self,
+        action: ControlAction,
+        source: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let result = match &action {
+            ControlAction::ChangeMap {
+                map,
+                game_type,
+                difficulty,
+            } => {
+                self.change_map(map, game_type.as_deref(), difficulty.as_ref())
+                    .await
+            }
+            ControlAction::RestartMap => self.restart_map().await,
+            ControlAction::Broadcast { message } => self.broadcast(message).await,
+            ControlAction::Kick { steam_id } => self.player_action(*steam_id, "kick").await,
+            ControlAction::Ban { steam_id } => self.player_action(*steam_id, "banid").await,
+            ControlAction::BanId { unique_net_id } => self.ban_id(unique_net_id).await,
+            ControlAction::BanIp { ip } => self.ban_ip(ip).await,
+        };
+        match &result {
+            Ok(()) => info!(
+                "Control {} by {}: {}",
+                action.name(),
+                source,
+                action.target().unwrap_or_default()
+            ),
+            Err(e) => error!("Control {} by {} failed: {}", action.name(), source, e),
+        }
+        let record = ControlRecord {
+            action: action.name(),
+            target: action.target(),
+            source: source.to_string(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            created: chrono::Utc::now().naive_utc(),
+        };
+        if let Err(e) = self.db_connection.log_control_action(record).await {
+            error!("Could not record control {}: {}", action.name(), e);
+        }
+        result
+    }
+
+    async fn change_map(
+        &mut self,
+        map: &str,
+        game_type: Option<&str>,
+        difficulty: Option<&KfDifficulty>,
+    ) -> Result<(), Box<dyn Error>> {
+        let current = self.get_game_session().await?;
+        let game_type = match game_type {
+            Some(game_type) => game_type.to_string(),
+            None => game_mode_class(&current.game_mode)
+                .ok_or_else(|| format!("Unknown current game type {}", current.game_type))?
+                .to_string(),
+        };
+        let url_extra = difficulty
+            .unwrap_or(&current.difficulty)
+            .value()
+            .map(|difficulty| format!("?Difficulty={}", difficulty))
+            .unwrap_or_default();
+        let form_data = HashMap::from([
+            ("gametype", game_type),
+            ("map", map.to_string()),
+            ("mutatorGroupCount", String::from("0")),
+            ("urlextra", url_extra),
+            ("action", String::from("change")),
+        ]);
+        let request = self.session.post(self.url.change.as_str()).form(&form_data);
+        self.fetch("change", request).await?;
+        Ok(())
+    }
+
+    async fn restart_map(&mut self) -> Result<(), Box<dyn Error>> {
+        let map = self.get_game_session().await?.map_name;
+        self.change_map(&map, None, None).await
+    }
+
+    async fn broadcast(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        let form_data = HashMap::from([("ajax", "1"), ("message", message), ("teamsay", "-1")]);
+        let request = self.session.post(self.url.chat.as_str()).form(&form_data);
+        self.fetch("chat", request).await?;
+        Ok(())
+    }
+
+    /// Runs an action of the players page on the player, found by the
+    /// player key of the row.
+    async fn player_action(&mut self, steam_id: u64, action: &str) -> Result<(), Box<dyn Error>> {
+        let player_key = self
+            .get_unique_players()
+            .await?
+            .into_iter()
+            .find(|player| player.steam_id == steam_id)
+            .ok_or_else(|| format!("Player {} is not on the server", steam_id))?
+            .player_key
+            .ok_or_else(|| format!("No player key for {}", steam_id))?;
+        let form_data = HashMap::from([("playerkey", player_key.as_str()), ("action", action)]);
+        let request = self
+            .session
+            .post(self.url.players.as_str())
+            .form(&form_data);
+        self.fetch("players", request).await?;
+        Ok(())
+    }
+
+    async fn ban_id(&mut self, unique_net_id: &str) -> Result<(), Box<dyn Error>> {
+        let form_data = HashMap::from([("uniqueid", unique_net_id), ("action", "add")]);
+        let request = self.session.post(self.url.bans.as_str()).form(&form_data);
+        self.fetch("bans", request).await?;
+        Ok(())
+    }
+
+    async fn ban_ip(&mut self, ip: &IpAddr) -> Result<(), Box<dyn Error>> {
+        let ip = ip.to_string();
+        let form_data = HashMap::from([
+            ("ipmask", ip.as_str()),
+            ("policy", "DENY"),
+            ("action", "add"),
+        ]);
+        let request = self.session.post(self.url.policy.as_str()).form(&form_data);
+        self.fetch("policy", request).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use super::control::ControlAction;
+use super::Kf2Logger;
+use crate::args::Kf2PingKickArgs;
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_scrape::models::PlayerInfo;
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+
+const SOURCE: &str = "ping_kick";
+
+#[derive(Debug)]
+struct PingState {
+    joined_at: NaiveDateTime,
+    high_since: Option<NaiveDateTime>,
+    warned_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum PingAction {
+    Warn { name: String, ping: u32 },
+    Kick { steam_id: u64 },
+}
+
+/// Tracks how long each player has had high ping. A player is warned once
+/// the ping has been high for the whole duration and kicked when it is still
+/// high a duration after the warning.
+pub(crate) struct PingKick {
+    args: Kf2PingKickArgs,
+    players: HashMap<u64, PingState>,
+}
+
+impl PingKick {
+    pub(crate) fn new(args: Kf2PingKickArgs) -> Self {
+        Self {
+            args,
+            players: HashMap::new(),
+        }
+    }
+
+    fn check(&mut self, players: &[PlayerInfo], now: NaiveDateTime) -> Vec<PingAction> {
+        self.players
+            .retain(|steam_id, _| players.iter().any(|p| p.steam_id == *steam_id));
+        let duration = chrono::Duration::from_std(self.args.duration).unwrap_or_default();
+        let grace = chrono::Duration::from_std(self.args.grace).unwrap_or_default();
+        let mut actions = Vec::new();
+        for player in players {
+            let state = self.players.entry(player.steam_id).or_insert(PingState {
+                joined_at: now,
+                high_since: None,
+                warned_at: None,
+            });
+            let exempt = player.admin && self.args.exempt_admins;
+            if exempt || now - state.joined_at < grace || player.ping <= self.args.threshold {
+                state.high_since = None;
+                state.warned_at = None;
+                continue;
+            }
+            let high_since = *state.high_since.get_or_insert(now);
+            match state.warned_at {
+                None if now - high_since >= duration => {
+                    state.warned_at = Some(now);
+                    actions.push(PingAction::Warn {
+                        name: player.name.clone(),
+                        ping: player.ping,
+                    });
+                }
+                Some(warned_at) if now - warned_at >= duration => {
+                    self.players.remove(&player.steam_id);
+                    actions.push(PingAction::Kick {
+                        steam_id: player.steam_id,
+                    });
+                }
+                _ => (),
+            }
+        }
+        actions
+    }
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn set_ping_kick(&mut self, args: Kf2PingKickArgs) {
+        self.ping_kick = Some(PingKick::new(args));
+    }
+
+    /// Warns and kicks players with high ping, using the pings scraped by
+    /// `log_unique_players`.
+    pub(crate) async fn kick_high_ping(&mut self) {
+        let Some(ping_kick) = &mut self.ping_kick else {
+            return;
+        };
+        let players = self.unique_players.as_deref().unwrap_or_default();
+        let threshold = ping_kick.args.threshold;
+        let actions = ping_kick.check(players, chrono::Utc::now().naive_utc());
+        for action in actions {
+            let action = match action {
+                PingAction::Warn { name, ping } => ControlAction::Broadcast {
+                    message: format!(
+                        "{}, your ping {} ms is over {} ms. Players with high ping are kicked.",
+                        name, ping, threshold
+                    ),
+                },
+                PingAction::Kick { steam_id } => ControlAction::Kick { steam_id },
+            };
+            // Failures are logged and recorded by control
+            let _ = self.control(action, SOURCE).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_ping {
+    use super::*;
+    use std::time::Duration;
+
+    fn player(steam_id: u64, ping: u32, admin: bool) -> PlayerInfo {
+        PlayerInfo {
+            name: String::from("koira"),
+            ping,
+            ip: [10, 0, 0, 1].into(),
+            unique_net_id: String::new(),
+            steam_id,
+            admin,
+            player_key: None,
+        }
+    }
+
+    fn at(seconds: i64) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(18, 0, 0)
+            .unwrap()
+            + chrono::Duration::seconds(seconds)
+    }
+
+    fn ping_kick() -> PingKick {
+        PingKick::new(Kf2PingKickArgs {
+            threshold: 200,
+            duration: Duration::from_secs(60),
+            grace: Duration::from_secs(30),
+            exempt_admins: true,
+        })
+    }
+
+    #[test]
+    fn test_warn_then_kick() {
+        let mut ping_kick = ping_kick();
+        let players = [player(1, 350, false)];
+        // The grace period starts at join
+        assert!(ping_kick.check(&players, at(0)).is_empty());
+        assert!(ping_kick.check(&players, at(30)).is_empty());
+        assert!(ping_kick.check(&players, at(60)).is_empty());
+        assert_eq!(
+            ping_kick.check(&players, at(90)),
+            vec![PingAction::Warn {
+                name: String::from("koira"),
+                ping: 350
+            }]
+        );
+        assert!(ping_kick.check(&players, at(120)).is_empty());
+        assert_eq!(
+            ping_kick.check(&players, at(150)),
+            vec![PingAction::Kick { steam_id: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_ping_recovers() {
+        let mut ping_kick = ping_kick();
+        ping_kick.check(&[player(1, 350, false)], at(0));
+        ping_kick.check(&[player(1, 350, false)], at(30));
+        assert_eq!(ping_kick.check(&[player(1, 350, false)], at(90)).len(), 1);
+        // Going under the threshold starts over
+        assert!(ping_kick.check(&[player(1, 80, false)], at(100)).is_empty());
+        assert!(ping_kick
+            .check(&[player(1, 350, false)], at(150))
+            .is_empty());
+        assert_eq!(
+            ping_kick.check(&[player(1, 350, false)], at(210)),
+            vec![PingAction::Warn {
+                name: String::from("koira"),
+                ping: 350
+            }]
+        );
+    }
+
+    #[test]
+    fn test_admins_exempt() {
+        let mut ping_kick = ping_kick();
+        for seconds in (0..600).step_by(30) {
+            assert!(ping_kick
+                .check(&[player(1, 500, true)], at(seconds))
+                .is_empty());
+        }
+        ping_kick.args.exempt_admins = false;
+        ping_kick.check(&[player(1, 500, true)], at(600));
+        assert_eq!(ping_kick.check(&[player(1, 500, true)], at(660)).len(), 1);
+    }
+}
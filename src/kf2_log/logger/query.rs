@@ -0,0 +1,139 @@
+use super::Kf2Logger;
+use crate::args::Kf2QueryArgs;
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_metrics;
+use crate::kf2_query::a2s::{A2sClient, A2sInfo};
+use log::{info, warn};
+
+/// Queries the server on its Steam query port next to the webadmin.
+pub(crate) struct QueryCheck {
+    client: A2sClient,
+    answering: bool,
+    /// Differences found on the last tick, so each is warned once.
+    mismatches: Vec<String>,
+}
+
+impl QueryCheck {
+    pub(crate) fn new(args: Kf2QueryArgs) -> Self {
+        Self {
+            client: A2sClient::new(args.address, args.timeout),
+            answering: true,
+            mismatches: Vec::new(),
+        }
+    }
+}
+
+/// Differences between the query answer and the map and player count
+/// scraped from the webadmin.
+fn compare(info: &A2sInfo, map: Option<&str>, players: usize) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if let Some(map) = map {
+        if !map.eq_ignore_ascii_case(&info.map) {
+            mismatches.push(format!("map {} on the webadmin, {} queried", map, info.map));
+        }
+    }
+    let queried = info.players.saturating_sub(info.bots) as usize;
+    if players != queried {
+        mismatches.push(format!(
+            "{} players on the webadmin, {} queried",
+            players, queried
+        ));
+    }
+    mismatches
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn set_query(&mut self, args: Kf2QueryArgs) {
+        self.query = Some(QueryCheck::new(args));
+    }
+
+    /// Queries the server once per tick. While the webadmin answers, the
+    /// query is compared with what was scraped. Otherwise it tells whether
+    /// the server is up and keeps the player count metric going.
+    pub(crate) async fn check_query(&mut self) {
+        let Some(query) = &mut self.query else {
+            return;
+        };
+        let info = match query.client.info().await {
+            Ok(info) => info,
+            Err(e) => {
+                if query.answering {
+                    query.answering = false;
+                    warn!("Server not answering queries: {}", e);
+                }
+                return;
+            }
+        };
+        if !query.answering {
+            query.answering = true;
+            info!("Server answering queries again");
+        }
+
+        if self.web_admin_reachable {
+            let map = self.current_game().map(|game| game.map_name.as_str());
+            let mismatches = compare(&info, map, self.current_players().len());
+            let Some(query) = &mut self.query else {
+                return;
+            };
+            for mismatch in &mismatches {
+                if !query.mismatches.contains(mismatch) {
+                    warn!("Query differs from the webadmin: {}", mismatch);
+                }
+            }
+            query.mismatches = mismatches;
+            return;
+        }
+
+        let names = match query.client.players().await {
+            Ok(players) => players
+                .into_iter()
+                .map(|player| player.name)
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>()
+                .join(", "),
+            Err(e) => {
+                warn!("Could not query players: {}", e);
+                String::new()
+            }
+        };
+        info!(
+            "Webadmin unreachable, server up on {} with {}/{} players {}",
+            info.map, info.players, info.max_players, names
+        );
+        kf2_metrics::set_current_players(info.players as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests_query {
+    use super::*;
+
+    fn info(map: &str, players: u8, bots: u8) -> A2sInfo {
+        A2sInfo {
+            name: String::from("Mock KF2"),
+            map: String::from(map),
+            folder: String::from("KFGame"),
+            game: String::from("Killing Floor 2"),
+            players,
+            max_players: 6,
+            bots,
+            password: false,
+            version: String::from("1099"),
+            port: Some(7777),
+            keywords: None,
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        assert!(compare(&info("KF-Outpost", 2, 0), Some("kf-outpost"), 2).is_empty());
+        assert!(compare(&info("KF-Outpost", 3, 1), None, 2).is_empty());
+        assert_eq!(
+            compare(&info("KF-Outpost", 1, 0), Some("KF-BurningParis"), 2),
+            vec![
+                "map KF-BurningParis on the webadmin, KF-Outpost queried",
+                "2 players on the webadmin, 1 queried",
+            ]
+        );
+    }
+}
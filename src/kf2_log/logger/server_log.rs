@@ -0,0 +1,64 @@
+use super::Kf2Logger;
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_event::EventKind;
+use crate::kf2_scrape::models::PlayerInfo;
+use crate::kf2_server_log::parse::LogLine;
+use log::debug;
+use std::collections::{HashMap, HashSet};
+
+/// Joins emitted from the server output before the webadmin showed them,
+/// so the next scrape does not emit them again.
+#[derive(Debug, Default)]
+pub(crate) struct LogEvents {
+    /// Steam ids of the names seen on the players page. The output only
+    /// has names, so players not scraped yet are left to the scrape.
+    known_players: HashMap<String, u64>,
+    joined: HashSet<u64>,
+}
+
+impl LogEvents {
+    /// Takes the players of a players page scrape and returns the Steam ids
+    /// of the players that joined in the output since the last one. Those
+    /// joined before this scrape, which has caught up with them.
+    pub(super) fn players_scraped(&mut self, players: &[PlayerInfo]) -> HashSet<u64> {
+        for player in players {
+            self.known_players
+                .insert(player.name.clone(), player.steam_id);
+        }
+        std::mem::take(&mut self.joined)
+    }
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    /// Handles a line of the server output and emits what it tells ahead of
+    /// the next scrape, which should follow early as the output lacks the
+    /// stats.
+    pub(crate) fn server_log_line(&mut self, line: LogLine) {
+        match line {
+            LogLine::MapLoaded { map } => {
+                debug!("Server output: {} loaded", map);
+                // A game starts once it is played, which the scrape tells
+                self.emit(EventKind::MapLoaded { map });
+            }
+            LogLine::PlayerJoined { name } => {
+                debug!("Server output: {} joined", name);
+                let steam_id = self.log_events.known_players.get(&name).copied();
+                let scraped = |steam_id| {
+                    self.unique_players
+                        .as_deref()
+                        .unwrap_or_default()
+                        .iter()
+                        .any(|player| player.steam_id == steam_id)
+                };
+                if let Some(steam_id) = steam_id.filter(|steam_id| !scraped(*steam_id)) {
+                    self.log_events.joined.insert(steam_id);
+                    self.emit(EventKind::PlayerJoined {
+                        steam_id,
+                        name,
+                        first_seen: false,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use super::Kf2Logger;
+use crate::args::Kf2ConfigHistoryArgs;
+use crate::kf2_database::store::Kf2Store;
+use crate::kf2_scrape::parse::DocumentExtractor;
+use log::info;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Keeps a history of the settings pages of the webadmin, so changes can be
+/// told apart in the stats of the games played with them.
+pub(crate) struct ConfigHistory {
+    interval: Duration,
+    last_snapshot: Option<Instant>,
+    config: Option<BTreeMap<String, String>>,
+}
+
+impl ConfigHistory {
+    pub(crate) fn new(args: Kf2ConfigHistoryArgs) -> Self {
+        Self {
+            interval: args.interval,
+            last_snapshot: None,
+            config: None,
+        }
+    }
+
+    fn is_due(&self) -> bool {
+        self.last_snapshot
+            .is_none_or(|last_snapshot| last_snapshot.elapsed() >= self.interval)
+    }
+}
+
+/// Settings whose value differs between the configurations, with the old
+/// and new value. Missing settings are empty.
+fn changes<'a>(
+    old: &'a BTreeMap<String, String>,
+    new: &'a BTreeMap<String, String>,
+) -> Vec<(&'a str, &'a str, &'a str)> {
+    let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let from = old.get(name).map_or("", String::as_str);
+            let to = new.get(name).map_or("", String::as_str);
+            (from != to).then_some((name.as_str(), from, to))
+        })
+        .collect()
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn set_config_history(&mut self, args: Kf2ConfigHistoryArgs) {
+        self.config_history = Some(ConfigHistory::new(args));
+    }
+
+    /// The general, map cycle and mutator settings, each named after its
+    /// page, e.g. `general.settings_GameLength`.
+    async fn get_config(&mut self) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        let pages = [
+            ("general", self.url.general.clone()),
+            ("map_cycle", self.url.map_cycle.clone()),
+            ("mutators", self.url.mutators.clone()),
+        ];
+        let mut config = BTreeMap::new();
+        for (page, url) in pages {
+            let request = self.session.get(url.as_str());
+            let text = self.fetch(page, request).await?;
+            for (name, value) in DocumentExtractor::new(&text).parse_settings()? {
+                config.insert(format!("{}.{}", page, name), value);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Runs once per interval. Stores the settings as a new version when
+    /// they changed.
+    pub(crate) async fn snapshot_config(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(config_history) = &mut self.config_history else {
+            return Ok(());
+        };
+        if !config_history.is_due() {
+            return Ok(());
+        }
+        config_history.last_snapshot = Some(Instant::now());
+
+        let config = self.get_config().await?;
+        let server = self.server_id.clone();
+        let stored = self
+            .db_connection
+            .log_server_config(&server, config.clone())
+            .await?;
+        let Some(config_history) = &mut self.config_history else {
+            return Ok(());
+        };
+        if stored {
+            match &config_history.config {
+                Some(old) => {
+                    for (name, from, to) in changes(old, &config) {
+                        info!("Setting {} changed from {:?} to {:?}", name, from, to);
+                    }
+                }
+                None => info!("Stored a new version of the server settings"),
+            }
+        }
+        config_history.config = Some(config);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_settings {
+    use super::*;
+
+    #[test]
+    fn test_changes() {
+        let old = BTreeMap::from([
+            (
+                String::from("general.settings_GameLength"),
+                String::from("1"),
+            ),
+            (
+                String::from("general.settings_MaxPlayers"),
+                String::from("6"),
+            ),
+            (String::from("mutators.mutator"), String::from("A")),
+        ]);
+        let new = BTreeMap::from([
+            (
+                String::from("general.settings_GameLength"),
+                String::from("2"),
+            ),
+            (
+                String::from("general.settings_MaxPlayers"),
+                String::from("6"),
+            ),
+            (
+                String::from("map_cycle.mapcycle"),
+                String::from("KF-Outpost"),
+            ),
+        ]);
+        assert_eq!(
+            changes(&old, &new),
+            vec![
+                ("general.settings_GameLength", "1", "2"),
+                ("map_cycle.mapcycle", "", "KF-Outpost"),
+                ("mutators.mutator", "A", ""),
+            ]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use super::announce::{placeholders, render};
+use super::control::ControlAction;
+use super::Kf2Logger;
+use crate::args::Kf2WelcomeArgs;
+use crate::kf2_database::store::Kf2Store;
+use log::warn;
+use std::collections::HashMap;
+use std::error::Error;
+
+const SOURCE: &str = "welcome";
+
+/// Placeholders of the greetings. `count` is the number of the map the
+/// player joined, counting earlier ones, and `nth` the same as an ordinal.
+const PLACEHOLDERS: [&str; 3] = ["name", "count", "nth"];
+
+/// A player who joined on this tick.
+pub(super) struct JoinedPlayer {
+    pub(super) steam_id: u64,
+    pub(super) name: String,
+    pub(super) first_seen: bool,
+}
+
+fn ordinal(count: u32) -> String {
+    let suffix = match (count % 10, count % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", count, suffix)
+}
+
+/// Greets joining players by their history. Players already on the server
+/// when the logger starts are not greeted.
+pub(crate) struct Greeter {
+    args: Kf2WelcomeArgs,
+    ready: bool,
+}
+
+impl Greeter {
+    pub(crate) fn new(args: Kf2WelcomeArgs) -> Result<Self, Box<dyn Error>> {
+        let messages = [
+            &args.new_message,
+            &args.back_message,
+            &args.milestone_message,
+        ];
+        for message in messages.into_iter().flatten() {
+            if let Some(name) = placeholders(message)
+                .into_iter()
+                .find(|name| !PLACEHOLDERS.contains(name))
+            {
+                return Err(format!("Unknown placeholder {{{}}} in {}", name, message).into());
+            }
+        }
+        Ok(Self { args, ready: false })
+    }
+
+    /// Greeting template for the player's map count. Milestones take the
+    /// place of the welcome back message.
+    fn template(&self, first_seen: bool, count: u32) -> Option<&str> {
+        if first_seen {
+            return self.args.new_message.as_deref();
+        }
+        if self.args.milestones.contains(&count) {
+            if let Some(message) = &self.args.milestone_message {
+                return Some(message);
+            }
+        }
+        self.args.back_message.as_deref()
+    }
+}
+
+impl<S: Kf2Store> Kf2Logger<S> {
+    pub(crate) fn set_welcome(&mut self, args: Kf2WelcomeArgs) -> Result<(), Box<dyn Error>> {
+        self.greeter = Some(Greeter::new(args)?);
+        Ok(())
+    }
+
+    /// Broadcasts a greeting for each joined player. Called on every scrape
+    /// of the players page, the first one only marks the players present.
+    pub(super) async fn welcome_players(&mut self, joined: Vec<JoinedPlayer>) {
+        let Some(greeter) = &mut self.greeter else {
+            return;
+        };
+        if !greeter.ready {
+            greeter.ready = true;
+            return;
+        }
+        for player in joined {
+            let count = match player.first_seen {
+                true => 1,
+                false => match self.db_connection.player_maps_played(player.steam_id).await {
+                    Ok(maps_played) => maps_played + 1,
+                    Err(e) => {
+                        warn!("Could not read the history of {}: {}", player.name, e);
+                        continue;
+                    }
+                },
+            };
+            let Some(template) = self
+                .greeter
+                .as_ref()
+                .and_then(|greeter| greeter.template(player.first_seen, count))
+            else {
+                continue;
+            };
+            let values = HashMap::from([
+                ("name", player.name),
+                ("count", count.to_string()),
+                ("nth", ordinal(count)),
+            ]);
+            let Some(message) = render(template, &values) else {
+                continue;
+            };
+            // Failures are logged and recorded by control
+            let _ = self
+                .control(ControlAction::Broadcast { message }, SOURCE)
+                .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_welcome {
+    use super::*;
+
+    fn greeter() -> Greeter {
+        Greeter::new(Kf2WelcomeArgs {
+            new_message: Some(String::from("Welcome {name}!")),
+            back_message: Some(String::from("Welcome back {name}, your {nth} map")),
+            milestone_message: Some(String::from("{name} plays their {nth} map here!")),
+            milestones: vec![10, 50],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_ordinal() {
+        let ordinals: Vec<String> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 101, 112]
+            .into_iter()
+            .map(ordinal)
+            .collect();
+        assert_eq!(
+            ordinals,
+            [
+                "1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "101st",
+                "112th"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_name_with_braces() {
+        let greeter = greeter();
+        let template = greeter.template(false, 9).unwrap();
+        let values = HashMap::from([
+            ("name", String::from("{nth} {count}")),
+            ("count", String::from("9")),
+            ("nth", ordinal(9)),
+        ]);
+        assert_eq!(
+            render(template, &values).as_deref(),
+            Some("Welcome back {nth} {count}, your 9th map")
+        );
+    }
+
+    #[test]
+    fn test_template() {
+        let greeter = greeter();
+        assert_eq!(greeter.template(true, 1), Some("Welcome {name}!"));
+        assert_eq!(
+            greeter.template(false, 9),
+            Some("Welcome back {name}, your {nth} map")
+        );
+        assert_eq!(
+            greeter.template(false, 50),
+            Some("{name} plays their {nth} map here!")
+        );
+        assert!(Greeter::new(Kf2WelcomeArgs {
+            new_message: Some(String::from("Hi {player}")),
+            back_message: None,
+            milestone_message: None,
+            milestones: Vec::new(),
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::kf2_scrape::models::{PlayerInGame, PlayerInfo};
+
+fn exact_name(name: &str) -> String {
+    name.to_string()
+}
+
+fn normalized_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Pairs the players page rows with the info page rows.
+///
+/// Player keys are used when both pages have them. Otherwise players are
+/// matched by exact name and then by normalized name. When several players
+/// share a name, the one with the closest ping is picked. Players that cannot
+/// be matched, e.g. someone joined between the two requests, are left out.
+pub(super) fn match_players(
+    players_info: &[PlayerInfo],
+    players_in_game: &[PlayerInGame],
+) -> Vec<(PlayerInfo, PlayerInGame)> {
+    let mut matched = Vec::new();
+    let mut unmatched_in_game: Vec<&PlayerInGame> = players_in_game.iter().collect();
+    let mut unmatched_info: Vec<&PlayerInfo> = players_info
+        .iter()
+        .filter(|player_info| {
+            let Some(key) = &player_info.player_key else {
+                return true;
+            };
+            match unmatched_in_game
+                .iter()
+                .position(|p| p.player_key.as_ref() == Some(key))
+            {
+                Some(i) => {
+                    let player_in_game = unmatched_in_game.remove(i);
+                    matched.push(((*player_info).clone(), player_in_game.clone()));
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect();
+
+    for name_key in [exact_name, normalized_name] {
+        let mut remaining_info = Vec::new();
+        for player_info in unmatched_info {
+            let key = name_key(&player_info.name);
+            let closest = unmatched_in_game
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| name_key(&p.name) == key)
+                .min_by_key(|(_, p)| p.ping.abs_diff(player_info.ping))
+                .map(|(i, _)| i);
+            match closest {
+                Some(i) => {
+                    let player_in_game = unmatched_in_game.remove(i);
+                    matched.push((player_info.clone(), player_in_game.clone()));
+                }
+                None => remaining_info.push(player_info),
+            }
+        }
+        unmatched_info = remaining_info;
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests_matching {
+    use super::*;
+    use crate::kf2_scrape::models::Perk;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn player_info(name: &str, steam_id: u64, ping: u32) -> PlayerInfo {
+        PlayerInfo {
+            name: String::from(name),
+            ping,
+            ip: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            unique_net_id: String::from(""),
+            steam_id,
+            admin: false,
+            player_key: None,
+        }
+    }
+
+    fn player_in_game(name: &str, kills: u32, ping: u32) -> PlayerInGame {
+        PlayerInGame {
+            name: String::from(name),
+            perk: Perk::Berserker,
+            dosh: 0,
+            health: 100,
+            kills,
+            ping,
+            admin: false,
+            player_key: None,
+        }
+    }
+
+    #[test]
+    fn test_match_players_by_name() {
+        let infos = vec![player_info("koira", 1, 50), player_info("kissa", 2, 60)];
+        let in_game = vec![
+            player_in_game("kissa", 20, 60),
+            player_in_game("koira", 10, 50),
+        ];
+        let matched = match_players(&infos, &in_game);
+        assert_eq!(matched.len(), 2);
+        assert!(matched
+            .iter()
+            .all(|(info, in_game)| info.name == in_game.name));
+    }
+
+    #[test]
+    fn test_match_players_joined_between_requests() {
+        let infos = vec![player_info("koira", 1, 50), player_info("kissa", 2, 60)];
+        let in_game = vec![player_in_game("koira", 10, 50)];
+        let matched = match_players(&infos, &in_game);
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].0.steam_id, 1);
+    }
+
+    #[test]
+    fn test_match_players_same_name_by_ping() {
+        let infos = vec![player_info("Player", 1, 40), player_info("Player", 2, 180)];
+        let in_game = vec![
+            player_in_game("Player", 5, 175),
+            player_in_game("Player", 9, 45),
+        ];
+        let matched = match_players(&infos, &in_game);
+        assert_eq!(matched.len(), 2);
+        let first = matched.iter().find(|(info, _)| info.steam_id == 1).unwrap();
+        assert_eq!(first.1.kills, 9);
+        let second = matched.iter().find(|(info, _)| info.steam_id == 2).unwrap();
+        assert_eq!(second.1.kills, 5);
+    }
+
+    #[test]
+    fn test_match_players_normalized_name() {
+        let infos = vec![player_info("Deep B Dark", 1, 50)];
+        let in_game = vec![player_in_game("deepbdark", 10, 50)];
+        let matched = match_players(&infos, &in_game);
+        assert_eq!(matched.len(), 1);
+    }
+
+    #[test]
+    fn test_match_players_by_key() {
+        let mut info = player_info("koira", 1, 50);
+        info.player_key = Some(String::from("3_0x0110000"));
+        let mut in_game = player_in_game("renamed", 10, 50);
+        in_game.player_key = Some(String::from("3_0x0110000"));
+        let matched = match_players(&[info], &[player_in_game("koira", 1, 50), in_game]);
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].1.name, "renamed");
+    }
+}
@@ -0,0 +1,801 @@
+use crate::kf2_scrape::models::{steam_unique_net_id, unique_net_id_steam_id};
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone)]
+pub(crate) struct MockPlayer {
+    pub(crate) name: String,
+    pub(crate) perk: String,
+    pub(crate) dosh: u32,
+    pub(crate) health: u32,
+    pub(crate) kills: u32,
+    pub(crate) ping: u32,
+    pub(crate) ip: String,
+    pub(crate) steam_id: u64,
+    pub(crate) admin: bool,
+}
+
+impl MockPlayer {
+    pub(crate) fn new(name: &str, perk: &str, steam_id: u64) -> Self {
+        Self {
+            name: String::from(name),
+            perk: String::from(perk),
+            dosh: 300,
+            health: 100,
+            kills: 0,
+            ping: 40,
+            ip: format!("10.0.0.{}", steam_id % 250 + 1),
+            steam_id,
+            admin: false,
+        }
+    }
+
+    fn unique_net_id(&self) -> String {
+        format!(
+            "0x{:016X}",
+            self.steam_id - 76561197960265728 + 0x0110000100000000
+        )
+    }
+}
+
+/// Game state served by the mock webadmin. Tests script a game by changing
+/// the state between logger ticks.
+#[derive(Debug, Clone)]
+pub(crate) struct MockGame {
+    pub(crate) map_name: String,
+    pub(crate) game_type: String,
+    pub(crate) difficulty: String,
+    pub(crate) wave: u16,
+    pub(crate) max_waves: u16,
+    pub(crate) max_players: u16,
+    pub(crate) boss_index: u8,
+    pub(crate) trader_open: bool,
+    pub(crate) ai_remaining: u32,
+    pub(crate) players: Vec<MockPlayer>,
+}
+
+impl Default for MockGame {
+    fn default() -> Self {
+        Self {
+            map_name: String::from("KF-BurningParis"),
+            game_type: String::from("Survival"),
+            difficulty: String::from("Hard"),
+            wave: 0,
+            max_waves: 7,
+            max_players: 6,
+            boss_index: 2,
+            trader_open: true,
+            ai_remaining: 0,
+            players: Vec::new(),
+        }
+    }
+}
+
+impl MockGame {
+    pub(crate) fn add_player(&mut self, player: MockPlayer) {
+        self.players.push(player);
+    }
+
+    pub(crate) fn remove_player(&mut self, name: &str) {
+        self.players.retain(|p| p.name != name);
+    }
+
+    /// Closes the trader and starts the next wave with the given zed count.
+    pub(crate) fn start_wave(&mut self, ai_remaining: u32) {
+        self.wave += 1;
+        self.trader_open = false;
+        self.ai_remaining = ai_remaining;
+    }
+
+    /// Kills the remaining zeds, shares the kills between players and opens
+    /// the trader.
+    pub(crate) fn clear_wave(&mut self) {
+        let count = self.players.len().max(1) as u32;
+        for player in self.players.iter_mut() {
+            player.kills += self.ai_remaining / count;
+            player.dosh += 200;
+        }
+        self.ai_remaining = 0;
+        self.trader_open = true;
+    }
+
+    /// Starts the boss wave, which webadmin shows as one past the last wave.
+    pub(crate) fn spawn_boss(&mut self) {
+        self.wave = self.max_waves + 1;
+        self.trader_open = false;
+        self.ai_remaining = 1;
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    game: MockGame,
+    username: String,
+    password: String,
+    auth_creds: Vec<String>,
+    next_id: u32,
+    logins: u32,
+    messages: Vec<String>,
+    bans: Vec<u64>,
+    ip_bans: Vec<String>,
+}
+
+impl MockState {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:08x}", self.next_id)
+    }
+}
+
+/// In-process stand-in for the KF2 webadmin. Serves the login, info,
+/// players and console pages with the same markup as the real server.
+pub(crate) struct MockWebAdmin {
+    address: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockWebAdmin {
+    pub(crate) async fn start(username: &str, password: &str) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            game: MockGame::default(),
+            username: String::from(username),
+            password: String::from(password),
+            auth_creds: Vec::new(),
+            next_id: 0,
+            logins: 0,
+            messages: Vec::new(),
+            bans: Vec::new(),
+            ip_bans: Vec::new(),
+        }));
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        log::error!("Mock webadmin: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            address,
+            state,
+            handle,
+        })
+    }
+
+    pub(crate) fn url(&self) -> url::Url {
+        url::Url::parse(&format!("http://{}/", self.address)).expect("Mock url")
+    }
+
+    /// Changes the game state between logger ticks.
+    pub(crate) fn game<R>(&self, script: impl FnOnce(&mut MockGame) -> R) -> R {
+        script(&mut self.lock().game)
+    }
+
+    pub(crate) fn logins(&self) -> u32 {
+        self.lock().logins
+    }
+
+    /// Messages broadcast to the chat.
+    pub(crate) fn messages(&self) -> Vec<String> {
+        self.lock().messages.clone()
+    }
+
+    /// Steam ids of banned players.
+    pub(crate) fn bans(&self) -> Vec<u64> {
+        self.lock().bans.clone()
+    }
+
+    /// Adds a ban as if an admin had banned the player on this server.
+    pub(crate) fn ban(&self, steam_id: u64) {
+        self.lock().bans.push(steam_id);
+    }
+
+    /// Lifts a ban as if an admin had deleted it on the bans page.
+    pub(crate) fn unban(&self, steam_id: u64) {
+        self.lock().bans.retain(|id| *id != steam_id);
+    }
+
+    /// Addresses denied on the policy page.
+    pub(crate) fn ip_bans(&self) -> Vec<String> {
+        self.lock().ip_bans.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().expect("Mock state poisoned")
+    }
+
+    /// Simulates a server restart: webadmin sessions are dropped and the
+    /// server comes back with an empty lobby on the given map.
+    pub(crate) fn restart(&self, map_name: &str) {
+        let mut state = self.lock();
+        state.auth_creds.clear();
+        state.game = MockGame {
+            map_name: String::from(map_name),
+            ..MockGame::default()
+        };
+    }
+}
+
+impl Drop for MockWebAdmin {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+impl Request {
+    fn cookie(&self, name: &str) -> Option<String> {
+        self.headers.get("cookie")?.split(';').find_map(|cookie| {
+            let (key, value) = cookie.trim().split_once('=')?;
+            (key == name).then(|| value.trim_matches('"').to_string())
+        })
+    }
+
+    fn form(&self) -> HashMap<String, String> {
+        self.body
+            .split('&')
+            .filter_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                Some((url_decode(key), url_decode(value)))
+            })
+            .collect()
+    }
+}
+
+fn url_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match input
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request, Box<dyn Error>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err("Connection closed before the request was read".into());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().ok_or("Empty request")?.split(' ');
+    let method = request_line.next().ok_or("No method")?.to_string();
+    let path = request_line.next().ok_or("No path")?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            Some((key.trim().to_lowercase(), value.trim().to_string()))
+        })
+        .collect();
+    let content_length: usize = headers
+        .get("content-length")
+        .map_or(Ok(0), |length| length.parse())?;
+    while buffer.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+    let body = String::from_utf8_lossy(&buffer[header_end..]).into_owned();
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    state: Arc<Mutex<MockState>>,
+) -> Result<(), Box<dyn Error>> {
+    let request = read_request(&mut stream).await?;
+    let (status, cookie, body) = respond(&request, &mut state.lock().expect("Mock state poisoned"));
+    let cookie = cookie
+        .map(|cookie| format!("Set-Cookie: {}; Path=/\r\n", cookie))
+        .unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        cookie,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn respond(request: &Request, state: &mut MockState) -> (&'static str, Option<String>, String) {
+    let path = request.path.split('?').next().unwrap_or_default();
+    let authenticated = request
+        .cookie("authcred")
+        .is_some_and(|cred| state.auth_creds.contains(&cred));
+    match (request.method.as_str(), path) {
+        ("GET", "/ServerAdmin/") => {
+            let session_id = format!("sessionid={}", state.next_id());
+            ("200 OK", Some(session_id), login_page())
+        }
+        ("POST", "/ServerAdmin/") => {
+            let form = request.form();
+            let valid = form.get("username") == Some(&state.username)
+                && form.get("password") == Some(&state.password)
+                && form.get("token").is_some_and(|token| !token.is_empty());
+            if !valid {
+                return ("200 OK", None, login_page());
+            }
+            let auth_cred = state.next_id();
+            state.auth_creds.push(auth_cred.clone());
+            state.logins += 1;
+            let cookie = format!("authcred=\"{}\"", auth_cred);
+            ("200 OK", Some(cookie), page("Welcome"))
+        }
+        _ if !authenticated => ("200 OK", None, login_page()),
+        ("GET", "/ServerAdmin/current/info") => ("200 OK", None, info_page(&state.game)),
+        ("GET", "/ServerAdmin/current/players") => ("200 OK", None, players_page(&state.game)),
+        ("POST", "/ServerAdmin/current/change") => {
+            change_game(&mut state.game, &request.form());
+            ("200 OK", None, page("Changing the map"))
+        }
+        ("POST", "/ServerAdmin/current/chat+frame+data") => {
+            let message = request.form().remove("message").unwrap_or_default();
+            state.messages.push(message);
+            ("200 OK", None, String::new())
+        }
+        ("POST", "/ServerAdmin/current/players") => {
+            player_action(state, &request.form());
+            ("200 OK", None, players_page(&state.game))
+        }
+        ("GET", "/ServerAdmin/policy/bans") => ("200 OK", None, bans_page(&state.bans)),
+        ("POST", "/ServerAdmin/policy/bans") => {
+            let form = request.form();
+            let steam_id = form
+                .get("uniqueid")
+                .and_then(|id| unique_net_id_steam_id(id));
+            if let Some(steam_id) = steam_id.filter(|id| !state.bans.contains(id)) {
+                state.bans.push(steam_id);
+            }
+            ("200 OK", None, bans_page(&state.bans))
+        }
+        ("POST", "/ServerAdmin/policy") => {
+            let ip = request.form().remove("ipmask").unwrap_or_default();
+            state.ip_bans.push(ip);
+            ("200 OK", None, page("Access Policy"))
+        }
+        ("GET", "/ServerAdmin/settings/general") => ("200 OK", None, general_page(&state.game)),
+        ("GET", "/ServerAdmin/settings/maplist") => ("200 OK", None, map_cycle_page()),
+        ("GET", "/ServerAdmin/settings/mutators") => ("200 OK", None, mutators_page()),
+        ("POST", "/ServerAdmin/console") => {
+            let command = request.form().remove("command").unwrap_or_default();
+            ("200 OK", None, console_page(&state.game, &command))
+        }
+        _ => ("404 Not Found", None, page("Not Found")),
+    }
+}
+
+/// Travels to the map like the server does, keeping the players.
+fn change_game(game: &mut MockGame, form: &HashMap<String, String>) {
+    let game_type = match form.get("gametype").map(String::as_str) {
+        Some("KFGameContent.KFGameInfo_Endless") => "Endless",
+        Some("KFGameContent.KFGameInfo_WeeklySurvival") => "Weekly Outbreak",
+        Some("KFGameContent.KFGameInfo_VersusSurvival") => "Versus Survival",
+        Some("KFGameContent.KFGameInfo_Objective") => "Objective",
+        _ => "Survival",
+    };
+    let difficulty = match form.get("urlextra").map(String::as_str) {
+        Some("?Difficulty=0") => "Normal",
+        Some("?Difficulty=2") => "Suicidal",
+        Some("?Difficulty=3") => "Hell on Earth",
+        _ => "Hard",
+    };
+    *game = MockGame {
+        map_name: form.get("map").cloned().unwrap_or_default(),
+        game_type: String::from(game_type),
+        difficulty: String::from(difficulty),
+        players: std::mem::take(&mut game.players),
+        ..MockGame::default()
+    };
+}
+
+/// Kicks or bans the player of the `playerkey` given on the players page.
+fn player_action(state: &mut MockState, form: &HashMap<String, String>) {
+    let Some(index) = form
+        .get("playerkey")
+        .and_then(|key| key.split_once('_'))
+        .and_then(|(index, _)| index.parse::<usize>().ok())
+        .and_then(|index| index.checked_sub(256))
+        .filter(|index| *index < state.game.players.len())
+    else {
+        return;
+    };
+    match form.get("action").map(String::as_str) {
+        Some("kick") => {
+            state.game.players.remove(index);
+        }
+        Some("banid") => {
+            let player = state.game.players.remove(index);
+            state.bans.push(player.steam_id);
+        }
+        _ => (),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn page(content: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en">
+<head><title>Killing Floor 2 WebAdmin</title></head>
+<body class="">
+<div id="content">
+{}
+</div>
+<div id="footer">Copyright 2014 Tripwire Interactive LLC</div>
+</body>
+</html>"#,
+        content
+    )
+}
+
+fn login_page() -> String {
+    page(
+        r#"<form id="loginform" method="post" action="/ServerAdmin/" autocomplete="off">
+<fieldset>
+<legend>Login</legend>
+<div class="section">
+<input type="hidden" name="token" value="5A0C3E9D1B2F4A6E" />
+<input type="hidden" id="password_hash" name="password_hash" value="" />
+<dl>
+    <dt><label for="username">Username</label></dt>
+    <dd><input type="text" id="username" name="username" value="" /></dd>
+    <dt><label for="password">Password</label></dt>
+    <dd><input type="password" id="password" name="password" value="" /></dd>
+    <dd><button type="submit">login</button></dd>
+</dl>
+</div>
+</fieldset>
+</form>"#,
+    )
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "Yes"
+    } else {
+        "No"
+    }
+}
+
+fn info_page(game: &MockGame) -> String {
+    let rows = if game.players.is_empty() {
+        String::from(r#"<tr class="even"><td colspan="8"><em>There are no players</em></td></tr>"#)
+    } else {
+        game.players
+            .iter()
+            .map(|p| {
+                format!(
+                    r#"<tr class="even">
+    <td style="background: transparent; color: transparent;">&#160;</td>
+    <td>{}</td>
+    <td>{}</td>
+    <td class="right">{}</td>
+    <td class="right">{}</td>
+    <td class="right">{}</td>
+    <td class="right" title="Packet loss: ">{}</td>
+    <td class="center">{}</td>
+</tr>"#,
+                    escape(&p.name),
+                    p.perk,
+                    p.dosh,
+                    p.health,
+                    p.kills,
+                    p.ping,
+                    yes_no(p.admin)
+                )
+            })
+            .collect()
+    };
+    page(&format!(
+        r#"<h2>Current Game</h2>
+<table id="currentinfo"><tr>
+<td>
+    <h3>Game</h3>
+    <div class="section narrow">
+        <dl id="currentGame">
+            <dt>Server Name</dt>
+            <dd>Mock KF2</dd>
+            <dt>Cluster</dt>
+            <dd>Default</dd>
+            <dt>Game Type</dt>
+            <dd>{game_type}</dd>
+            <dt>Map</dt>
+            <dd>{map}</dd>
+        </dl>
+    </div>
+    <h3>Rules</h3>
+    <div class="section narrow">
+        <dl id="currentRules">
+            <dt>Wave</dt>
+            <dd>{wave}/{max_waves}</dd>
+            <dt>Difficulty</dt>
+            <dd>{difficulty}</dd>
+            <dt>Players</dt>
+            <dd>{players}/{max_players}</dd>
+        </dl>
+    </div>
+</td>
+<td>
+    <h3>Players</h3>
+    <div class="section narrow">
+        <table id="players" class="grid" width="100%">
+            <thead><tr><th>&#160;</th><th>Name</th><th>Perk</th><th>Dosh</th><th>Health</th><th>Kills</th><th>Ping</th><th>Admin</th></tr></thead>
+            <tbody>
+{rows}
+            </tbody>
+        </table>
+    </div>
+</td>
+</tr></table>"#,
+        game_type = game.game_type,
+        map = game.map_name,
+        wave = game.wave,
+        max_waves = game.max_waves,
+        difficulty = game.difficulty,
+        players = game.players.len(),
+        max_players = game.max_players,
+        rows = rows
+    ))
+}
+
+fn players_page(game: &MockGame) -> String {
+    let rows = if game.players.is_empty() {
+        String::from(r#"<tr class="even"><td colspan="10"><em>There are no players</em></td></tr>"#)
+    } else {
+        game.players
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let unique_net_id = p.unique_net_id();
+                format!(
+                    r#"<tr class="even">
+    <td style="background: transparent; color: transparent;">&#160;</td>
+    <td>{name}</td>
+    <td class="right">{ping}</td>
+    <td>{ip}</td>
+    <td>{unique_net_id}</td>
+    <td>{steam_id}</td>
+    <td class="center"></td>
+    <td class="center">{admin}</td>
+    <td class="center">
+        <form method="post" action="/ServerAdmin/current/players">
+            <input type="hidden" name="playerkey" value="{key}_{unique_net_id}" />
+            <button type="submit">Execute</button>
+        </form>
+    </td>
+    <td></td>
+</tr>"#,
+                    name = escape(&p.name),
+                    ping = p.ping,
+                    ip = p.ip,
+                    unique_net_id = unique_net_id,
+                    steam_id = p.steam_id,
+                    admin = yes_no(p.admin),
+                    key = 256 + i,
+                )
+            })
+            .collect()
+    };
+    page(&format!(
+        r#"<h2>Players</h2>
+<table id="players" class="grid">
+    <thead><tr><th>&#160;</th><th>Name</th><th>Ping</th><th>IP</th><th>Unique ID</th><th>Steam ID</th><th>Spectator</th><th>Admin</th><th>Actions</th><th></th></tr></thead>
+    <tbody>
+{}
+    </tbody>
+</table>"#,
+        rows
+    ))
+}
+
+/// General settings, with the difficulty and length of the game.
+fn general_page(game: &MockGame) -> String {
+    let length = match game.max_waves {
+        4 => 0,
+        10 => 2,
+        _ => 1,
+    };
+    let difficulty = match game.difficulty.as_str() {
+        "Normal" => 0,
+        "Suicidal" => 2,
+        "Hell on Earth" => 3,
+        _ => 1,
+    };
+    page(&format!(
+        r#"<form id="settingsform" method="post" action="/ServerAdmin/settings/general">
+<input type="text" name="settings_ServerName" value="Mock KF2" />
+<select name="settings_GameDifficulty"><option value="{difficulty}.000000" selected="selected">{}</option></select>
+<select name="settings_GameLength"><option value="{length}" selected="selected">{} Waves</option></select>
+<input type="number" name="settings_MaxPlayers" value="{}" />
+<input type="password" name="settings_GamePassword" value="secret" />
+<button type="submit" name="action" value="save">Save settings</button>
+</form>"#,
+        game.difficulty,
+        game.max_waves,
+        game.max_players,
+        difficulty = difficulty,
+        length = length,
+    ))
+}
+
+/// A single map cycle, the active one.
+fn map_cycle_page() -> String {
+    page(
+        r#"<form id="mapcycleform" method="post" action="/ServerAdmin/settings/maplist">
+<select name="maplistidx"><option value="0" selected="selected">Cycle 0 (active)</option></select>
+<textarea name="mapcycle">KF-BurningParis
+KF-Outpost</textarea>
+</form>"#,
+    )
+}
+
+/// A mutator that is not enabled.
+fn mutators_page() -> String {
+    page(
+        r#"<form id="mutatorform" method="post" action="/ServerAdmin/settings/mutators">
+<input type="checkbox" name="mutator" value="KFMutator.KFMutator_MaxPlayersV2" />
+<input type="hidden" name="mutatorGroupCount" value="1" />
+</form>"#,
+    )
+}
+
+/// Bans of players, who all have Steam ids in the mock.
+fn bans_page(bans: &[u64]) -> String {
+    let rows = if bans.is_empty() {
+        String::from(r#"<tr class="even"><td colspan="5"><em>There are no bans</em></td></tr>"#)
+    } else {
+        bans.iter()
+            .enumerate()
+            .map(|(i, steam_id)| {
+                format!(
+                    r#"<tr class="even">
+    <td>{unique_net_id}</td>
+    <td>{steam_id}</td>
+    <td></td>
+    <td>2024/05/01 18:42:10</td>
+    <td><button type="submit" name="banid" value="plainid_{i}">Delete</button></td>
+</tr>"#,
+                    unique_net_id = steam_unique_net_id(*steam_id),
+                    steam_id = steam_id,
+                    i = i,
+                )
+            })
+            .collect()
+    };
+    page(&format!(
+        r#"<h2>Banned IDs</h2>
+<table id="bans" class="grid">
+    <thead><tr><th>Unique ID</th><th>Steam ID</th><th>Player name</th><th>Timestamp</th><th>&#160;</th></tr></thead>
+    <tbody>
+{}
+    </tbody>
+</table>"#,
+        rows
+    ))
+}
+
+/// Answers each command of a `|` separated chain, the way the console runs
+/// them one after another into the same results.
+fn console_page(game: &MockGame, command: &str) -> String {
+    let results: String = command
+        .split('|')
+        .map(|command| console_results(game, command))
+        .collect();
+    page(&format!(
+        r#"<h2>Management Console</h2>
+<div id="consoleCommand" class="section">
+    <form action="/ServerAdmin/console" method="post">
+        <p><input type="text" name="command" value="{command}" size="80" /><button type="submit">execute</button></p>
+    </form>
+</div>
+<div id="consoleResults" style="">&gt; <span class="command">{command}</span>{results}</div>"#,
+        command = escape(command),
+        results = results
+    ))
+}
+
+fn console_results(game: &MockGame, command: &str) -> String {
+    let mut words = command.split_whitespace();
+    let class = words
+        .next()
+        .filter(|word| word.eq_ignore_ascii_case("getall"))
+        .and(words.next());
+    let property = words.next().unwrap_or_default();
+    let object = |class: &str, index: usize| {
+        format!(
+            "{}.TheWorld:PersistentLevel.{}_{}.{}",
+            game.map_name, class, index, property
+        )
+    };
+    let values: Vec<(String, String)> = match (class.unwrap_or_default(), property) {
+        ("KFGameReplicationInfo", "WaveNum") => {
+            vec![(object("KFGameReplicationInfo", 0), game.wave.to_string())]
+        }
+        ("KFGameReplicationInfo", "bTraderIsOpen") => vec![(
+            object("KFGameReplicationInfo", 0),
+            String::from(if game.trader_open { "True" } else { "False" }),
+        )],
+        ("KFGameReplicationInfo", "AIRemaining") => {
+            vec![(
+                object("KFGameReplicationInfo", 0),
+                game.ai_remaining.to_string(),
+            )]
+        }
+        ("KFGameReplicationInfo", "BossIndex") => {
+            vec![(
+                object("KFGameReplicationInfo", 0),
+                game.boss_index.to_string(),
+            )]
+        }
+        _ => Vec::new(),
+    };
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, (object, value))| {
+            format!(
+                "<br />\n    {}) {} {} = {}",
+                i,
+                class.unwrap_or_default(),
+                object,
+                escape(value)
+            )
+        })
+        .collect()
+}
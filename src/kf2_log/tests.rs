@@ -0,0 +1,633 @@
+#[cfg(test)]
+mod logger_tests {
+    use crate::args::{
+        Kf2AfkArgs, Kf2AnnounceArgs, Kf2BanSyncArgs, Kf2ConfigHistoryArgs, Kf2ServerArgs,
+        Kf2WelcomeArgs,
+    };
+    use crate::kf2_database::memory::MemoryStore;
+    use crate::kf2_event::EventKind;
+    use crate::kf2_log::logger::afk::AfkAction;
+    use crate::kf2_log::logger::control::ControlAction;
+    use crate::kf2_log::logger::{Boss, Kf2Logger};
+    use crate::kf2_log::mock::{MockPlayer, MockWebAdmin};
+    use crate::kf2_scrape::models::KfDifficulty;
+    use crate::kf2_server_log::parse::LogLine;
+    use crate::kf2_webhook::sender::{WebhookFormat, WebhookTarget, Webhooks};
+    use std::sync::{Arc, Mutex};
+
+    const USERNAME: &str = "admin";
+    const PASSWORD: &str = "kissa123";
+    const SERVER_ID: &str = "kf2-eu-1";
+
+    async fn start() -> (MockWebAdmin, Kf2Logger<MemoryStore>) {
+        let server = MockWebAdmin::start(USERNAME, PASSWORD).await.unwrap();
+        let logger = connect(&server, PASSWORD).await.unwrap();
+        (server, logger)
+    }
+
+    async fn connect(
+        server: &MockWebAdmin,
+        password: &str,
+    ) -> Result<Kf2Logger<MemoryStore>, Box<dyn std::error::Error>> {
+        let args = Kf2ServerArgs {
+            server_ip: server.url(),
+            server_id: String::from(SERVER_ID),
+            username: String::from(USERNAME),
+            password: String::from(password),
+        };
+        Kf2Logger::new_session(args, MemoryStore::default()).await
+    }
+
+    /// Runs the logging steps in the same order as the main loop.
+    async fn tick(logger: &mut Kf2Logger<MemoryStore>) {
+        logger.log_unique_players().await.unwrap();
+        logger.loq_in_game_players().await.unwrap();
+        logger.log_game_session().await.unwrap();
+        logger.log_wave_session().await.unwrap();
+        logger.log_player_sessions().await.unwrap();
+    }
+
+    fn join_lobby(server: &MockWebAdmin) {
+        server.game(|game| {
+            game.add_player(MockPlayer::new("koira", "Berserker", 76561198039614177));
+            game.add_player(MockPlayer::new("Tom & Jerry", "Medic", 76561198000740875));
+        });
+    }
+
+    /// Plays waves until the given wave has been cleared.
+    async fn play_until(server: &MockWebAdmin, logger: &mut Kf2Logger<MemoryStore>, wave: u16) {
+        while server.game(|game| game.wave) < wave {
+            server.game(|game| game.start_wave(20));
+            tick(logger).await;
+            server.game(|game| game.clear_wave());
+            tick(logger).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login() {
+        let (server, mut logger) = start().await;
+        assert_eq!(server.logins(), 1);
+        tick(&mut logger).await;
+        assert!(logger.store().game_sessions.is_empty());
+        assert!(logger.store().unique_players.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_login_wrong_password() {
+        let server = MockWebAdmin::start(USERNAME, PASSWORD).await.unwrap();
+        assert!(connect(&server, "koira").await.is_err());
+        assert_eq!(server.logins(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_players_join() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        tick(&mut logger).await;
+
+        let store = logger.store();
+        assert_eq!(store.unique_players.len(), 2);
+        assert_eq!(store.ip_addresses.len(), 2);
+        assert_eq!(store.current_players.len(), 2);
+        assert!(store.unique_players.iter().any(|p| p.name == "Tom & Jerry"));
+        // The game is saved once somebody has killed a zed
+        assert!(store.game_sessions.is_empty());
+        assert!(store.player_sessions.is_empty());
+
+        server.game(|game| game.remove_player("koira"));
+        tick(&mut logger).await;
+        assert_eq!(logger.store().current_players.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_waves_advance() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        tick(&mut logger).await;
+        play_until(&server, &mut logger, 3).await;
+
+        let store = logger.store();
+        assert_eq!(store.game_sessions.len(), 1);
+        let game_session = &store.game_sessions[0];
+        assert_eq!(game_session.map_name, "KF-BurningParis");
+        assert_eq!(game_session.reached_wave, 3);
+        assert_eq!(game_session.players_at_most, 2);
+
+        assert_eq!(store.player_sessions.len(), 2);
+        assert!(store.player_sessions.iter().all(|p| p.kills == 30));
+        assert_eq!(store.maps_played(76561198039614177), 1);
+
+        let waves: Vec<u16> = store.wave_sessions.iter().map(|w| w.wave).collect();
+        assert_eq!(waves, vec![1, 2, 3]);
+        assert!(store
+            .wave_sessions
+            .iter()
+            .all(|w| w.trader_opened_at.is_some()));
+        assert!(store.wave_sessions[..2]
+            .iter()
+            .all(|w| w.ended_at.is_some()));
+        assert_eq!(store.wave_sessions[2].ended_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_boss_spawns() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        tick(&mut logger).await;
+        let max_waves = server.game(|game| game.max_waves);
+        play_until(&server, &mut logger, max_waves).await;
+        server.game(|game| game.spawn_boss());
+        tick(&mut logger).await;
+
+        let store = logger.store();
+        assert_eq!(store.game_sessions.len(), 1);
+        assert_eq!(store.game_sessions[0].boss, Boss::KingFleshpound);
+        assert_eq!(store.game_sessions[0].reached_wave, max_waves + 1);
+        assert_eq!(
+            store.wave_sessions.last().map(|w| w.wave),
+            Some(max_waves + 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_restart() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        tick(&mut logger).await;
+        play_until(&server, &mut logger, 2).await;
+
+        server.restart("KF-Outpost");
+        join_lobby(&server);
+        tick(&mut logger).await;
+        assert_eq!(server.logins(), 2);
+        play_until(&server, &mut logger, 1).await;
+
+        let store = logger.store();
+        let maps: Vec<&str> = store
+            .game_sessions
+            .iter()
+            .map(|g| g.map_name.as_str())
+            .collect();
+        assert_eq!(maps, vec!["KF-BurningParis", "KF-Outpost"]);
+        assert!(store.game_sessions.iter().all(|g| g.server == SERVER_ID));
+        assert_eq!(store.game_sessions[0].reached_wave, 2);
+        assert_eq!(store.player_sessions.len(), 4);
+        assert_eq!(store.maps_played(76561198000740875), 2);
+    }
+
+    /// Starts a webhook receiver that collects the names of received events.
+    async fn receive_webhooks() -> (url::Url, Arc<Mutex<Vec<String>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let received = events.clone();
+        let router = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move |axum::Json(body): axum::Json<serde_json::Value>| {
+                let name = body["event"].as_str().unwrap_or_default().to_string();
+                received.lock().unwrap().push(name);
+                async {}
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let url = url::Url::parse(&format!("http://{}/hook", address)).unwrap();
+        (url, events)
+    }
+
+    #[tokio::test]
+    async fn test_webhooks() {
+        let (url, events) = receive_webhooks().await;
+        let (server, mut logger) = start().await;
+        let target = WebhookTarget {
+            url,
+            format: WebhookFormat::Json,
+            secret: None,
+        };
+        logger
+            .events()
+            .spawn_sink(Webhooks::with_targets(vec![target], None).unwrap());
+        join_lobby(&server);
+        tick(&mut logger).await;
+        play_until(&server, &mut logger, 2).await;
+        server.game(|game| {
+            game.remove_player("koira");
+            game.remove_player("Tom & Jerry");
+        });
+        tick(&mut logger).await;
+
+        let expected = [
+            "new_player",
+            "new_player",
+            "game_started",
+            "wave_reached",
+            "server_empty",
+            "game_ended",
+        ];
+        for _ in 0..50 {
+            if events.lock().unwrap().len() >= expected.len() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let mut events = events.lock().unwrap().clone();
+        events.sort();
+        let mut expected = expected.map(String::from).to_vec();
+        expected.sort();
+        assert_eq!(events, expected);
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let (server, mut logger) = start().await;
+        let mut receiver = logger.events().subscribe();
+        join_lobby(&server);
+        tick(&mut logger).await;
+        play_until(&server, &mut logger, 2).await;
+        server.game(|game| {
+            game.players[0].perk = String::from("Support");
+            game.remove_player("Tom & Jerry");
+        });
+        tick(&mut logger).await;
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event.kind.clone());
+        }
+        let names: Vec<&str> = events.iter().map(|event| event.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "player_joined",
+                "player_joined",
+                "game_started",
+                "wave_advanced",
+                "player_left",
+                "perk_changed",
+            ]
+        );
+        assert_eq!(
+            events[0],
+            EventKind::PlayerJoined {
+                steam_id: 76561198039614177,
+                name: String::from("koira"),
+                first_seen: true,
+            }
+        );
+        assert_eq!(
+            events[3],
+            EventKind::WaveAdvanced {
+                wave: 2,
+                boss: None
+            }
+        );
+        assert_eq!(
+            events[5],
+            EventKind::PerkChanged {
+                steam_id: 76561198039614177,
+                name: String::from("koira"),
+                from: String::from("Berserker"),
+                to: String::from("Support"),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_log_events() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        tick(&mut logger).await;
+        play_until(&server, &mut logger, 1).await;
+        let mut receiver = logger.events().subscribe();
+
+        // The output is ahead of the webadmin, which then catches up
+        server.game(|game| game.remove_player("Tom & Jerry"));
+        tick(&mut logger).await;
+        logger.server_log_line(LogLine::PlayerJoined {
+            name: String::from("Tom & Jerry"),
+        });
+        server.game(|game| {
+            game.add_player(MockPlayer::new("Tom & Jerry", "Medic", 76561198000740875))
+        });
+        tick(&mut logger).await;
+        // Players never scraped have no Steam id to emit
+        logger.server_log_line(LogLine::PlayerJoined {
+            name: String::from("kissa"),
+        });
+        // A map load is not a game start, that waits for the scrape
+        logger.server_log_line(LogLine::MapLoaded {
+            map: String::from("KF-Outpost"),
+        });
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if event.kind != EventKind::Snapshot {
+                events.push(event.kind.clone());
+            }
+        }
+        assert_eq!(
+            events,
+            vec![
+                EventKind::PlayerLeft {
+                    steam_id: 76561198000740875,
+                    name: String::from("Tom & Jerry"),
+                },
+                EventKind::PlayerJoined {
+                    steam_id: 76561198000740875,
+                    name: String::from("Tom & Jerry"),
+                    first_seen: false,
+                },
+                EventKind::MapLoaded {
+                    map: String::from("KF-Outpost"),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_control_change_map() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        logger
+            .control(
+                ControlAction::ChangeMap {
+                    map: String::from("KF-Outpost"),
+                    game_type: None,
+                    difficulty: Some(KfDifficulty::HellOnEarth),
+                },
+                "cli",
+            )
+            .await
+            .unwrap();
+        let (map_name, game_type, difficulty) = server.game(|game| {
+            (
+                game.map_name.clone(),
+                game.game_type.clone(),
+                game.difficulty.clone(),
+            )
+        });
+        assert_eq!(map_name, "KF-Outpost");
+        assert_eq!(game_type, "Survival");
+        assert_eq!(difficulty, "Hell on Earth");
+
+        server.game(|game| game.difficulty = String::from("Suicidal"));
+        logger
+            .control(ControlAction::RestartMap, "cli")
+            .await
+            .unwrap();
+        assert_eq!(server.game(|game| game.difficulty.clone()), "Suicidal");
+
+        let records = &logger.store().control_actions;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].action, "change_map");
+        assert_eq!(
+            records[0].target.as_deref(),
+            Some("KF-Outpost, Hell on Earth")
+        );
+        assert_eq!(records[1].action, "restart_map");
+        assert!(records.iter().all(|record| record.error.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_control_players() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        let message = String::from("Trader closes in 10 & counting");
+        logger
+            .control(ControlAction::Broadcast { message }, "cli")
+            .await
+            .unwrap();
+        assert_eq!(server.messages(), vec!["Trader closes in 10 & counting"]);
+
+        logger
+            .control(
+                ControlAction::Kick {
+                    steam_id: 76561198039614177,
+                },
+                "cli",
+            )
+            .await
+            .unwrap();
+        logger
+            .control(
+                ControlAction::Ban {
+                    steam_id: 76561198000740875,
+                },
+                "cli",
+            )
+            .await
+            .unwrap();
+        assert!(server.game(|game| game.players.is_empty()));
+        assert_eq!(server.bans(), vec![76561198000740875]);
+
+        // Players no longer on the server cannot be kicked
+        let kick = ControlAction::Kick {
+            steam_id: 76561198039614177,
+        };
+        assert!(logger.control(kick, "cli").await.is_err());
+        let record = logger.store().control_actions.last().unwrap();
+        assert_eq!(record.action, "kick");
+        assert_eq!(record.target.as_deref(), Some("76561198039614177"));
+        assert!(record.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_ban_sync() {
+        let (server, mut logger) = start().await;
+        join_lobby(&server);
+        tick(&mut logger).await;
+        server.ban(76561198000740875);
+        let path = std::env::temp_dir().join(format!("kf2-bans-{}.txt", rand::random::<u64>()));
+        std::fs::write(&path, "# Shared bans\n76561198039614177 koira\n").unwrap();
+        logger
+            .set_ban_sync(Kf2BanSyncArgs {
+                interval: std::time::Duration::ZERO,
+                file: Some(path.clone()),
+                database: None,
+                ips: true,
+            })
+            .unwrap();
+
+        logger.sync_bans().await.unwrap();
+        assert_eq!(server.bans(), vec![76561198000740875, 76561198039614177]);
+        assert_eq!(server.ip_bans(), vec!["10.0.0.178"]);
+        let bans = &logger.store().bans;
+        assert_eq!(bans.len(), 2);
+        assert!(bans.iter().all(|(ban_server, _)| ban_server == SERVER_ID));
+        assert_eq!(bans[1].1.unique_net_id, "0x0110000104BAC2E1");
+
+        // Bans already on the server are not added again
+        logger.sync_bans().await.unwrap();
+        assert_eq!(server.bans().len(), 2);
+        assert_eq!(server.ip_bans().len(), 1);
+        let records = &logger.store().control_actions;
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.source == "ban_sync"));
+
+        // Denied addresses are kept in the store over a restart
+        logger
+            .set_ban_sync(Kf2BanSyncArgs {
+                interval: std::time::Duration::ZERO,
+                file: Some(path.clone()),
+                database: None,
+                ips: true,
+            })
+            .unwrap();
+        logger.sync_bans().await.unwrap();
+        assert_eq!(server.ip_bans().len(), 1);
+        assert_eq!(logger.store().ip_bans.len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lifted_ban() {
+        let (server, mut logger) = start().await;
+        server.ban(76561198000740875);
+        let path = std::env::temp_dir().join(format!("kf2-bans-{}.txt", rand::random::<u64>()));
+        std::fs::write(&path, "76561198039614177 koira\n").unwrap();
+        logger
+            .set_ban_sync(Kf2BanSyncArgs {
+                interval: std::time::Duration::ZERO,
+                file: Some(path.clone()),
+                database: None,
+                ips: false,
+            })
+            .unwrap();
+        logger.sync_bans().await.unwrap();
+        assert_eq!(logger.store().bans.len(), 2);
+
+        // A ban lifted on the webadmin stays lifted and leaves the store
+        server.unban(76561198000740875);
+        logger.sync_bans().await.unwrap();
+        assert_eq!(server.bans(), vec![76561198039614177]);
+        let bans = &logger.store().bans;
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0].1.steam_id, Some(76561198039614177));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_announcements() {
+        let (server, mut logger) = start().await;
+        let path = std::env::temp_dir().join(format!("kf2-announce-{}.txt", rand::random::<u64>()));
+        std::fs::write(
+            &path,
+            "every 15m {top_player} leads on {map} with {top_kills} kills\n",
+        )
+        .unwrap();
+        logger
+            .set_announcements(Kf2AnnounceArgs { path: path.clone() })
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Nobody to announce to
+        tick(&mut logger).await;
+        logger.announce().await;
+        assert!(server.messages().is_empty());
+
+        join_lobby(&server);
+        server.game(|game| game.players[0].kills = 25);
+        tick(&mut logger).await;
+        logger.announce().await;
+        logger.announce().await;
+        assert_eq!(
+            server.messages(),
+            vec!["koira leads on KF-BurningParis with 25 kills"]
+        );
+        let record = logger.store().control_actions.last().unwrap();
+        assert_eq!(record.source, "announce");
+    }
+
+    #[tokio::test]
+    async fn test_welcome() {
+        let (server, mut logger) = start().await;
+        logger
+            .set_welcome(Kf2WelcomeArgs {
+                new_message: Some(String::from("Welcome {name}!")),
+                back_message: Some(String::from("Welcome back {name}, your {nth} map")),
+                milestone_message: None,
+                milestones: Vec::new(),
+            })
+            .unwrap();
+        // Players already on the server are not greeted
+        join_lobby(&server);
+        tick(&mut logger).await;
+        assert!(server.messages().is_empty());
+
+        server.game(|game| game.add_player(MockPlayer::new("kissa", "Support", 76561198000000001)));
+        play_until(&server, &mut logger, 1).await;
+        server.game(|game| game.remove_player("koira"));
+        tick(&mut logger).await;
+        server
+            .game(|game| game.add_player(MockPlayer::new("koira", "Berserker", 76561198039614177)));
+        tick(&mut logger).await;
+        assert_eq!(
+            server.messages(),
+            vec!["Welcome kissa!", "Welcome back koira, your 2nd map"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_afk() {
+        let (server, mut logger) = start().await;
+        logger.set_afk(Kf2AfkArgs {
+            waves: 2,
+            action: AfkAction::Record,
+        });
+        join_lobby(&server);
+        for _ in 0..3 {
+            server.game(|game| game.start_wave(20));
+            tick(&mut logger).await;
+            logger.track_afk().await.unwrap();
+            server.game(|game| {
+                game.clear_wave();
+                // Tom & Jerry stays in spawn
+                game.players[1].kills = 0;
+                game.players[1].dosh = 300;
+            });
+            tick(&mut logger).await;
+            logger.track_afk().await.unwrap();
+        }
+        assert!(logger.store().afk_periods.is_empty());
+
+        server.game(|game| game.players[1].kills = 1);
+        tick(&mut logger).await;
+        logger.track_afk().await.unwrap();
+        let periods = &logger.store().afk_periods;
+        assert_eq!(periods.len(), 1);
+        let session = logger
+            .store()
+            .player_sessions
+            .iter()
+            .find(|session| session.db_id == Some(periods[0].player_session_id))
+            .unwrap();
+        assert_eq!(session.steam_id, 76561198000740875);
+    }
+
+    #[tokio::test]
+    async fn test_config_history() {
+        let (server, mut logger) = start().await;
+        logger.set_config_history(Kf2ConfigHistoryArgs {
+            interval: std::time::Duration::ZERO,
+        });
+        logger.snapshot_config().await.unwrap();
+        logger.snapshot_config().await.unwrap();
+        assert_eq!(logger.store().config_versions.len(), 1);
+        let (version_server, config) = &logger.store().config_versions[0];
+        assert_eq!(version_server, SERVER_ID);
+        assert_eq!(config["general.settings_GameDifficulty"], "1.000000");
+        assert_eq!(config["map_cycle.mapcycle"], "KF-BurningParis\nKF-Outpost");
+        assert_eq!(config["mutators.mutator"], "");
+        assert!(!config.contains_key("general.settings_GamePassword"));
+
+        server.game(|game| {
+            game.difficulty = String::from("Suicidal");
+            game.max_waves = 10;
+        });
+        logger.snapshot_config().await.unwrap();
+        let versions = &logger.store().config_versions;
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].1["general.settings_GameDifficulty"], "2.000000");
+        assert_eq!(versions[1].1["general.settings_GameLength"], "2");
+    }
+}
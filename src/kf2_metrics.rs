@@ -0,0 +1,151 @@
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Prometheus metrics of the logger, served at `/metrics` by the API.
+struct Metrics {
+    registry: Registry,
+    current_players: IntGauge,
+    current_wave: IntGauge,
+    max_players: IntGauge,
+    scrape_duration: HistogramVec,
+    parse_failures: IntCounterVec,
+    db_write_duration: HistogramVec,
+    login_attempts: IntCounterVec,
+    game_sessions_started: IntCounterVec,
+    game_sessions_finished: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some(String::from("kf2")), None)?;
+        let metrics = Self {
+            current_players: IntGauge::new("current_players", "Players in the game")?,
+            current_wave: IntGauge::new("current_wave", "Current wave")?,
+            max_players: IntGauge::new("max_players", "Player slots on the server")?,
+            scrape_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "scrape_duration_seconds",
+                    "Duration of webadmin page requests",
+                ),
+                &["page"],
+            )?,
+            parse_failures: IntCounterVec::new(
+                Opts::new(
+                    "parse_failures_total",
+                    "Webadmin pages that failed to parse",
+                ),
+                &["page"],
+            )?,
+            db_write_duration: HistogramVec::new(
+                HistogramOpts::new("db_write_duration_seconds", "Duration of database writes"),
+                &["operation"],
+            )?,
+            login_attempts: IntCounterVec::new(
+                Opts::new("login_attempts_total", "Webadmin login attempts"),
+                &["result"],
+            )?,
+            game_sessions_started: IntCounterVec::new(
+                Opts::new("game_sessions_started_total", "Game sessions started"),
+                &["map", "difficulty"],
+            )?,
+            game_sessions_finished: IntCounterVec::new(
+                Opts::new("game_sessions_finished_total", "Game sessions finished"),
+                &["map", "difficulty"],
+            )?,
+            registry,
+        };
+        let collectors: Vec<Box<dyn Collector>> = vec![
+            Box::new(metrics.current_players.clone()),
+            Box::new(metrics.current_wave.clone()),
+            Box::new(metrics.max_players.clone()),
+            Box::new(metrics.scrape_duration.clone()),
+            Box::new(metrics.parse_failures.clone()),
+            Box::new(metrics.db_write_duration.clone()),
+            Box::new(metrics.login_attempts.clone()),
+            Box::new(metrics.game_sessions_started.clone()),
+            Box::new(metrics.game_sessions_finished.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector)?;
+        }
+        Ok(metrics)
+    }
+}
+
+static METRICS: LazyLock<Metrics> =
+    LazyLock::new(|| Metrics::new().expect("Could not register metrics"));
+
+pub(crate) fn set_current_players(players: usize) {
+    METRICS.current_players.set(players as i64);
+}
+
+pub(crate) fn set_game(current_wave: u16, max_players: u16) {
+    METRICS.current_wave.set(current_wave as i64);
+    METRICS.max_players.set(max_players as i64);
+}
+
+pub(crate) fn observe_scrape(page: &str, duration: Duration) {
+    METRICS
+        .scrape_duration
+        .with_label_values(&[page])
+        .observe(duration.as_secs_f64());
+}
+
+pub(crate) fn parse_failure(page: &str) {
+    METRICS.parse_failures.with_label_values(&[page]).inc();
+}
+
+pub(crate) fn observe_db_write(operation: &str, duration: Duration) {
+    METRICS
+        .db_write_duration
+        .with_label_values(&[operation])
+        .observe(duration.as_secs_f64());
+}
+
+pub(crate) fn login_attempt(success: bool) {
+    let result = if success { "success" } else { "failure" };
+    METRICS.login_attempts.with_label_values(&[result]).inc();
+}
+
+pub(crate) fn game_session_started(map: &str, difficulty: &str) {
+    METRICS
+        .game_sessions_started
+        .with_label_values(&[map, difficulty])
+        .inc();
+}
+
+pub(crate) fn game_session_finished(map: &str, difficulty: &str) {
+    METRICS
+        .game_sessions_finished
+        .with_label_values(&[map, difficulty])
+        .inc();
+}
+
+/// Metrics in the Prometheus text format.
+pub(crate) fn render() -> Result<String, prometheus::Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&METRICS.registry.gather(), &mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+#[cfg(test)]
+mod tests_metrics {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        set_current_players(5);
+        observe_scrape("info", Duration::from_millis(120));
+        game_session_started("KF-Metrics-Test", "Hell on Earth");
+        let metrics = render().unwrap();
+        assert!(metrics.contains("# TYPE kf2_current_players gauge"));
+        assert!(metrics.contains("kf2_scrape_duration_seconds_count{page=\"info\"}"));
+        assert!(metrics.contains(
+            "kf2_game_sessions_started_total{difficulty=\"Hell on Earth\",map=\"KF-Metrics-Test\"} 1"
+        ));
+    }
+}
@@ -0,0 +1,38 @@
+pub(super) mod a2s;
+#[cfg(test)]
+pub(super) mod mock;
+mod tests;
+
+use crate::args::Kf2QueryArgs;
+use a2s::A2sClient;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Prints what the server answers to each query.
+pub(crate) async fn print(args: Kf2QueryArgs) -> Result<(), Box<dyn Error>> {
+    let client = A2sClient::new(args.address, args.timeout);
+    let info = client.info().await?;
+    println!("{} ({} {})", info.name, info.game, info.version);
+    println!(
+        "Map {}, {}/{} players, {} bots{}",
+        info.map,
+        info.players,
+        info.max_players,
+        info.bots,
+        if info.password { ", password" } else { "" }
+    );
+    if let Some(port) = info.port {
+        println!("Game port {}", port);
+    }
+    for player in client.players().await? {
+        println!(
+            "  {:<24} {:>6} {:>6.0}s",
+            player.name, player.score, player.duration
+        );
+    }
+    let rules: BTreeMap<String, String> = client.rules().await?.into_iter().collect();
+    for (name, value) in rules {
+        println!("  {} = {}", name, value);
+    }
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+/// Header of a response that fits in one packet.
+const SINGLE_PACKET: i32 = -1;
+/// Header of a response split into several packets.
+const MULTI_PACKET: i32 = -2;
+/// Largest packet servers send.
+const MAX_PACKET_SIZE: usize = 1400;
+
+const A2S_INFO: u8 = 0x54;
+const A2S_PLAYER: u8 = 0x55;
+const A2S_RULES: u8 = 0x56;
+const S2C_CHALLENGE: u8 = 0x41;
+const INFO_RESPONSE: u8 = 0x49;
+const PLAYER_RESPONSE: u8 = 0x44;
+const RULES_RESPONSE: u8 = 0x45;
+
+/// Answer to A2S_INFO. Fields of the extra data flag that KF2 does not use
+/// are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct A2sInfo {
+    pub(crate) name: String,
+    pub(crate) map: String,
+    pub(crate) folder: String,
+    pub(crate) game: String,
+    pub(crate) players: u8,
+    pub(crate) max_players: u8,
+    pub(crate) bots: u8,
+    pub(crate) password: bool,
+    pub(crate) version: String,
+    pub(crate) port: Option<u16>,
+    pub(crate) keywords: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct A2sPlayer {
+    pub(crate) name: String,
+    pub(crate) score: i32,
+    /// Seconds on the server.
+    pub(crate) duration: f32,
+}
+
+/// Reads the little-endian fields of a response.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        if self.data.len() < count {
+            return Err("Query response ended early".into());
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Box<dyn Error>> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, Box<dyn Error>> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, Box<dyn Error>> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    /// Null-terminated string. Player names are not always valid UTF-8.
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        let end = self
+            .data
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or("Query response string not terminated")?;
+        let text = String::from_utf8_lossy(self.take(end)?).into_owned();
+        self.take(1)?;
+        Ok(text)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+}
+
+fn parse_info(data: &[u8]) -> Result<A2sInfo, Box<dyn Error>> {
+    let mut reader = Reader::new(data);
+    let _protocol = reader.u8()?;
+    let name = reader.string()?;
+    let map = reader.string()?;
+    let folder = reader.string()?;
+    let game = reader.string()?;
+    let _app_id = reader.u16()?;
+    let players = reader.u8()?;
+    let max_players = reader.u8()?;
+    let bots = reader.u8()?;
+    let _server_type = reader.u8()?;
+    let _environment = reader.u8()?;
+    let password = reader.u8()? == 1;
+    let _vac = reader.u8()?;
+    let version = reader.string()?;
+    let mut info = A2sInfo {
+        name,
+        map,
+        folder,
+        game,
+        players,
+        max_players,
+        bots,
+        password,
+        version,
+        port: None,
+        keywords: None,
+    };
+    // The extra data flag is optional
+    let Ok(flags) = reader.u8() else {
+        return Ok(info);
+    };
+    if flags & 0x80 != 0 {
+        info.port = Some(reader.u16()?);
+    }
+    if flags & 0x10 != 0 {
+        reader.u64()?;
+    }
+    if flags & 0x40 != 0 {
+        reader.u16()?;
+        reader.string()?;
+    }
+    if flags & 0x20 != 0 {
+        info.keywords = Some(reader.string()?);
+    }
+    Ok(info)
+}
+
+fn parse_players(data: &[u8]) -> Result<Vec<A2sPlayer>, Box<dyn Error>> {
+    let mut reader = Reader::new(data);
+    let count = reader.u8()?;
+    let mut players = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let _index = reader.u8()?;
+        players.push(A2sPlayer {
+            name: reader.string()?,
+            score: reader.i32()?,
+            duration: reader.f32()?,
+        });
+    }
+    Ok(players)
+}
+
+fn parse_rules(data: &[u8]) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut reader = Reader::new(data);
+    let count = reader.u16()?;
+    let mut rules = HashMap::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = reader.string()?;
+        let value = reader.string()?;
+        rules.insert(name, value);
+    }
+    Ok(rules)
+}
+
+/// Client of the Steam server query protocol, which KF2 answers on its
+/// query port, 27015 by default. It works when the webadmin is disabled.
+#[derive(Debug, Clone)]
+pub(crate) struct A2sClient {
+    address: SocketAddr,
+    timeout: Duration,
+}
+
+impl A2sClient {
+    pub(crate) fn new(address: SocketAddr, timeout: Duration) -> Self {
+        Self { address, timeout }
+    }
+
+    pub(crate) async fn info(&self) -> Result<A2sInfo, Box<dyn Error>> {
+        let mut request = vec![0xFF, 0xFF, 0xFF, 0xFF, A2S_INFO];
+        request.extend_from_slice(b"Source Engine Query\0");
+        let data = self.query(request, INFO_RESPONSE).await?;
+        parse_info(&data)
+    }
+
+    pub(crate) async fn players(&self) -> Result<Vec<A2sPlayer>, Box<dyn Error>> {
+        let data = self
+            .query(challenge_request(A2S_PLAYER), PLAYER_RESPONSE)
+            .await?;
+        parse_players(&data)
+    }
+
+    pub(crate) async fn rules(&self) -> Result<HashMap<String, String>, Box<dyn Error>> {
+        let data = self
+            .query(challenge_request(A2S_RULES), RULES_RESPONSE)
+            .await?;
+        parse_rules(&data)
+    }
+
+    /// Sends the request and returns the payload after the response header.
+    /// A challenge from the server is answered by sending the request again
+    /// with the challenge in place of the last four bytes, or appended for
+    /// A2S_INFO.
+    async fn query(&self, mut request: Vec<u8>, response: u8) -> Result<Vec<u8>, Box<dyn Error>> {
+        let bind: SocketAddr = match self.address {
+            SocketAddr::V4(_) => "0.0.0.0:0".parse()?,
+            SocketAddr::V6(_) => "[::]:0".parse()?,
+        };
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect(self.address).await?;
+        // One challenge is expected, a second one means the server is
+        // not accepting ours
+        for _ in 0..2 {
+            socket.send(&request).await?;
+            let payload = timeout(self.timeout, self.receive(&socket))
+                .await
+                .map_err(|_| format!("No answer to query from {}", self.address))??;
+            match payload.split_first() {
+                Some((&S2C_CHALLENGE, challenge)) if challenge.len() == 4 => {
+                    if request[4] == A2S_INFO && request.len() == 25 {
+                        request.extend_from_slice(challenge);
+                    } else {
+                        let start = request.len() - 4;
+                        request[start..].copy_from_slice(challenge);
+                    }
+                }
+                Some((&kind, data)) if kind == response => return Ok(data.to_vec()),
+                Some((kind, _)) => {
+                    return Err(format!("Unexpected query response 0x{:02X}", kind).into())
+                }
+                None => return Err("Empty query response".into()),
+            }
+        }
+        Err("Query challenge not accepted".into())
+    }
+
+    /// Reads one response, joining the packets of a split one.
+    async fn receive(&self, socket: &UdpSocket) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut packets: Vec<Option<Vec<u8>>> = Vec::new();
+        let mut buffer = [0u8; MAX_PACKET_SIZE * 2];
+        loop {
+            let size = socket.recv(&mut buffer).await?;
+            let mut reader = Reader::new(&buffer[..size]);
+            match reader.i32()? {
+                SINGLE_PACKET => return Ok(reader.rest().to_vec()),
+                MULTI_PACKET => (),
+                header => return Err(format!("Invalid query header {}", header).into()),
+            }
+            let id = reader.i32()?;
+            if id < 0 {
+                return Err("Compressed query responses are not supported".into());
+            }
+            let total = reader.u8()? as usize;
+            let number = reader.u8()? as usize;
+            let _size = reader.u16()?;
+            if number >= total {
+                return Err(format!("Query packet {} of {}", number, total).into());
+            }
+            packets.resize(total, None);
+            packets[number] = Some(reader.rest().to_vec());
+            if packets.iter().all(Option::is_some) {
+                let joined: Vec<u8> = packets.into_iter().flatten().flatten().collect();
+                // The joined payload starts with the single packet header
+                let mut reader = Reader::new(&joined);
+                if reader.i32()? != SINGLE_PACKET {
+                    return Err("Invalid split query response".into());
+                }
+                return Ok(reader.rest().to_vec());
+            }
+        }
+    }
+}
+
+fn challenge_request(kind: u8) -> Vec<u8> {
+    vec![0xFF, 0xFF, 0xFF, 0xFF, kind, 0xFF, 0xFF, 0xFF, 0xFF]
+}
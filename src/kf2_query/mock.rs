@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+const CHALLENGE: [u8; 4] = [0x4B, 0x46, 0x32, 0x21];
+
+/// Server state answered by the stand-in.
+#[derive(Debug, Clone)]
+pub(crate) struct MockQueryState {
+    pub(crate) name: String,
+    pub(crate) map: String,
+    pub(crate) max_players: u8,
+    /// Name, score and seconds on the server.
+    pub(crate) players: Vec<(String, i32, f32)>,
+    pub(crate) rules: BTreeMap<String, String>,
+    /// Bytes of payload per packet, splitting larger responses like a
+    /// server with a small MTU.
+    pub(crate) split_size: Option<usize>,
+    /// Drops every request, like a server that is down.
+    pub(crate) silent: bool,
+}
+
+impl Default for MockQueryState {
+    fn default() -> Self {
+        Self {
+            name: String::from("Mock KF2"),
+            map: String::from("KF-BurningParis"),
+            max_players: 6,
+            players: Vec::new(),
+            rules: BTreeMap::new(),
+            split_size: None,
+            silent: false,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(mut self, value: u8) -> Self {
+        self.data.push(value);
+        self
+    }
+
+    fn bytes(mut self, value: &[u8]) -> Self {
+        self.data.extend_from_slice(value);
+        self
+    }
+
+    fn string(self, value: &str) -> Self {
+        self.bytes(value.as_bytes()).u8(0)
+    }
+}
+
+/// In-process stand-in for the query port of a KF2 server. Every query has
+/// to answer a challenge first, as newer servers ask for A2S_INFO too.
+pub(crate) struct MockQueryServer {
+    address: SocketAddr,
+    state: Arc<Mutex<MockQueryState>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockQueryServer {
+    pub(crate) async fn start() -> Result<Self, Box<dyn Error>> {
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let address = socket.local_addr()?;
+        let state = Arc::new(Mutex::new(MockQueryState::default()));
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            let mut buffer = [0u8; 1400];
+            while let Ok((size, peer)) = socket.recv_from(&mut buffer).await {
+                let state = server_state.lock().expect("Mock state poisoned").clone();
+                if state.silent {
+                    continue;
+                }
+                for packet in respond(&buffer[..size], &state) {
+                    if let Err(e) = socket.send_to(&packet, peer).await {
+                        log::error!("Mock query server: {}", e);
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            address,
+            state,
+            handle,
+        })
+    }
+
+    pub(crate) fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub(crate) fn state<R>(&self, script: impl FnOnce(&mut MockQueryState) -> R) -> R {
+        script(&mut self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockQueryState> {
+        self.state.lock().expect("Mock state poisoned")
+    }
+}
+
+impl Drop for MockQueryServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn respond(request: &[u8], state: &MockQueryState) -> Vec<Vec<u8>> {
+    let Some((&kind, body)) = request.get(4..).and_then(|rest| rest.split_first()) else {
+        return Vec::new();
+    };
+    let challenge = body.get(body.len().saturating_sub(4)..);
+    let challenged = match kind {
+        0x54 => body.len() == 24 && challenge == Some(&CHALLENGE[..]),
+        0x55 | 0x56 => challenge == Some(&CHALLENGE[..]),
+        _ => return Vec::new(),
+    };
+    let payload = if !challenged {
+        Writer::default().u8(0x41).bytes(&CHALLENGE)
+    } else {
+        match kind {
+            0x54 => info(state),
+            0x55 => players(state),
+            _ => rules(state),
+        }
+    };
+    let response = Writer::default()
+        .bytes(&[0xFF; 4])
+        .bytes(&payload.data)
+        .data;
+    match state.split_size {
+        Some(size) if challenged => split(&response, size),
+        _ => vec![response],
+    }
+}
+
+fn info(state: &MockQueryState) -> Writer {
+    Writer::default()
+        .u8(0x49)
+        .u8(17)
+        .string(&state.name)
+        .string(&state.map)
+        .string("KFGame")
+        .string("Killing Floor 2")
+        .bytes(&0u16.to_le_bytes())
+        .u8(state.players.len() as u8)
+        .u8(state.max_players)
+        .u8(0)
+        .u8(b'd')
+        .u8(b'l')
+        .u8(0)
+        .u8(1)
+        .string("1099")
+        .u8(0x80 | 0x10 | 0x20)
+        .bytes(&7777u16.to_le_bytes())
+        .bytes(&90071992547409920u64.to_le_bytes())
+        .string("p2,s5,d1")
+}
+
+fn players(state: &MockQueryState) -> Writer {
+    let mut writer = Writer::default().u8(0x44).u8(state.players.len() as u8);
+    for (index, (name, score, duration)) in state.players.iter().enumerate() {
+        writer = writer
+            .u8(index as u8)
+            .string(name)
+            .bytes(&score.to_le_bytes())
+            .bytes(&duration.to_le_bytes());
+    }
+    writer
+}
+
+fn rules(state: &MockQueryState) -> Writer {
+    let mut writer = Writer::default()
+        .u8(0x45)
+        .bytes(&(state.rules.len() as u16).to_le_bytes());
+    for (name, value) in &state.rules {
+        writer = writer.string(name).string(value);
+    }
+    writer
+}
+
+/// Splits the response into multi-packet responses, sent last packet first
+/// since UDP does not keep the order either.
+fn split(response: &[u8], size: usize) -> Vec<Vec<u8>> {
+    let chunks: Vec<&[u8]> = response.chunks(size).collect();
+    let total = chunks.len() as u8;
+    chunks
+        .into_iter()
+        .enumerate()
+        .rev()
+        .map(|(number, chunk)| {
+            Writer::default()
+                .bytes(&(-2i32).to_le_bytes())
+                .bytes(&77i32.to_le_bytes())
+                .u8(total)
+                .u8(number as u8)
+                .bytes(&(size as u16).to_le_bytes())
+                .bytes(chunk)
+                .data
+        })
+        .collect()
+}
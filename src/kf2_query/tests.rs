@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod query_tests {
+    use crate::kf2_query::a2s::{A2sClient, A2sInfo};
+    use crate::kf2_query::mock::MockQueryServer;
+    use std::time::Duration;
+
+    async fn start() -> (MockQueryServer, A2sClient) {
+        let server = MockQueryServer::start().await.unwrap();
+        let client = A2sClient::new(server.address(), Duration::from_secs(2));
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn test_info() {
+        let (server, client) = start().await;
+        server.state(|state| state.players.push((String::from("koira"), 120, 61.5)));
+        assert_eq!(
+            client.info().await.unwrap(),
+            A2sInfo {
+                name: String::from("Mock KF2"),
+                map: String::from("KF-BurningParis"),
+                folder: String::from("KFGame"),
+                game: String::from("Killing Floor 2"),
+                players: 1,
+                max_players: 6,
+                bots: 0,
+                password: false,
+                version: String::from("1099"),
+                port: Some(7777),
+                keywords: Some(String::from("p2,s5,d1")),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_players() {
+        let (server, client) = start().await;
+        assert!(client.players().await.unwrap().is_empty());
+        server.state(|state| {
+            state.players.push((String::from("koira"), 120, 61.5));
+            state.players.push((String::from("Tom & Jerry"), 80, 30.0));
+        });
+        let players = client.players().await.unwrap();
+        assert_eq!(players.len(), 2);
+        assert_eq!(players[1].name, "Tom & Jerry");
+        assert_eq!(players[0].score, 120);
+        assert_eq!(players[0].duration, 61.5);
+    }
+
+    #[tokio::test]
+    async fn test_split_rules() {
+        let (server, client) = start().await;
+        server.state(|state| {
+            for i in 0..40 {
+                state
+                    .rules
+                    .insert(format!("Mutator{}", i), format!("KFMutator.Value{}", i));
+            }
+            state.split_size = Some(200);
+        });
+        let rules = client.rules().await.unwrap();
+        assert_eq!(rules.len(), 40);
+        assert_eq!(rules["Mutator39"], "KFMutator.Value39");
+    }
+
+    #[tokio::test]
+    async fn test_no_answer() {
+        let (server, _) = start().await;
+        server.state(|state| state.silent = true);
+        let client = A2sClient::new(server.address(), Duration::from_millis(100));
+        let error = client.info().await.unwrap_err();
+        assert!(error.to_string().starts_with("No answer to query"));
+    }
+}
@@ -1,2 +1,3 @@
 pub(super) mod models;
 pub(super) mod parse;
+mod tests;
@@ -1,4 +1,4 @@
-use std::{error::Error, net::IpAddr};
+use std::{fmt, net::IpAddr, str::FromStr};
 
 use crate::kf2_log::logger::Boss;
 
@@ -15,25 +15,31 @@ pub(crate) enum Perk {
     Survivalist,
     Swat,
     NotSelected,
+    Other(String),
 }
 
 impl Perk {
-    pub(super) fn map(input: &str) -> Result<Self, Box<dyn Error>> {
-        let mut input = input.to_lowercase();
-        input.retain(|c| !c.is_whitespace());
-        match input.as_str() {
-            "berserker" => Ok(Perk::Berserker),
-            "commando" => Ok(Perk::Commando),
-            "support" => Ok(Perk::Support),
-            "fieldmedic" => Ok(Perk::FieldMedic),
-            "demolitionist" => Ok(Perk::Demolitionist),
-            "firebug" => Ok(Perk::Firebug),
-            "gunslinger" => Ok(Perk::Gunslinger),
-            "sharpshooter" => Ok(Perk::Sharpshooter),
-            "survivalist" => Ok(Perk::Survivalist),
-            "swat" => Ok(Perk::Swat),
-            "" => Ok(Perk::NotSelected),
-            _ => Err("Invalid perk".into()),
+    /// Maps the perk name shown in webadmin. Perks added by mods are kept as
+    /// `Other` so the player row is not dropped.
+    pub(super) fn map(input: &str) -> Self {
+        let mut key = input.to_lowercase();
+        key.retain(|c| !c.is_whitespace());
+        match key.as_str() {
+            "berserker" => Perk::Berserker,
+            "commando" => Perk::Commando,
+            "support" => Perk::Support,
+            "fieldmedic" => Perk::FieldMedic,
+            "demolitionist" => Perk::Demolitionist,
+            "firebug" => Perk::Firebug,
+            "gunslinger" => Perk::Gunslinger,
+            "sharpshooter" => Perk::Sharpshooter,
+            "survivalist" => Perk::Survivalist,
+            "swat" => Perk::Swat,
+            "" => Perk::NotSelected,
+            _ => {
+                log::warn!("Unknown perk {}", input);
+                Perk::Other(input.trim().to_string())
+            }
         }
     }
 }
@@ -52,6 +58,7 @@ impl ToString for Perk {
             Perk::Survivalist => String::from("Survivalist"),
             Perk::Swat => String::from("Swat"),
             Perk::NotSelected => String::from("Not Selected"),
+            Perk::Other(perk) => perk.clone(),
         }
     }
 }
@@ -65,6 +72,7 @@ pub(crate) struct PlayerInGame {
     pub(crate) kills: u32,
     pub(crate) ping: u32,
     pub(crate) admin: bool,
+    pub(crate) player_key: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -75,6 +83,7 @@ pub(crate) struct PlayerInfo {
     pub(crate) unique_net_id: String,
     pub(crate) steam_id: u64,
     pub(crate) admin: bool,
+    pub(crate) player_key: Option<String>,
 }
 
 pub(super) enum PlayerData {
@@ -110,18 +119,64 @@ pub(crate) enum KfDifficulty {
     Hard,
     Suicidal,
     HellOnEarth,
+    Other(String),
 }
 
 impl KfDifficulty {
-    pub(super) fn map(input: &str) -> Result<Self, Box<dyn Error>> {
-        let mut input = input.to_lowercase();
-        input.retain(|c| !c.is_whitespace());
-        match input.as_str() {
+    /// Maps the difficulty shown in webadmin. Custom difficulties are kept as
+    /// `Other`.
+    pub(super) fn map(input: &str) -> Self {
+        let mut key = input.to_lowercase();
+        key.retain(|c| !c.is_whitespace());
+        match key.as_str() {
+            "normal" => KfDifficulty::Normal,
+            "hard" => KfDifficulty::Hard,
+            "suicidal" => KfDifficulty::Suicidal,
+            "hellonearth" => KfDifficulty::HellOnEarth,
+            _ => {
+                log::warn!("Unknown difficulty {}", input);
+                KfDifficulty::Other(input.trim().to_string())
+            }
+        }
+    }
+
+    /// Index used by `?Difficulty=` in the map url.
+    pub(crate) fn value(&self) -> Option<u8> {
+        match self {
+            KfDifficulty::Normal => Some(0),
+            KfDifficulty::Hard => Some(1),
+            KfDifficulty::Suicidal => Some(2),
+            KfDifficulty::HellOnEarth => Some(3),
+            KfDifficulty::Other(_) => None,
+        }
+    }
+
+    /// Difficulty of a `?Difficulty=` index or `GameDifficulty` setting.
+    pub(crate) fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(KfDifficulty::Normal),
+            1 => Some(KfDifficulty::Hard),
+            2 => Some(KfDifficulty::Suicidal),
+            3 => Some(KfDifficulty::HellOnEarth),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for KfDifficulty {
+    type Err = String;
+
+    /// Parses a difficulty given by the user. Unlike `map`, only the four
+    /// difficulties of the game are accepted.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut key = s.to_lowercase();
+        key.retain(|c| c.is_alphanumeric());
+        match key.as_str() {
             "normal" => Ok(KfDifficulty::Normal),
             "hard" => Ok(KfDifficulty::Hard),
             "suicidal" => Ok(KfDifficulty::Suicidal),
-            "hellonearth" => Ok(KfDifficulty::HellOnEarth),
-            _ => Err(format!("Unknown difficulty {}", input).into()),
+            "hellonearth" | "hoe" => Ok(KfDifficulty::HellOnEarth),
+            _ => Err(format!("Unknown difficulty {}", s)),
         }
     }
 }
@@ -133,6 +188,104 @@ impl ToString for KfDifficulty {
             KfDifficulty::Hard => String::from("Hard"),
             KfDifficulty::Suicidal => String::from("Suicidal"),
             KfDifficulty::HellOnEarth => String::from("Hell on Earth"),
+            KfDifficulty::Other(difficulty) => difficulty.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum GameMode {
+    Survival,
+    Endless,
+    Weekly,
+    Objective,
+    Versus,
+    Other(String),
+}
+
+impl GameMode {
+    /// Detects the game mode from the game type shown in webadmin, e.g.
+    /// `Survival`, `Endless`, `Weekly Outbreak` or `Versus Survival`.
+    pub(super) fn map(game_type: &str) -> Self {
+        let key = game_type.to_lowercase();
+        if key.contains("weekly") {
+            GameMode::Weekly
+        } else if key.contains("endless") {
+            GameMode::Endless
+        } else if key.contains("versus") {
+            GameMode::Versus
+        } else if key.contains("objective") {
+            GameMode::Objective
+        } else if key.contains("survival") {
+            GameMode::Survival
+        } else {
+            log::warn!("Unknown game mode {}", game_type);
+            GameMode::Other(game_type.trim().to_string())
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameMode::Survival => write!(f, "Survival"),
+            GameMode::Endless => write!(f, "Endless"),
+            GameMode::Weekly => write!(f, "Weekly"),
+            GameMode::Objective => write!(f, "Objective"),
+            GameMode::Versus => write!(f, "Versus"),
+            GameMode::Other(game_mode) => write!(f, "{}", game_mode),
+        }
+    }
+}
+
+/// Maps `KFGameReplicationInfo.CurrentWeeklyIndex` to the outbreak name.
+pub(crate) fn weekly_outbreak(index: u8) -> String {
+    match index {
+        0 => String::from("Boom"),
+        1 => String::from("Cranium Cracker"),
+        2 => String::from("Tiny Terror"),
+        3 => String::from("Bobble Zed"),
+        4 => String::from("Poundemonium"),
+        5 => String::from("Up, Up and Decay"),
+        6 => String::from("Zed Time"),
+        7 => String::from("Beefcake"),
+        8 => String::from("Bloodthirst"),
+        9 => String::from("Coliseum"),
+        10 => String::from("Arachnophobia"),
+        11 => String::from("Scavenger"),
+        12 => String::from("Wild West London"),
+        13 => String::from("Abandon All Hope"),
+        14 => String::from("Boss Rush"),
+        15 => String::from("Shrunken Heads"),
+        n => format!("Outbreak {}", n),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum VersusTeam {
+    Human,
+    Zed,
+}
+
+impl VersusTeam {
+    /// Maps the `Team` reference of a `KFPlayerReplicationInfo`, e.g.
+    /// `KFTeamInfo_Zeds'KF-BurningParis.TheWorld:PersistentLevel.KFTeamInfo_Zeds_0'`.
+    pub(crate) fn map(team: &str) -> Option<Self> {
+        if team.contains("KFTeamInfo_Zeds") {
+            Some(VersusTeam::Zed)
+        } else if team.contains("KFTeamInfo_Human") {
+            Some(VersusTeam::Human)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for VersusTeam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersusTeam::Human => write!(f, "Human"),
+            VersusTeam::Zed => write!(f, "Zed"),
         }
     }
 }
@@ -146,7 +299,65 @@ pub(crate) struct GameInfo {
     pub(crate) map_name: String,
     pub(crate) difficulty: KfDifficulty,
     pub(crate) game_type: String,
+    pub(crate) game_mode: GameMode,
     pub(crate) boss: Boss,
+    pub(crate) weekly_outbreak: Option<String>,
+    pub(crate) objective: Option<String>,
+}
+
+/// Row of the webadmin bans page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BanInfo {
+    pub(crate) unique_net_id: String,
+    pub(crate) steam_id: Option<u64>,
+    pub(crate) name: Option<String>,
+}
+
+impl BanInfo {
+    pub(crate) fn from_steam_id(steam_id: u64) -> Self {
+        Self {
+            unique_net_id: steam_unique_net_id(steam_id),
+            steam_id: Some(steam_id),
+            name: None,
+        }
+    }
+}
+
+/// Upper half of the unique net id of every Steam account. Steam ids are the
+/// same number, so the unique net id is the Steam id in hex.
+const STEAM_ID_PREFIX: u64 = 0x0110000100000000;
+
+pub(crate) fn steam_unique_net_id(steam_id: u64) -> String {
+    format!("0x{:016X}", steam_id)
+}
+
+/// Unique net id as the webadmin shows it, a lowercase `0x` and uppercase
+/// hex digits.
+pub(crate) fn normalize_unique_net_id(unique_net_id: &str) -> String {
+    match unique_net_id
+        .strip_prefix("0x")
+        .or_else(|| unique_net_id.strip_prefix("0X"))
+    {
+        Some(hex) => format!("0x{}", hex.to_uppercase()),
+        None => unique_net_id.to_uppercase(),
+    }
+}
+
+/// Steam id of a unique net id, if it belongs to a Steam account.
+pub(crate) fn unique_net_id_steam_id(unique_net_id: &str) -> Option<u64> {
+    let hex = unique_net_id
+        .strip_prefix("0x")
+        .or_else(|| unique_net_id.strip_prefix("0X"))?;
+    u64::from_str_radix(hex, 16)
+        .ok()
+        .filter(|id| id & 0xFFFFFFFF00000000 == STEAM_ID_PREFIX)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WaveInfo {
+    pub(crate) wave: u16,
+    pub(crate) trader_open: bool,
+    pub(crate) ai_remaining: u32,
 }
 
 #[cfg(test)]
@@ -155,22 +366,23 @@ mod tests_perk {
 
     #[test]
     fn test_perk_map_() {
-        let perk = Perk::map("Berserker").unwrap();
+        let perk = Perk::map("Berserker");
         assert_eq!(perk, Perk::Berserker);
 
-        let perk = Perk::map("Field Medic").unwrap();
+        let perk = Perk::map("Field Medic");
         assert_eq!(perk, Perk::FieldMedic);
     }
 
     #[test]
-    fn test_perk_map_error() {
-        let perk = Perk::map("kissa");
-        assert!(perk.is_err());
+    fn test_perk_map_other() {
+        let perk = Perk::map(" Kissa Medic ");
+        assert_eq!(perk, Perk::Other(String::from("Kissa Medic")));
+        assert_eq!(perk.to_string(), "Kissa Medic");
     }
 
     #[test]
     fn test_perk_empty() {
-        let perk = Perk::map("").unwrap();
+        let perk = Perk::map("");
         assert_eq!(perk, Perk::NotSelected);
     }
 
@@ -195,17 +407,18 @@ mod tests_kf_difficulty {
 
     #[test]
     fn test_kf_difficulty_map_() {
-        let difficulty = KfDifficulty::map("Normal").unwrap();
+        let difficulty = KfDifficulty::map("Normal");
         assert_eq!(difficulty, KfDifficulty::Normal);
 
-        let difficulty = KfDifficulty::map("Hell on Earth").unwrap();
+        let difficulty = KfDifficulty::map("Hell on Earth");
         assert_eq!(difficulty, KfDifficulty::HellOnEarth);
     }
 
     #[test]
-    fn test_kf_difficulty_map_error() {
-        let difficulty = KfDifficulty::map("kissa");
-        assert!(difficulty.is_err());
+    fn test_kf_difficulty_map_other() {
+        let difficulty = KfDifficulty::map("Kissa");
+        assert_eq!(difficulty, KfDifficulty::Other(String::from("Kissa")));
+        assert_eq!(difficulty.to_string(), "Kissa");
     }
 
     #[test]
@@ -229,6 +442,7 @@ mod tests_kf_difficulty {
             unique_net_id: String::from(""),
             steam_id: 0,
             admin: false,
+            player_key: None,
         });
         assert_eq!(
             player_data.into_p_info(),
@@ -238,7 +452,8 @@ mod tests_kf_difficulty {
                 ip: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
                 unique_net_id: String::from(""),
                 steam_id: 0,
-                admin: false
+                admin: false,
+                player_key: None,
             })
         )
     }
@@ -253,6 +468,7 @@ mod tests_kf_difficulty {
             kills: 0,
             ping: 0,
             admin: false,
+            player_key: None,
         });
         assert_eq!(
             player_data.into_p_in_game(),
@@ -263,8 +479,66 @@ mod tests_kf_difficulty {
                 health: 0,
                 kills: 0,
                 ping: 0,
-                admin: false
+                admin: false,
+                player_key: None,
             })
         );
     }
 }
+
+#[cfg(test)]
+mod tests_game_mode {
+    use super::*;
+
+    #[test]
+    fn test_game_mode_map() {
+        assert_eq!(GameMode::map("Survival"), GameMode::Survival);
+        assert_eq!(GameMode::map("Endless"), GameMode::Endless);
+        assert_eq!(GameMode::map("Weekly Outbreak"), GameMode::Weekly);
+        assert_eq!(GameMode::map("Objective"), GameMode::Objective);
+        assert_eq!(GameMode::map("Versus Survival"), GameMode::Versus);
+    }
+
+    #[test]
+    fn test_game_mode_map_other() {
+        let game_mode = GameMode::map("Zedternal");
+        assert_eq!(game_mode, GameMode::Other(String::from("Zedternal")));
+        assert_eq!(game_mode.to_string(), "Zedternal");
+    }
+
+    #[test]
+    fn test_weekly_outbreak() {
+        assert_eq!(weekly_outbreak(0), "Boom");
+        assert_eq!(weekly_outbreak(12), "Wild West London");
+        assert_eq!(weekly_outbreak(14), "Boss Rush");
+        assert_eq!(weekly_outbreak(99), "Outbreak 99");
+    }
+
+    #[test]
+    fn test_versus_team_map() {
+        let team =
+            VersusTeam::map("KFTeamInfo_Zeds'KF-Nuked.TheWorld:PersistentLevel.KFTeamInfo_Zeds_0'");
+        assert_eq!(team, Some(VersusTeam::Zed));
+        let team = VersusTeam::map(
+            "KFTeamInfo_Human'KF-Nuked.TheWorld:PersistentLevel.KFTeamInfo_Human_1'",
+        );
+        assert_eq!(team, Some(VersusTeam::Human));
+        assert_eq!(VersusTeam::map("None"), None);
+    }
+}
+
+#[cfg(test)]
+mod tests_unique_net_id {
+    use super::*;
+
+    #[test]
+    fn test_steam_unique_net_id() {
+        assert_eq!(steam_unique_net_id(76561198039614177), "0x0110000104BAC2E1");
+        assert_eq!(
+            unique_net_id_steam_id("0x0110000104bac2e1"),
+            Some(76561198039614177)
+        );
+        assert_eq!(unique_net_id_steam_id("0x00000000DEADBEEF"), None);
+        assert_eq!(unique_net_id_steam_id("76561198039614177"), None);
+    }
+}
@@ -1,23 +1,60 @@
 use crate::kf2_log::logger::Boss;
+use crate::kf2_metrics;
 
-use super::models::{GameInfo, KfDifficulty, Perk, PlayerData, PlayerInGame, PlayerInfo};
+use super::models::{
+    BanInfo, GameInfo, GameMode, KfDifficulty, Perk, PlayerData, PlayerInGame, PlayerInfo,
+};
 use log::error;
 use reqwest::header::HeaderMap;
 use scraper::{ElementRef, Html, Selector};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     error::Error,
     net::{IpAddr, Ipv4Addr},
     str::FromStr,
 };
+use unicode_normalization::UnicodeNormalization;
+
+/// Length of the `VARCHAR(50)` columns text is stored in.
+const MAX_TEXT_LENGTH: usize = 50;
+
+/// Normalises text to NFC, drops control characters and collapses
+/// whitespace runs into single spaces.
+fn normalize_text<'a>(fragments: impl Iterator<Item = &'a str>) -> String {
+    let text: String = fragments.collect::<String>().nfc().collect();
+    text.split_whitespace()
+        .map(|word| word.chars().filter(|c| !c.is_control()).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Truncates text to the column length on a char boundary.
+fn truncate_text(text: String) -> String {
+    match text.char_indices().nth(MAX_TEXT_LENGTH) {
+        Some((index, _)) => text[..index].trim_end().to_string(),
+        None => text,
+    }
+}
+
+/// Decoded text of an HTML fragment, e.g. a console result line.
+fn fragment_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    normalize_text(fragment.root_element().text())
+}
 
 pub(super) struct ElementParse;
 impl ElementParse {
+    /// Text content of the element with entities decoded and markup removed.
+    fn text(element: ElementRef) -> String {
+        normalize_text(element.text())
+    }
+
     fn int<T>(element: Option<ElementRef>, err_msg: &str) -> Result<T, Box<dyn Error>>
     where
         T: FromStr,
     {
-        let s = element.ok_or(err_msg)?.inner_html();
+        let s = Self::text(element.ok_or(err_msg)?);
         if let Ok(int) = s.parse() {
             Ok(int)
         } else {
@@ -27,7 +64,7 @@ impl ElementParse {
     }
 
     fn bool(element: Option<ElementRef>, err_msg: &str) -> Result<bool, Box<dyn Error>> {
-        let s = element.ok_or(err_msg)?.inner_html().to_lowercase();
+        let s = Self::text(element.ok_or(err_msg)?).to_lowercase();
         if s == "yes" {
             Ok(true)
         } else if s == "no" {
@@ -39,12 +76,12 @@ impl ElementParse {
     }
 
     fn string(element: Option<ElementRef>, err_msg: &str) -> Result<String, Box<dyn Error>> {
-        Ok(element.ok_or(err_msg)?.inner_html())
+        Ok(truncate_text(Self::text(element.ok_or(err_msg)?)))
     }
 
     fn ip_addr(element: Option<ElementRef>, err_msg: &str) -> Result<IpAddr, Box<dyn Error>> {
         Ok(IpAddr::V4(
-            element.ok_or(err_msg)?.inner_html().parse::<Ipv4Addr>()?,
+            Self::text(element.ok_or(err_msg)?).parse::<Ipv4Addr>()?,
         ))
     }
 
@@ -52,7 +89,7 @@ impl ElementParse {
         mut td_fields: impl Iterator<Item = ElementRef<'a>>,
     ) -> Result<PlayerInGame, Box<dyn Error>> {
         let name = Self::string(td_fields.next(), "Name tr not found")?;
-        let perk = Perk::map(&Self::string(td_fields.next(), "Perk tr not found")?)?;
+        let perk = Perk::map(&Self::string(td_fields.next(), "Perk tr not found")?);
         let dosh = Self::int(td_fields.next(), "Dosh td not found").unwrap_or(0);
         let health = Self::int(td_fields.next(), "Health td not found").unwrap_or(0);
         let kills = Self::int(td_fields.next(), "Kills td not found").unwrap_or(0);
@@ -66,6 +103,7 @@ impl ElementParse {
             health,
             ping,
             admin,
+            player_key: None,
         })
     }
 
@@ -86,6 +124,7 @@ impl ElementParse {
             unique_net_id,
             steam_id,
             admin,
+            player_key: None,
         })
     }
 }
@@ -115,6 +154,13 @@ impl DocumentExtractor {
         Ok(value.to_string())
     }
 
+    pub(crate) fn is_login_page(&self) -> bool {
+        match Selector::parse(r#"form[id="loginform"]"#) {
+            Ok(selector) => self.document.select(&selector).next().is_some(),
+            Err(_) => false,
+        }
+    }
+
     fn parse_player_table(&self) -> Result<Vec<ElementRef>, Box<dyn Error>> {
         let tr_selector = Selector::parse(r#"table[id="players"] tbody tr"#)?;
         let em_selector = Selector::parse("em")?;
@@ -131,17 +177,32 @@ impl DocumentExtractor {
         }
     }
 
+    /// Webadmin player action forms carry a hidden `playerkey` input that
+    /// identifies the player on the server.
+    fn parse_player_key(tr_player: ElementRef) -> Result<Option<String>, Box<dyn Error>> {
+        let key_selector = Selector::parse(r#"input[name="playerkey"]"#)?;
+        Ok(tr_player
+            .select(&key_selector)
+            .next()
+            .and_then(|input| input.value().attr("value"))
+            .filter(|key| !key.is_empty())
+            .map(|key| key.to_string()))
+    }
+
     fn parse_tr_player(tr_player: ElementRef) -> Result<PlayerData, Box<dyn Error>> {
         let td_selector = Selector::parse("td")?;
         let td_fields = tr_player.select(&td_selector).skip(1);
         let count = td_fields.clone().count();
+        let player_key = Self::parse_player_key(tr_player)?;
         match count {
             7 => {
-                let player_in_game = ElementParse::player_in_game(td_fields)?;
+                let mut player_in_game = ElementParse::player_in_game(td_fields)?;
+                player_in_game.player_key = player_key;
                 Ok(PlayerData::PlayerInGame(player_in_game))
             }
             9 => {
-                let player_info = ElementParse::player_info(td_fields)?;
+                let mut player_info = ElementParse::player_info(td_fields)?;
+                player_info.player_key = player_key;
                 Ok(PlayerData::PlayerInfo(player_info))
             }
             n => {
@@ -157,6 +218,7 @@ impl DocumentExtractor {
             .filter_map(|tr_player| match Self::parse_tr_player(tr_player) {
                 Ok(player) => Some(player),
                 Err(e) => {
+                    kf2_metrics::parse_failure("player_table");
                     log::error!("{}", e);
                     None
                 }
@@ -193,6 +255,100 @@ impl DocumentExtractor {
             .collect()
     }
 
+    /// Rows of the `policy/bans` page. Empty name and Steam id cells are
+    /// left out, e.g. for bans added by unique id only.
+    pub(crate) fn parse_bans(&self) -> Result<Vec<BanInfo>, Box<dyn Error>> {
+        let tr_selector = Selector::parse(r#"table[id="bans"] tbody tr"#)?;
+        let td_selector = Selector::parse("td")?;
+        let em_selector = Selector::parse("em")?;
+        let mut bans = Vec::new();
+        for tr_ban in self.document.select(&tr_selector) {
+            if tr_ban.select(&em_selector).next().is_some() {
+                continue;
+            }
+            let mut td_fields = tr_ban.select(&td_selector);
+            let unique_net_id = ElementParse::string(td_fields.next(), "Unique ID td not found")?;
+            let steam_id = ElementParse::string(td_fields.next(), "Steam ID td not found")?;
+            let name = ElementParse::string(td_fields.next(), "Name td not found")?;
+            bans.push(BanInfo {
+                unique_net_id,
+                steam_id: match steam_id.as_str() {
+                    "" => None,
+                    steam_id => Some(steam_id.parse()?),
+                },
+                name: Some(name).filter(|name| !name.is_empty()),
+            });
+        }
+        Ok(bans)
+    }
+
+    /// Values of the settings form on a `settings/*` page, by field name.
+    /// Passwords are left out. Checkboxes sharing a name, like the mutators,
+    /// list the checked values separated by commas, and an unchecked single
+    /// checkbox is empty. Lines of a text area are trimmed.
+    pub(crate) fn parse_settings(&self) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        let form_selector = Selector::parse(r#"div[id="content"] form:not([id="loginform"])"#)?;
+        let field_selector = Selector::parse("input[name], select[name], textarea[name]")?;
+        let option_selector = Selector::parse("option")?;
+        let form = self
+            .document
+            .select(&form_selector)
+            .next()
+            .ok_or("Settings form not found")?;
+        let mut settings = BTreeMap::new();
+        let mut checked: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for field in form.select(&field_selector) {
+            let element = field.value();
+            let name = element.attr("name").unwrap_or_default().to_string();
+            let value = element.attr("value").unwrap_or_default().to_string();
+            let is_checked = element.attr("checked").is_some();
+            match (element.name(), element.attr("type").unwrap_or("text")) {
+                ("input", "password" | "submit" | "button" | "reset" | "image" | "file") => (),
+                ("input", "checkbox") => {
+                    let values = checked.entry(name).or_default();
+                    if is_checked {
+                        values.push(value);
+                    }
+                }
+                ("input", "radio") => {
+                    if is_checked {
+                        settings.insert(name, value);
+                    }
+                }
+                ("input", _) => {
+                    settings.insert(name, value);
+                }
+                ("select", _) => {
+                    let mut options = field.select(&option_selector);
+                    let selected = field
+                        .select(&option_selector)
+                        .find(|option| option.value().attr("selected").is_some())
+                        .or_else(|| options.next());
+                    let value = selected
+                        .map(|option| match option.value().attr("value") {
+                            Some(value) => value.to_string(),
+                            None => ElementParse::text(option),
+                        })
+                        .unwrap_or_default();
+                    settings.insert(name, value);
+                }
+                _ => {
+                    let text: String = field.text().collect();
+                    let lines: Vec<&str> = text
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .collect();
+                    settings.insert(name, lines.join("\n"));
+                }
+            }
+        }
+        for (name, values) in checked {
+            settings.insert(name, values.join(","));
+        }
+        Ok(settings)
+    }
+
     fn parse_current_game(&self) -> Result<Vec<scraper::element_ref::ElementRef>, Box<dyn Error>> {
         let dd_selector = Selector::parse(r#"dl[id="currentGame"] dd"#)?;
         Ok(self.document.select(&dd_selector).collect())
@@ -214,11 +370,14 @@ impl DocumentExtractor {
             );
             return Err("Current game or rules not found".into());
         }
-        let game_type = current_game[2].inner_html();
-        let map_name = current_game[3].inner_html();
-        let wave = current_rules[0].inner_html();
-        let difficulty = KfDifficulty::map(&current_rules[1].inner_html())?;
-        let players = current_rules[2].inner_html();
+        let game_type = ElementParse::string(current_game.get(2).copied(), "Game type not found")?;
+        let map_name = ElementParse::string(current_game.get(3).copied(), "Map not found")?;
+        let wave = ElementParse::text(current_rules[0]);
+        let difficulty = KfDifficulty::map(&ElementParse::string(
+            current_rules.get(1).copied(),
+            "Difficulty not found",
+        )?);
+        let players = ElementParse::string(current_rules.get(2).copied(), "Players not found")?;
 
         let (current_wave, max_wave) = wave
             .split_once('/')
@@ -232,6 +391,8 @@ impl DocumentExtractor {
         let current_players = current_players.parse()?;
         let max_players = max_players.parse()?;
 
+        let game_mode = GameMode::map(&game_type);
+
         Ok(GameInfo {
             max_waves,
             current_wave,
@@ -240,11 +401,20 @@ impl DocumentExtractor {
             map_name,
             difficulty,
             game_type,
+            game_mode,
             boss: Boss::Undefined,
+            weekly_outbreak: None,
+            objective: None,
         })
     }
 
-    pub(crate) fn parse_current_boss_info(&self) -> Result<u8, Box<dyn Error>> {
+    /// Parses `getall` output lines such as
+    /// `0) KFGameReplicationInfo KF-Elysium.TheWorld:PersistentLevel.KFGameReplicationInfo_105.BossIndex = 3`
+    /// into (object name, value) pairs.
+    pub(crate) fn parse_console_results(
+        &self,
+        property: &str,
+    ) -> Result<Vec<(String, String)>, Box<dyn Error>> {
         let console_results_selector = Selector::parse(r#"div[id="consoleResults"]"#)?;
         let result = self
             .document
@@ -252,13 +422,65 @@ impl DocumentExtractor {
             .next()
             .ok_or("consoleResults not found!")?
             .inner_html();
-        let boss_index = result
-            .split("BossIndex = ")
-            .last()
-            .ok_or("Boss index not found")?
-            .parse()?;
+        let separator = format!(".{} = ", property);
+        let results = result
+            .split("<br>")
+            .filter_map(|line| {
+                let (_, line) = line.trim().split_once(") ")?;
+                let (_, line) = line.split_once(' ')?;
+                let (object, value) = line.split_once(&separator)?;
+                let object = object.rsplit('.').next()?;
+                Some((object.to_string(), fragment_text(value)))
+            })
+            .collect();
+        Ok(results)
+    }
+
+    fn parse_console_result(&self, property: &str) -> Result<String, Box<dyn Error>> {
+        let (_, value) = self
+            .parse_console_results(property)?
+            .pop()
+            .ok_or(format!("{} not found", property))?;
+        Ok(value)
+    }
+
+    pub(crate) fn parse_current_boss_info(&self) -> Result<u8, Box<dyn Error>> {
+        let boss_index = self.parse_console_result("BossIndex")?.parse()?;
         Ok(boss_index)
     }
+
+    pub(crate) fn parse_trader_open(&self) -> Result<bool, Box<dyn Error>> {
+        let trader_open = self.parse_console_result("bTraderIsOpen")?.to_lowercase();
+        match trader_open.as_str() {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            s => Err(format!("Parse bool error. Cannot parse {} into boolean", s).into()),
+        }
+    }
+
+    pub(crate) fn parse_ai_remaining(&self) -> Result<u32, Box<dyn Error>> {
+        let ai_remaining = self.parse_console_result("AIRemaining")?.parse()?;
+        Ok(ai_remaining)
+    }
+
+    pub(crate) fn parse_wave_num(&self) -> Result<u16, Box<dyn Error>> {
+        let wave_num = self.parse_console_result("WaveNum")?.parse()?;
+        Ok(wave_num)
+    }
+
+    pub(crate) fn parse_weekly_index(&self) -> Result<u8, Box<dyn Error>> {
+        let weekly_index = self.parse_console_result("CurrentWeeklyIndex")?.parse()?;
+        Ok(weekly_index)
+    }
+
+    pub(crate) fn parse_current_objective(&self) -> Result<Option<String>, Box<dyn Error>> {
+        let objective = self.parse_console_result("CurrentObjective")?;
+        if objective == "None" {
+            Ok(None)
+        } else {
+            Ok(Some(objective))
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -434,6 +656,40 @@ mod tests_element_parse {
         assert_eq!(r.unwrap(), "Hello, world!");
     }
 
+    #[test]
+    fn test_parse_string_decodes_entities() {
+        let html = get_document_with("Tom &amp; Jerry &lt;3");
+        let e = Some(get_element_ref(&html, "td"));
+        let r = ElementParse::string(e, "");
+        assert_eq!(r.unwrap(), "Tom & Jerry <3");
+    }
+
+    #[test]
+    fn test_parse_string_strips_markup() {
+        let html = get_document_with("<b>koira</b>&#160;<i>kissa</i>\n");
+        let e = Some(get_element_ref(&html, "td"));
+        let r = ElementParse::string(e, "");
+        assert_eq!(r.unwrap(), "koira kissa");
+    }
+
+    #[test]
+    fn test_parse_string_normalizes_unicode() {
+        let html = get_document_with("Ka\u{0308}rppa\u{0007} 🦊");
+        let e = Some(get_element_ref(&html, "td"));
+        let r = ElementParse::string(e, "");
+        assert_eq!(r.unwrap(), "Kärppa 🦊");
+    }
+
+    #[test]
+    fn test_parse_string_truncates_on_char_boundary() {
+        let name = "ä".repeat(40) + &"🦊".repeat(20);
+        let html = get_document_with(&name);
+        let e = Some(get_element_ref(&html, "td"));
+        let r = ElementParse::string(e, "").unwrap();
+        assert_eq!(r.chars().count(), MAX_TEXT_LENGTH);
+        assert_eq!(r, "ä".repeat(40) + &"🦊".repeat(10));
+    }
+
     #[test]
     fn test_parse_player_in_game() {
         let doc = r#"
@@ -475,6 +731,33 @@ mod tests_element_parse {
         assert_eq!(r.admin, false);
     }
 
+    #[test]
+    fn test_parse_player_in_game_custom_perk() {
+        let doc = r#"
+        <table id="players" class="grid" width="100%">
+        <tbody>
+        <tr class="even">
+        <td style="background: transparent; color: transparent;">&#160;</td>
+        <td>Kissa</td>
+        <td>Hemogoblin</td>
+        <td class="right">460</td>
+        <td class="right">83</td>
+        <td class="right">86</td>
+        <td class="right" title="Packet loss: ">36</td>
+        <td class="center">No</td>
+        </tr>
+        </tbody>
+        </table>"#;
+        let html = Html::parse_fragment(doc);
+        let e = Some(get_element_ref(&html, "tr")).unwrap();
+        let td_selector = Selector::parse("td").unwrap();
+        let td_fields = e.select(&td_selector).skip(1);
+        let r = ElementParse::player_in_game(td_fields).unwrap();
+        assert_eq!(r.name, "Kissa");
+        assert_eq!(r.perk, Perk::Other(String::from("Hemogoblin")));
+        assert_eq!(r.kills, 86);
+    }
+
     #[test]
     fn test_parse_player_info() {
         let doc = r#"
@@ -759,7 +1042,12 @@ mod tests_document_extractor {
                 <td></td>
                 <td class="center">Yes</td>
                 <td class="center">Yes</td>
-                <td></td>
+                <td>
+                    <form method="post" action="/ServerAdmin/current/players">
+                        <input type="hidden" name="playerkey" value="2_0x011000049" />
+                        <button type="submit" name="action" value="kick">Kick</button>
+                    </form>
+                </td>
             </tr>
             <tr class="even">
                 <td style="background: transparent; color: transparent;">&#160;</td>
@@ -793,6 +1081,10 @@ mod tests_document_extractor {
     }
 
     fn get_current_boss_document(boss: Boss) -> String {
+        get_console_document("BossIndex", &boss.value().to_string())
+    }
+
+    fn get_console_document(property: &str, value: &str) -> String {
         format!(
             r#"
         <!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd">
@@ -804,16 +1096,17 @@ mod tests_document_extractor {
             <p><em>Note:</em> not all console commands will return information. This does not mean that they were not
                 executed.</p>
             <form action="/ServerAdmin/console" method="post">
-                <p><input type="text" name="command" value="getall KFGameReplicationInfo BossIndex" size="80" /><button
+                <p><input type="text" name="command" value="getall KFGameReplicationInfo {property}" size="80" /><button
                         type="submit">execute</button></p>
             </form>
         </div>
-        <div id="consoleResults" style="">&gt; <span class="command">getall KFGameReplicationInfo BossIndex</span><br />
-            0) KFGameReplicationInfo KF-Elysium.TheWorld:PersistentLevel.KFGameReplicationInfo_105.BossIndex = {boss_index}</div>
+        <div id="consoleResults" style="">&gt; <span class="command">getall KFGameReplicationInfo {property}</span><br />
+            0) KFGameReplicationInfo KF-Elysium.TheWorld:PersistentLevel.KFGameReplicationInfo_105.{property} = {value}</div>
         </div>
         </body>
         </html>"#,
-            boss_index = boss.value()
+            property = property,
+            value = value
         )
     }
 
@@ -841,6 +1134,17 @@ mod tests_document_extractor {
         assert!(token.is_err());
     }
 
+    #[test]
+    fn test_is_login_page() {
+        let document = get_form_token_document("kissa123");
+        let extractor = DocumentExtractor::new(&document);
+        assert!(extractor.is_login_page());
+
+        let document = get_player_table_document(true);
+        let extractor = DocumentExtractor::new(&document);
+        assert!(!extractor.is_login_page());
+    }
+
     #[test]
     fn test_parse_player_table() {
         let document = get_player_table_document(true);
@@ -895,6 +1199,8 @@ mod tests_document_extractor {
         assert!(players[0].unique_net_id == "asdasd123");
         assert!(players[0].steam_id == 123123123);
         assert!(players[0].admin == false);
+        assert!(players[0].player_key.is_none());
+        assert!(players[1].player_key == Some(String::from("2_0x011000049")));
         assert!(players.last().unwrap().name == "` CRÆZY");
         assert!(players.last().unwrap().ping == 0);
     }
@@ -923,6 +1229,26 @@ mod tests_document_extractor {
         assert!(boss != Boss::Abomination.value());
     }
 
+    #[test]
+    fn test_parse_current_boss_unknown() {
+        let document = get_current_boss_document(Boss::Unknown(7));
+        let extractor = DocumentExtractor::new(&document);
+        let boss = extractor.parse_current_boss_info().unwrap();
+        assert_eq!(Boss::map(&boss), Boss::Unknown(7));
+    }
+
+    #[test]
+    fn test_boss_value_round_trip() {
+        for boss in [
+            Boss::HansVolter,
+            Boss::Abomination,
+            Boss::Undefined,
+            Boss::Unknown(5),
+        ] {
+            assert_eq!(Boss::map(&boss.value()), boss);
+        }
+    }
+
     #[test]
     fn test_parse_current_boss_empty() {
         let document = get_steam_player_table_document(false);
@@ -930,4 +1256,84 @@ mod tests_document_extractor {
         let boss = extractor.parse_current_boss_info();
         assert!(boss.is_err());
     }
+
+    #[test]
+    fn test_parse_trader_open() {
+        let document = get_console_document("bTraderIsOpen", "True");
+        let extractor = DocumentExtractor::new(&document);
+        assert!(extractor.parse_trader_open().unwrap());
+
+        let document = get_console_document("bTraderIsOpen", "False");
+        let extractor = DocumentExtractor::new(&document);
+        assert!(!extractor.parse_trader_open().unwrap());
+    }
+
+    #[test]
+    fn test_parse_trader_open_error() {
+        let document = get_console_document("bTraderIsOpen", "Kissa");
+        let extractor = DocumentExtractor::new(&document);
+        let trader_open = extractor.parse_trader_open();
+        assert!(trader_open.is_err());
+    }
+
+    #[test]
+    fn test_parse_ai_remaining() {
+        let document = get_console_document("AIRemaining", "37");
+        let extractor = DocumentExtractor::new(&document);
+        let ai_remaining = extractor.parse_ai_remaining().unwrap();
+        assert_eq!(ai_remaining, 37);
+    }
+
+    #[test]
+    fn test_parse_wave_num() {
+        let document = get_console_document("WaveNum", "4");
+        let extractor = DocumentExtractor::new(&document);
+        let wave_num = extractor.parse_wave_num().unwrap();
+        assert_eq!(wave_num, 4);
+    }
+
+    #[test]
+    fn test_parse_wave_num_wrong_property() {
+        let document = get_console_document("AIRemaining", "4");
+        let extractor = DocumentExtractor::new(&document);
+        let wave_num = extractor.parse_wave_num();
+        assert!(wave_num.is_err());
+    }
+
+    #[test]
+    fn test_parse_console_results_multiple() {
+        let document = r#"<div id="consoleResults" style="">&gt; <span class="command">getall KFPlayerReplicationInfo Team</span><br />
+            0) KFPlayerReplicationInfoVersus KF-Nuked.TheWorld:PersistentLevel.KFPlayerReplicationInfoVersus_3.Team = KFTeamInfo_Human'KF-Nuked.TheWorld:PersistentLevel.KFTeamInfo_Human_1'<br />
+            1) KFPlayerReplicationInfoVersus KF-Nuked.TheWorld:PersistentLevel.KFPlayerReplicationInfoVersus_4.Team = KFTeamInfo_Zeds'KF-Nuked.TheWorld:PersistentLevel.KFTeamInfo_Zeds_0'</div>"#;
+        let extractor = DocumentExtractor::new(document);
+        let results = extractor.parse_console_results("Team").unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "KFPlayerReplicationInfoVersus_3");
+        assert_eq!(
+            results[0].1,
+            "KFTeamInfo_Human'KF-Nuked.TheWorld:PersistentLevel.KFTeamInfo_Human_1'"
+        );
+        assert_eq!(results[1].0, "KFPlayerReplicationInfoVersus_4");
+    }
+
+    #[test]
+    fn test_parse_current_objective() {
+        let document = get_console_document("CurrentObjective", "None");
+        let extractor = DocumentExtractor::new(&document);
+        assert_eq!(extractor.parse_current_objective().unwrap(), None);
+
+        let document = get_console_document("CurrentObjective", "KFMapObjective_AreaDefense_2");
+        let extractor = DocumentExtractor::new(&document);
+        assert_eq!(
+            extractor.parse_current_objective().unwrap(),
+            Some(String::from("KFMapObjective_AreaDefense_2"))
+        );
+    }
+
+    #[test]
+    fn test_parse_weekly_index() {
+        let document = get_console_document("CurrentWeeklyIndex", "14");
+        let extractor = DocumentExtractor::new(&document);
+        assert_eq!(extractor.parse_weekly_index().unwrap(), 14);
+    }
 }
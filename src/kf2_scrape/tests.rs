@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod fixture_tests {
+    use crate::kf2_scrape::parse::DocumentExtractor;
+    use std::{env, fmt::Debug, fs, path::PathBuf};
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    fn get_fixture(name: &str) -> DocumentExtractor {
+        let path = fixture_path(name);
+        let text = fs::read_to_string(&path)
+            .unwrap_or_else(|_| panic!("Could not read fixture {}", path.display()));
+        DocumentExtractor::new(&text)
+    }
+
+    /// Compares the value with `tests/fixtures/<name>.snap`. Run the tests with
+    /// `UPDATE_SNAPSHOTS=1` to rewrite the snapshots after a parser change.
+    fn assert_snapshot<T: Debug>(name: &str, value: T) {
+        let path = fixture_path(&format!("{}.snap", name));
+        let actual = format!("{:#?}\n", value);
+        if env::var("UPDATE_SNAPSHOTS").is_ok() {
+            fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "Could not read snapshot {}, run with UPDATE_SNAPSHOTS=1",
+                path.display()
+            )
+        });
+        assert_eq!(actual, expected, "Snapshot {} does not match", name);
+    }
+
+    fn assert_info_page_snapshot(name: &str) {
+        let document = get_fixture(&format!("{}.html", name));
+        let game_info = document.parse_current_map_info().map_err(|e| e.to_string());
+        assert_snapshot(&format!("{}.game_info", name), game_info);
+        assert_snapshot(
+            &format!("{}.players", name),
+            document.parse_in_game_player_info(),
+        );
+    }
+
+    #[test]
+    fn test_fixture_login() {
+        let document = get_fixture("login.html");
+        assert_eq!(document.parse_form_token().unwrap(), "7E2F1C8A9B3D4E5F");
+        assert!(document.parse_in_game_player_info().is_empty());
+    }
+
+    #[test]
+    fn test_fixture_error_page() {
+        let document = get_fixture("error_page_not_found.html");
+        assert!(document.parse_form_token().is_err());
+        assert!(document.parse_current_map_info().is_err());
+        assert!(document.parse_in_game_player_info().is_empty());
+        assert!(document.parse_steam_player_info().is_empty());
+        assert!(document.parse_current_boss_info().is_err());
+    }
+
+    #[test]
+    fn test_fixture_info_empty_server() {
+        assert_info_page_snapshot("info_empty_server");
+    }
+
+    #[test]
+    fn test_fixture_info_full_lobby() {
+        assert_info_page_snapshot("info_full_lobby");
+    }
+
+    #[test]
+    fn test_fixture_info_trader_time() {
+        assert_info_page_snapshot("info_trader_time");
+    }
+
+    #[test]
+    fn test_fixture_info_boss_wave() {
+        assert_info_page_snapshot("info_boss_wave");
+    }
+
+    #[test]
+    fn test_fixture_info_weekly_outbreak() {
+        assert_info_page_snapshot("info_weekly_outbreak");
+    }
+
+    #[test]
+    fn test_fixture_info_plain_layout() {
+        assert_info_page_snapshot("info_plain_layout");
+    }
+
+    #[test]
+    fn test_fixture_players_full_lobby() {
+        let document = get_fixture("players_full_lobby.html");
+        assert_snapshot(
+            "players_full_lobby.players",
+            document.parse_steam_player_info(),
+        );
+    }
+
+    #[test]
+    fn test_fixture_players_empty_server() {
+        let document = get_fixture("players_empty_server.html");
+        assert!(document.parse_steam_player_info().is_empty());
+    }
+
+    #[test]
+    fn test_fixture_policy_bans() {
+        let document = get_fixture("policy_bans.html");
+        assert_snapshot("policy_bans.bans", document.parse_bans().unwrap());
+        let document = get_fixture("players_empty_server.html");
+        assert!(document.parse_bans().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fixture_settings() {
+        for name in ["settings_general", "settings_maplist", "settings_mutators"] {
+            let document = get_fixture(&format!("{}.html", name));
+            assert_snapshot(
+                &format!("{}.settings", name),
+                document.parse_settings().unwrap(),
+            );
+        }
+        assert!(get_fixture("login.html").parse_settings().is_err());
+        assert!(get_fixture("error_page_not_found.html")
+            .parse_settings()
+            .is_err());
+    }
+
+    #[test]
+    fn test_fixture_console() {
+        let document = get_fixture("console_boss_index.html");
+        assert_eq!(document.parse_current_boss_info().unwrap(), 3);
+        let document = get_fixture("console_trader_open.html");
+        assert!(document.parse_trader_open().unwrap());
+        let document = get_fixture("console_ai_remaining.html");
+        assert_eq!(document.parse_ai_remaining().unwrap(), 0);
+    }
+}
@@ -0,0 +1,46 @@
+pub(super) mod parse;
+mod tail;
+
+use crate::args::Kf2ServerLogArgs;
+use log::warn;
+use parse::{parse_line, LogLine};
+use std::thread;
+use tail::LogTail;
+use tokio::sync::mpsc;
+
+/// Lines buffered before the tailing waits for the logger.
+const CAPACITY: usize = 256;
+
+/// Tails the server output on its own thread, as the file reads block, and
+/// sends the parsed lines until the receiver is dropped.
+pub(crate) fn spawn(args: Kf2ServerLogArgs) -> mpsc::Receiver<LogLine> {
+    let (sender, receiver) = mpsc::channel(CAPACITY);
+    let tail = move || {
+        let mut tail = LogTail::new(args.path);
+        let mut failing = false;
+        loop {
+            thread::sleep(args.poll);
+            let lines = match tail.read_lines() {
+                Ok(lines) => lines,
+                Err(e) => {
+                    if !failing {
+                        warn!("Could not read the server output: {}", e);
+                    }
+                    failing = true;
+                    continue;
+                }
+            };
+            failing = false;
+            for line in lines.iter().filter_map(|line| parse_line(line)) {
+                if sender.blocking_send(line).is_err() {
+                    return;
+                }
+            }
+        }
+    };
+    thread::Builder::new()
+        .name(String::from("server-log"))
+        .spawn(tail)
+        .expect("Server log thread starts");
+    receiver
+}
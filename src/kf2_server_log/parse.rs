@@ -0,0 +1,76 @@
+/// What a line of the server output says happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LogLine {
+    MapLoaded { map: String },
+    PlayerJoined { name: String },
+}
+
+/// Drops the `[0123.45]` uptime prefix and the `Log:` style category.
+fn message(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_end();
+    let line = match line.strip_prefix('[') {
+        Some(rest) => rest.split_once("] ")?.1,
+        None => line,
+    };
+    line.split_once(": ")
+}
+
+/// Parses the lines the tracker follows, both logged by the engine:
+///
+/// - `Log: LoadMap: KF-BurningParis?Game=...` when a map loads
+/// - `Log: Join succeeded: <name>` when a player joins
+///
+/// and everything else is skipped. Waves, leaves and chat are not in the
+/// output without a mutator logging them, so they are left to the scrape.
+pub(crate) fn parse_line(line: &str) -> Option<LogLine> {
+    let (category, text) = message(line)?;
+    if category != "Log" {
+        return None;
+    }
+    if let Some(map) = text.strip_prefix("LoadMap: ") {
+        let map = map.split('?').next().unwrap_or_default().trim();
+        return Some(LogLine::MapLoaded {
+            map: map.to_string(),
+        })
+        .filter(|_| !map.is_empty());
+    }
+    let name = text.strip_prefix("Join succeeded: ")?;
+    Some(LogLine::PlayerJoined {
+        name: name.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests_parse {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let lines = [
+            "[0012.34] Log: LoadMap: KF-BurningParis?Game=KFGameContent.KFGameInfo_Survival",
+            "[0098.91] Log: Join succeeded: Tom & Jerry\r",
+            "[0301.00] Log: Join request: /Game/Maps?Name=koira",
+            "[0302.00] ScriptLog: Say: koira: hello",
+            "[0303.00] Log: LoadMap: ",
+            "Init: Version: 1099",
+            "",
+        ];
+        let parsed: Vec<Option<LogLine>> = lines.into_iter().map(parse_line).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                Some(LogLine::MapLoaded {
+                    map: String::from("KF-BurningParis")
+                }),
+                Some(LogLine::PlayerJoined {
+                    name: String::from("Tom & Jerry")
+                }),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ]
+        );
+    }
+}
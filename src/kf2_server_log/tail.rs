@@ -0,0 +1,145 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Name of the files `server_manager.py` redirects the server output to,
+/// `server_output_<YYYYMMDD>.log`.
+const PREFIX: &str = "server_output_";
+const SUFFIX: &str = ".log";
+
+/// Follows the newest server output file in a directory. A newer file
+/// replaces the followed one when the server is restarted, and a file that
+/// shrinks was truncated and is read again from the start.
+pub(crate) struct LogTail {
+    directory: PathBuf,
+    path: Option<PathBuf>,
+    offset: u64,
+    /// End of the file when it was last read without a line break.
+    partial: Vec<u8>,
+}
+
+impl LogTail {
+    pub(crate) fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            path: None,
+            offset: 0,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Newest file, going by the date in the name.
+    fn newest(&self) -> io::Result<Option<PathBuf>> {
+        let mut newest: Option<PathBuf> = None;
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let is_output = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(PREFIX) && name.ends_with(SUFFIX));
+            if is_output && newest.as_ref().is_none_or(|newest| path > *newest) {
+                newest = Some(path);
+            }
+        }
+        Ok(newest)
+    }
+
+    /// Complete lines written since the last read. The file found on the
+    /// first read is followed from its end, so earlier output is skipped.
+    pub(crate) fn read_lines(&mut self) -> io::Result<Vec<String>> {
+        let newest = self.newest()?;
+        let mut lines = Vec::new();
+        if newest != self.path {
+            match self.path.take() {
+                Some(old) => {
+                    // The rest of the old file, unless it is already gone
+                    match self.read_from(&old) {
+                        Ok(old_lines) => lines = old_lines,
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+                        Err(e) => return Err(e),
+                    }
+                    self.offset = 0;
+                }
+                None => {
+                    self.offset = match &newest {
+                        Some(path) => fs::metadata(path)?.len(),
+                        None => 0,
+                    };
+                }
+            }
+            self.partial.clear();
+            self.path = newest;
+        }
+        if let Some(path) = self.path.clone() {
+            lines.extend(self.read_from(&path)?);
+        }
+        Ok(lines)
+    }
+
+    fn read_from(&mut self, path: &Path) -> io::Result<Vec<String>> {
+        let mut file = File::open(path)?;
+        if file.metadata()?.len() < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut data = std::mem::take(&mut self.partial);
+        self.offset += file.read_to_end(&mut data)? as u64;
+        let complete = match data.iter().rposition(|byte| *byte == b'\n') {
+            Some(end) => end + 1,
+            None => 0,
+        };
+        self.partial = data.split_off(complete);
+        // The server output is not always valid UTF-8
+        Ok(String::from_utf8_lossy(&data)
+            .lines()
+            .map(String::from)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests_tail {
+    use super::*;
+    use std::io::Write;
+
+    fn append(path: &Path, text: &str) {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_follow_rotation() {
+        let directory =
+            std::env::temp_dir().join(format!("kf2-server-log-{}", rand::random::<u64>()));
+        fs::create_dir_all(&directory).unwrap();
+        let first = directory.join("server_output_20261018.log");
+        let second = directory.join("server_output_20261019.log");
+        append(&first, "before the logger started\n");
+        append(
+            &directory.join("logger_output_20261020.log"),
+            "not followed\n",
+        );
+
+        let mut tail = LogTail::new(directory.clone());
+        assert!(tail.read_lines().unwrap().is_empty());
+        append(&first, "one\ntw");
+        assert_eq!(tail.read_lines().unwrap(), vec!["one"]);
+        append(&first, "o\nthree\n");
+        append(&second, "restarted\n");
+        assert_eq!(
+            tail.read_lines().unwrap(),
+            vec!["two", "three", "restarted"]
+        );
+
+        // Started again on the same day
+        fs::write(&second, "again\n").unwrap();
+        assert_eq!(tail.read_lines().unwrap(), vec!["again"]);
+        assert!(tail.read_lines().unwrap().is_empty());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+mod html;
+mod pages;
+
+use crate::args::Kf2SiteArgs;
+use crate::kf2_database::export::ExportFilter;
+use crate::kf2_database::management::KfDbManager;
+use crate::kf2_database::models::{GameSessionDbQ, PlayerDbQ, PlayerSessionDbQ};
+use chrono::NaiveDateTime;
+use log::info;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Everything the site is rendered from, loaded once from the database.
+pub(crate) struct SiteData {
+    pub(crate) players: Vec<PlayerDbQ>,
+    pub(crate) game_sessions: Vec<GameSessionDbQ>,
+    pub(crate) player_sessions: Vec<PlayerSessionDbQ>,
+    pub(crate) generated_at: NaiveDateTime,
+}
+
+impl SiteData {
+    fn load(db: &KfDbManager) -> Result<Self, Box<dyn Error>> {
+        let filter = ExportFilter::default();
+        Ok(Self {
+            players: db.export_unique_players(&filter)?,
+            game_sessions: db.export_game_sessions(&filter)?,
+            player_sessions: db.export_player_sessions(&filter)?,
+            generated_at: chrono::Utc::now().naive_utc(),
+        })
+    }
+}
+
+/// Totals of one player over a set of games.
+struct PlayerStats<'a> {
+    player: &'a PlayerDbQ,
+    kills: u64,
+    games: usize,
+    highest_wave: u16,
+}
+
+/// Site data indexed for rendering. Games are kept newest first.
+struct Site<'a> {
+    data: &'a SiteData,
+    games: Vec<&'a GameSessionDbQ>,
+    games_by_id: HashMap<u32, &'a GameSessionDbQ>,
+    players_by_id: HashMap<u64, &'a PlayerDbQ>,
+    sessions_by_game: HashMap<u32, Vec<&'a PlayerSessionDbQ>>,
+    sessions_by_player: HashMap<u64, Vec<&'a PlayerSessionDbQ>>,
+}
+
+impl<'a> Site<'a> {
+    fn new(data: &'a SiteData) -> Self {
+        let mut games: Vec<&GameSessionDbQ> = data.game_sessions.iter().collect();
+        games.sort_by_key(|game| Reverse(game.started_at));
+        let mut sessions_by_game: HashMap<u32, Vec<&PlayerSessionDbQ>> = HashMap::new();
+        let mut sessions_by_player: HashMap<u64, Vec<&PlayerSessionDbQ>> = HashMap::new();
+        for session in &data.player_sessions {
+            sessions_by_game
+                .entry(session.game_session_id)
+                .or_default()
+                .push(session);
+            sessions_by_player
+                .entry(session.steam_id)
+                .or_default()
+                .push(session);
+        }
+        for sessions in sessions_by_game.values_mut() {
+            sessions.sort_by_key(|session| Reverse(session.kills));
+        }
+        for sessions in sessions_by_player.values_mut() {
+            sessions.sort_by_key(|session| Reverse(session.started_at));
+        }
+        Self {
+            data,
+            games,
+            games_by_id: data.game_sessions.iter().map(|g| (g.id, g)).collect(),
+            players_by_id: data.players.iter().map(|p| (p.steam_id, p)).collect(),
+            sessions_by_game,
+            sessions_by_player,
+        }
+    }
+
+    fn player_name(&self, steam_id: u64) -> &str {
+        self.players_by_id
+            .get(&steam_id)
+            .map(|player| player.name.as_str())
+            .unwrap_or("Unknown")
+    }
+
+    /// Stats of every player, counting only the games on `map` when given.
+    fn player_stats(&self, map: Option<&str>) -> Vec<PlayerStats<'a>> {
+        let mut stats: HashMap<u64, PlayerStats> = HashMap::new();
+        for session in &self.data.player_sessions {
+            let Some(game) = self.games_by_id.get(&session.game_session_id) else {
+                continue;
+            };
+            if map.is_some_and(|map| map != game.map_name) {
+                continue;
+            }
+            let Some(player) = self.players_by_id.get(&session.steam_id) else {
+                continue;
+            };
+            let player_stats = stats.entry(session.steam_id).or_insert(PlayerStats {
+                player,
+                kills: 0,
+                games: 0,
+                highest_wave: 0,
+            });
+            player_stats.kills += session.kills as u64;
+            player_stats.games += 1;
+            player_stats.highest_wave = player_stats.highest_wave.max(game.reached_wave);
+        }
+        stats.into_values().collect()
+    }
+
+    fn write(&self, output: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(output.join("players"))?;
+        fs::create_dir_all(output.join("maps"))?;
+        fs::write(output.join("style.css"), html::STYLE)?;
+        fs::write(output.join("index.html"), self.index())?;
+        fs::write(output.join("leaderboard.html"), self.leaderboard())?;
+        fs::write(output.join("games.html"), self.games_page())?;
+        fs::write(output.join("maps.html"), self.maps())?;
+        fs::write(output.join("players.html"), self.players())?;
+        for map_name in self.map_names() {
+            fs::write(output.join(html::map_file(map_name)), self.map(map_name))?;
+        }
+        for player in &self.data.players {
+            fs::write(
+                output.join(html::player_file(player.steam_id)),
+                self.player(player),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the stats site from the database into the output directory.
+pub(crate) fn run(args: Kf2SiteArgs, db: &KfDbManager) -> Result<(), Box<dyn Error>> {
+    let data = SiteData::load(db)?;
+    Site::new(&data).write(&args.output)?;
+    info!(
+        "Generated site for {} players and {} games in {}",
+        data.players.len(),
+        data.game_sessions.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_site {
+    use super::*;
+
+    fn time(day: u32, hour: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2023, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn player(steam_id: u64, name: &str) -> PlayerDbQ {
+        PlayerDbQ {
+            steam_id,
+            name: String::from(name),
+            maps_played: 2,
+            avg_ping: 40,
+            unique_net_id: String::new(),
+            created: time(1, 18),
+            last_seen: time(2, 22),
+        }
+    }
+
+    fn game(id: u32, map_name: &str, reached_wave: u16, day: u32) -> GameSessionDbQ {
+        GameSessionDbQ {
+            id,
+            max_waves: 7,
+            reached_wave,
+            max_players: 6,
+            players_at_most: 2,
+            map_name: String::from(map_name),
+            difficulty: String::from("Hell on Earth"),
+            game_type: String::from("KFGameContent.KFGameInfo_Survival"),
+            game_mode: String::from("Survival"),
+            boss: String::from("Patriarch"),
+            weekly_outbreak: None,
+            objectives: None,
+            versus_round: None,
+            started_at: time(day, 20),
+            ended_at: Some(time(day, 21)),
+        }
+    }
+
+    fn session(
+        id: u32,
+        game_session_id: u32,
+        steam_id: u64,
+        perk: &str,
+        kills: u32,
+    ) -> PlayerSessionDbQ {
+        PlayerSessionDbQ {
+            id,
+            game_session_id,
+            steam_id,
+            perk: String::from(perk),
+            kills,
+            team: None,
+            started_at: time(game_session_id, 20),
+            ended_at: time(game_session_id, 21),
+        }
+    }
+
+    fn data() -> SiteData {
+        SiteData {
+            players: vec![
+                player(76561198039614177, "koira"),
+                player(76561198000740875, "<b>Tom & Jerry</b>"),
+            ],
+            game_sessions: vec![
+                game(1, "KF-BurningParis", 8, 1),
+                game(2, "KF-Outpost", 4, 2),
+            ],
+            player_sessions: vec![
+                session(1, 1, 76561198039614177, "Berserker", 120),
+                session(2, 1, 76561198000740875, "Field Medic", 80),
+                session(3, 2, 76561198000740875, "Field Medic", 60),
+            ],
+            generated_at: time(3, 12),
+        }
+    }
+
+    #[test]
+    fn test_player_stats() {
+        let data = data();
+        let site = Site::new(&data);
+        let stats = site.player_stats(None);
+        let tom = stats
+            .iter()
+            .find(|stats| stats.player.steam_id == 76561198000740875)
+            .unwrap();
+        assert_eq!((tom.kills, tom.games, tom.highest_wave), (140, 2, 8));
+
+        let outpost = site.player_stats(Some("KF-Outpost"));
+        assert_eq!(outpost.len(), 1);
+        assert_eq!(outpost[0].kills, 60);
+    }
+
+    #[test]
+    fn test_write_site() {
+        let output = std::env::temp_dir().join(format!("kf2-site-{}", rand::random::<u64>()));
+        let data = data();
+        Site::new(&data).write(&output).unwrap();
+
+        for file in [
+            "index.html",
+            "leaderboard.html",
+            "games.html",
+            "maps.html",
+            "players.html",
+            "style.css",
+            "maps/KF-Outpost.html",
+            "players/76561198039614177.html",
+        ] {
+            assert!(output.join(file).exists(), "{} not written", file);
+        }
+        let games = fs::read_to_string(output.join("games.html")).unwrap();
+        assert!(games.contains("&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;"));
+        assert!(!games.contains("<b>Tom"));
+        assert!(games.contains("Field Medic, 80 kills"));
+        // The newest game is listed first
+        assert!(games.find("KF-Outpost").unwrap() < games.find("KF-BurningParis").unwrap());
+
+        let profile = fs::read_to_string(output.join("players/76561198000740875.html")).unwrap();
+        assert!(profile.contains(r#"href="../maps/KF-Outpost.html""#));
+        fs::remove_dir_all(&output).unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::NaiveDateTime;
+
+pub(super) const STYLE: &str = "body { font-family: sans-serif; margin: 0 auto; max-width: 960px; padding: 0 1em; background: #1b1b1b; color: #ddd; }
+a { color: #e8a33d; }
+nav a { margin-right: 1em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #333; }
+td.number, th.number { text-align: right; }
+ul.players { margin: 0; padding-left: 1em; }
+footer { color: #888; font-size: 0.8em; margin: 2em 0; }
+";
+
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub(super) fn time(time: &NaiveDateTime) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// File name for a map page. Map names are file names of the server, but
+/// custom maps can contain anything.
+pub(super) fn map_file(map_name: &str) -> String {
+    let slug: String = map_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("maps/{}.html", slug)
+}
+
+pub(super) fn player_file(steam_id: u64) -> String {
+    format!("players/{}.html", steam_id)
+}
+
+/// Link to another page. `root` is the relative path from the current page
+/// to the site root.
+pub(super) fn link(root: &str, file: &str, text: &str) -> String {
+    format!(r#"<a href="{}{}">{}</a>"#, root, file, escape(text))
+}
+
+/// Table cell: text cells are escaped, numbers are aligned right and HTML
+/// cells are inserted as is.
+pub(super) enum Cell {
+    Text(String),
+    Number(String),
+    Html(String),
+}
+
+impl Cell {
+    pub(super) fn number(value: impl ToString) -> Self {
+        Cell::Number(value.to_string())
+    }
+}
+
+pub(super) fn table(headers: &[&str], rows: Vec<Vec<Cell>>) -> String {
+    let mut html = String::from("<table>\n<tr>");
+    for header in headers {
+        html.push_str(&format!("<th>{}</th>", escape(header)));
+    }
+    html.push_str("</tr>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            match cell {
+                Cell::Text(text) => html.push_str(&format!("<td>{}</td>", escape(&text))),
+                Cell::Number(number) => {
+                    html.push_str(&format!(r#"<td class="number">{}</td>"#, escape(&number)))
+                }
+                Cell::Html(cell) => html.push_str(&format!("<td>{}</td>", cell)),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+pub(super) fn page(root: &str, title: &str, content: &str, generated_at: &NaiveDateTime) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} - Killing Floor 2 stats</title>
+<link rel="stylesheet" href="{root}style.css">
+</head>
+<body>
+<nav>{home}{leaderboard}{games}{maps}{players}</nav>
+<h1>{title}</h1>
+{content}
+<footer>Generated {generated} UTC</footer>
+</body>
+</html>
+"#,
+        title = escape(title),
+        root = root,
+        home = link(root, "index.html", "Home"),
+        leaderboard = link(root, "leaderboard.html", "Leaderboard"),
+        games = link(root, "games.html", "Games"),
+        maps = link(root, "maps.html", "Maps"),
+        players = link(root, "players.html", "Players"),
+        content = content,
+        generated = time(generated_at),
+    )
+}
@@ -0,0 +1,253 @@
+use super::html::{escape, link, map_file, page, player_file, table, time, Cell};
+use super::{PlayerStats, Site};
+use crate::kf2_database::models::{GameSessionDbQ, PlayerDbQ};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+
+const RECENT_GAMES: usize = 100;
+const LEADERBOARD_SIZE: usize = 100;
+const TOP: usize = 10;
+
+fn wave(game: &GameSessionDbQ) -> String {
+    if game.max_waves == 0 {
+        game.reached_wave.to_string()
+    } else {
+        format!("{}/{}", game.reached_wave, game.max_waves)
+    }
+}
+
+impl Site<'_> {
+    pub(super) fn map_names(&self) -> BTreeSet<&str> {
+        self.games
+            .iter()
+            .map(|game| game.map_name.as_str())
+            .collect()
+    }
+
+    fn player_link(&self, root: &str, steam_id: u64) -> String {
+        link(root, &player_file(steam_id), self.player_name(steam_id))
+    }
+
+    /// Games with the perk and kills of every player.
+    fn games_table<'g>(
+        &self,
+        root: &str,
+        games: impl Iterator<Item = &'g GameSessionDbQ>,
+    ) -> String {
+        let rows = games
+            .map(|game| {
+                let players: String = self
+                    .sessions_by_game
+                    .get(&game.id)
+                    .into_iter()
+                    .flatten()
+                    .map(|session| {
+                        format!(
+                            "<li>{} ({}, {} kills)</li>",
+                            self.player_link(root, session.steam_id),
+                            escape(&session.perk),
+                            session.kills
+                        )
+                    })
+                    .collect();
+                vec![
+                    Cell::Text(time(&game.started_at)),
+                    Cell::Html(link(root, &map_file(&game.map_name), &game.map_name)),
+                    Cell::Text(game.difficulty.clone()),
+                    Cell::Text(game.game_mode.clone()),
+                    Cell::Number(wave(game)),
+                    Cell::Html(format!(r#"<ul class="players">{}</ul>"#, players)),
+                ]
+            })
+            .collect();
+        table(
+            &["Started", "Map", "Difficulty", "Mode", "Wave", "Players"],
+            rows,
+        )
+    }
+
+    fn stats_table(&self, root: &str, stats: &[PlayerStats]) -> String {
+        let rows = stats
+            .iter()
+            .enumerate()
+            .map(|(rank, stats)| {
+                vec![
+                    Cell::number(rank + 1),
+                    Cell::Html(self.player_link(root, stats.player.steam_id)),
+                    Cell::number(stats.kills),
+                    Cell::number(stats.games),
+                    Cell::number(stats.highest_wave),
+                ]
+            })
+            .collect();
+        table(&["#", "Player", "Kills", "Games", "Highest wave"], rows)
+    }
+
+    fn ranked(&self, map: Option<&str>, size: usize) -> Vec<PlayerStats<'_>> {
+        let mut stats = self.player_stats(map);
+        stats.sort_by(|a, b| {
+            b.kills
+                .cmp(&a.kills)
+                .then(a.player.steam_id.cmp(&b.player.steam_id))
+        });
+        stats.truncate(size);
+        stats
+    }
+
+    pub(super) fn index(&self) -> String {
+        let content = format!(
+            "<p>{} players, {} games on {} maps.</p>\n<h2>Top killers</h2>\n{}<h2>Recent games</h2>\n{}",
+            self.data.players.len(),
+            self.games.len(),
+            self.map_names().len(),
+            self.stats_table("", &self.ranked(None, TOP)),
+            self.games_table("", self.games.iter().copied().take(TOP)),
+        );
+        page("", "Killing Floor 2", &content, &self.data.generated_at)
+    }
+
+    pub(super) fn leaderboard(&self) -> String {
+        let mut stats = self.player_stats(None);
+        let mut content = String::from("<h2>Kills</h2>\n");
+        stats.sort_by_key(|stats| Reverse(stats.kills));
+        content.push_str(&self.stats_table("", &stats[..stats.len().min(LEADERBOARD_SIZE)]));
+        content.push_str("<h2>Games</h2>\n");
+        stats.sort_by_key(|stats| Reverse(stats.games));
+        content.push_str(&self.stats_table("", &stats[..stats.len().min(LEADERBOARD_SIZE)]));
+        content.push_str("<h2>Highest wave</h2>\n");
+        stats.sort_by_key(|stats| Reverse(stats.highest_wave));
+        content.push_str(&self.stats_table("", &stats[..stats.len().min(LEADERBOARD_SIZE)]));
+        page("", "Leaderboard", &content, &self.data.generated_at)
+    }
+
+    pub(super) fn games_page(&self) -> String {
+        let content = self.games_table("", self.games.iter().copied().take(RECENT_GAMES));
+        page("", "Recent games", &content, &self.data.generated_at)
+    }
+
+    pub(super) fn maps(&self) -> String {
+        let mut maps: HashMap<&str, Vec<&GameSessionDbQ>> = HashMap::new();
+        for game in &self.games {
+            maps.entry(&game.map_name).or_default().push(game);
+        }
+        let mut maps: Vec<_> = maps.into_iter().collect();
+        maps.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+        let rows = maps
+            .into_iter()
+            .map(|(map_name, games)| {
+                let highest_wave = games.iter().map(|g| g.reached_wave).max();
+                vec![
+                    Cell::Html(link("", &map_file(map_name), map_name)),
+                    Cell::number(games.len()),
+                    Cell::number(highest_wave.unwrap_or_default()),
+                    Cell::Text(time(&games[0].started_at)),
+                ]
+            })
+            .collect();
+        let content = table(&["Map", "Games", "Highest wave", "Last played"], rows);
+        page("", "Maps", &content, &self.data.generated_at)
+    }
+
+    pub(super) fn map(&self, map_name: &str) -> String {
+        let games: Vec<&GameSessionDbQ> = self
+            .games
+            .iter()
+            .copied()
+            .filter(|game| game.map_name == map_name)
+            .collect();
+        let content =
+            format!(
+            "<p>{} games, highest wave {}.</p>\n<h2>Top players</h2>\n{}<h2>Recent games</h2>\n{}",
+            games.len(),
+            games.iter().map(|g| g.reached_wave).max().unwrap_or_default(),
+            self.stats_table("../", &self.ranked(Some(map_name), TOP)),
+            self.games_table("../", games.into_iter().take(RECENT_GAMES)),
+        );
+        page("../", map_name, &content, &self.data.generated_at)
+    }
+
+    pub(super) fn players(&self) -> String {
+        let stats: HashMap<u64, PlayerStats> = self
+            .player_stats(None)
+            .into_iter()
+            .map(|stats| (stats.player.steam_id, stats))
+            .collect();
+        let mut players: Vec<&PlayerDbQ> = self.data.players.iter().collect();
+        players.sort_by_key(|player| Reverse(player.last_seen));
+        let rows = players
+            .into_iter()
+            .map(|player| {
+                let stats = stats.get(&player.steam_id);
+                vec![
+                    Cell::Html(link("", &player_file(player.steam_id), &player.name)),
+                    Cell::number(stats.map(|s| s.kills).unwrap_or_default()),
+                    Cell::number(stats.map(|s| s.games).unwrap_or_default()),
+                    Cell::Text(time(&player.last_seen)),
+                ]
+            })
+            .collect();
+        let content = table(&["Player", "Kills", "Games", "Last seen"], rows);
+        page("", "Players", &content, &self.data.generated_at)
+    }
+
+    pub(super) fn player(&self, player: &PlayerDbQ) -> String {
+        let sessions = self
+            .sessions_by_player
+            .get(&player.steam_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut perks: HashMap<&str, (usize, u64)> = HashMap::new();
+        for session in sessions {
+            let perk = perks.entry(&session.perk).or_default();
+            perk.0 += 1;
+            perk.1 += session.kills as u64;
+        }
+        let mut perks: Vec<_> = perks.into_iter().collect();
+        perks.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then(a.0.cmp(b.0)));
+        let perk_rows = perks
+            .into_iter()
+            .map(|(perk, (games, kills))| {
+                vec![
+                    Cell::Text(perk.to_string()),
+                    Cell::number(games),
+                    Cell::number(kills),
+                ]
+            })
+            .collect();
+        let session_rows = sessions
+            .iter()
+            .take(RECENT_GAMES)
+            .filter_map(|session| {
+                let game = self.games_by_id.get(&session.game_session_id)?;
+                Some(vec![
+                    Cell::Text(time(&session.started_at)),
+                    Cell::Html(link("../", &map_file(&game.map_name), &game.map_name)),
+                    Cell::Text(game.difficulty.clone()),
+                    Cell::Number(wave(game)),
+                    Cell::Text(session.perk.clone()),
+                    Cell::number(session.kills),
+                ])
+            })
+            .collect();
+        let kills: u64 = sessions.iter().map(|s| s.kills as u64).sum();
+        let content = format!(
+            r#"<p><a href="https://steamcommunity.com/profiles/{}">Steam profile</a></p>
+<p>First seen {}, last seen {}. {} maps played, {} games with kills and {} kills in total.</p>
+<h2>Perks</h2>
+{}<h2>Recent games</h2>
+{}"#,
+            player.steam_id,
+            time(&player.created),
+            time(&player.last_seen),
+            player.maps_played,
+            sessions.len(),
+            kills,
+            table(&["Perk", "Games", "Kills"], perk_rows),
+            table(
+                &["Started", "Map", "Difficulty", "Wave", "Perk", "Kills"],
+                session_rows
+            ),
+        );
+        page("../", &player.name, &content, &self.data.generated_at)
+    }
+}
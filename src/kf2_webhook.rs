@@ -0,0 +1,3 @@
+pub(super) mod event;
+mod payload;
+pub(super) mod sender;
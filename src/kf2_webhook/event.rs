@@ -0,0 +1,85 @@
+use crate::kf2_event::{EventKind, GameOutcome};
+use serde::Serialize;
+use std::fmt;
+
+/// Something the tracker noticed that webhooks are sent for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum WebhookEvent {
+    GameStarted,
+    WaveReached { wave: u16 },
+    BossReached { boss: String },
+    GameEnded { outcome: GameOutcome },
+    NewPlayer { steam_id: u64, name: String },
+    ServerEmpty,
+    ServerFull,
+    WebAdminUnreachable { error: String },
+}
+
+impl WebhookEvent {
+    /// The webhook sent for a tracker event, if any.
+    pub(crate) fn from_event(kind: &EventKind) -> Option<Self> {
+        let event = match kind {
+            EventKind::GameStarted => WebhookEvent::GameStarted,
+            EventKind::WaveAdvanced {
+                boss: Some(boss), ..
+            } => WebhookEvent::BossReached { boss: boss.clone() },
+            EventKind::WaveAdvanced { wave, boss: None } => {
+                WebhookEvent::WaveReached { wave: *wave }
+            }
+            EventKind::GameEnded { outcome } => WebhookEvent::GameEnded {
+                outcome: outcome.clone(),
+            },
+            EventKind::PlayerJoined {
+                steam_id,
+                name,
+                first_seen: true,
+            } => WebhookEvent::NewPlayer {
+                steam_id: *steam_id,
+                name: name.clone(),
+            },
+            EventKind::ServerEmpty => WebhookEvent::ServerEmpty,
+            EventKind::ServerFull => WebhookEvent::ServerFull,
+            EventKind::WebAdminUnreachable { error } => WebhookEvent::WebAdminUnreachable {
+                error: error.clone(),
+            },
+            EventKind::PlayerJoined { .. }
+            | EventKind::PlayerLeft { .. }
+            | EventKind::PerkChanged { .. }
+            | EventKind::MapLoaded { .. }
+            | EventKind::Snapshot => return None,
+        };
+        Some(event)
+    }
+
+    /// Event name used in the payload and in `WEBHOOK_EVENTS`.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::GameStarted => "game_started",
+            WebhookEvent::WaveReached { .. } => "wave_reached",
+            WebhookEvent::BossReached { .. } => "boss_reached",
+            WebhookEvent::GameEnded { .. } => "game_ended",
+            WebhookEvent::NewPlayer { .. } => "new_player",
+            WebhookEvent::ServerEmpty => "server_empty",
+            WebhookEvent::ServerFull => "server_full",
+            WebhookEvent::WebAdminUnreachable { .. } => "web_admin_unreachable",
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookEvent::GameStarted => write!(f, "Game started"),
+            WebhookEvent::WaveReached { wave } => write!(f, "Wave {} reached", wave),
+            WebhookEvent::BossReached { boss } => write!(f, "Boss wave reached: {}", boss),
+            WebhookEvent::GameEnded { outcome } => write!(f, "Game ended: {}", outcome),
+            WebhookEvent::NewPlayer { name, .. } => write!(f, "New player: {}", name),
+            WebhookEvent::ServerEmpty => write!(f, "Server is empty"),
+            WebhookEvent::ServerFull => write!(f, "Server is full"),
+            WebhookEvent::WebAdminUnreachable { error } => {
+                write!(f, "Webadmin unreachable: {}", error)
+            }
+        }
+    }
+}
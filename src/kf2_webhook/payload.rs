@@ -0,0 +1,133 @@
+use super::event::WebhookEvent;
+use crate::kf2_api::live::{LiveGame, LivePlayer};
+use crate::kf2_event::GameOutcome;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Body of the generic JSON webhook.
+#[derive(Serialize)]
+pub(super) struct JsonPayload<'a> {
+    #[serde(flatten)]
+    pub(super) event: &'a WebhookEvent,
+    pub(super) timestamp: chrono::NaiveDateTime,
+    pub(super) game_session: Option<&'a LiveGame>,
+    pub(super) players: &'a [LivePlayer],
+}
+
+fn color(event: &WebhookEvent) -> u32 {
+    match event {
+        WebhookEvent::GameEnded {
+            outcome: GameOutcome::Won,
+        } => 0x2ecc71,
+        WebhookEvent::GameEnded { .. } | WebhookEvent::WebAdminUnreachable { .. } => 0xe74c3c,
+        WebhookEvent::BossReached { .. } => 0xe67e22,
+        _ => 0x3498db,
+    }
+}
+
+/// Body of a Discord webhook: one embed with the game and the players.
+pub(super) fn discord_payload(payload: &JsonPayload) -> Value {
+    let mut fields = Vec::new();
+    if let Some(game) = payload.game_session {
+        let wave = if game.max_waves == 0 {
+            game.current_wave.to_string()
+        } else {
+            format!("{}/{}", game.current_wave, game.max_waves)
+        };
+        fields.push(json!({"name": "Map", "value": game.map_name, "inline": true}));
+        fields.push(json!({"name": "Difficulty", "value": game.difficulty, "inline": true}));
+        fields.push(json!({"name": "Mode", "value": game.game_mode, "inline": true}));
+        fields.push(json!({"name": "Wave", "value": wave, "inline": true}));
+    }
+    if !payload.players.is_empty() {
+        let players: Vec<String> = payload
+            .players
+            .iter()
+            .map(|p| format!("{} ({}, {} kills)", p.name, p.perk, p.kills))
+            .collect();
+        fields.push(json!({"name": "Players", "value": players.join("\n"), "inline": false}));
+    }
+    json!({
+        "embeds": [{
+            "title": payload.event.to_string(),
+            "color": color(payload.event),
+            "fields": fields,
+            "timestamp": payload.timestamp.and_utc().to_rfc3339(),
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests_payload {
+    use super::*;
+
+    fn game() -> LiveGame {
+        LiveGame {
+            id: Some(7),
+            map_name: String::from("KF-Outpost"),
+            difficulty: String::from("Hard"),
+            game_type: String::from("Survival"),
+            game_mode: String::from("Survival"),
+            boss: String::from("Patriarch"),
+            current_wave: 4,
+            max_waves: 7,
+            reached_wave: 4,
+            max_players: 6,
+            players_at_most: 2,
+            weekly_outbreak: None,
+            objective: None,
+            versus_round: 1,
+            started_at: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    fn players() -> Vec<LivePlayer> {
+        vec![LivePlayer {
+            name: String::from("koira"),
+            perk: String::from("Berserker"),
+            dosh: 300,
+            health: 100,
+            kills: 42,
+            ping: 40,
+            admin: false,
+        }]
+    }
+
+    #[test]
+    fn test_json_payload() {
+        let event = WebhookEvent::GameEnded {
+            outcome: GameOutcome::Lost,
+        };
+        let game = game();
+        let players = players();
+        let payload = JsonPayload {
+            event: &event,
+            timestamp: chrono::NaiveDateTime::default(),
+            game_session: Some(&game),
+            players: &players,
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["event"], "game_ended");
+        assert_eq!(value["outcome"], "Lost");
+        assert_eq!(value["game_session"]["map_name"], "KF-Outpost");
+        assert_eq!(value["players"][0]["kills"], 42);
+    }
+
+    #[test]
+    fn test_discord_payload() {
+        let event = WebhookEvent::WaveReached { wave: 4 };
+        let game = game();
+        let players = players();
+        let payload = JsonPayload {
+            event: &event,
+            timestamp: chrono::NaiveDateTime::default(),
+            game_session: Some(&game),
+            players: &players,
+        };
+        let value = discord_payload(&payload);
+        let embed = &value["embeds"][0];
+        assert_eq!(embed["title"], "Wave 4 reached");
+        assert_eq!(embed["fields"][3]["value"], "4/7");
+        assert_eq!(embed["fields"][4]["value"], "koira (Berserker, 42 kills)");
+    }
+}
@@ -0,0 +1,279 @@
+use super::event::WebhookEvent;
+use super::payload::{discord_payload, JsonPayload};
+use crate::args::Kf2WebhookArgs;
+use crate::kf2_event::{EventSink, TrackerEvent};
+use hmac::{Hmac, Mac};
+use log::{error, warn};
+use reqwest::Client;
+use sha2::Sha256;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// Header with the HMAC-SHA256 of the body, `sha256=<hex>`.
+pub(crate) const SIGNATURE_HEADER: &str = "X-KF2-Signature";
+const RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WebhookFormat {
+    Discord,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct WebhookTarget {
+    pub(crate) url: Url,
+    pub(crate) format: WebhookFormat,
+    pub(crate) secret: Option<String>,
+}
+
+/// Event sink sending webhooks. Each delivery runs in its own task, so a
+/// slow receiver does not hold up the other events.
+#[derive(Clone)]
+pub(crate) struct Webhooks {
+    client: Client,
+    targets: Arc<Vec<WebhookTarget>>,
+    events: Option<Vec<String>>,
+    retry_delay: Duration,
+}
+
+impl Webhooks {
+    pub(crate) fn new(args: Kf2WebhookArgs) -> Result<Self, Box<dyn Error>> {
+        let mut targets = Vec::new();
+        if let Some(url) = args.discord_url {
+            targets.push(WebhookTarget {
+                url,
+                format: WebhookFormat::Discord,
+                secret: None,
+            });
+        }
+        if let Some(url) = args.url {
+            targets.push(WebhookTarget {
+                url,
+                format: WebhookFormat::Json,
+                secret: args.secret,
+            });
+        }
+        Ok(Self::with_targets(targets, args.events)?)
+    }
+
+    pub(crate) fn with_targets(
+        targets: Vec<WebhookTarget>,
+        events: Option<Vec<String>>,
+    ) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            client: Client::builder().timeout(Duration::from_secs(10)).build()?,
+            targets: Arc::new(targets),
+            events,
+            retry_delay: RETRY_DELAY,
+        })
+    }
+
+    fn is_enabled(&self, event: &WebhookEvent) -> bool {
+        match &self.events {
+            Some(events) => events.iter().any(|name| name == event.name()),
+            None => true,
+        }
+    }
+
+    fn notify(&self, event: WebhookEvent, tracker_event: &TrackerEvent) {
+        if !self.is_enabled(&event) {
+            return;
+        }
+        let payload = JsonPayload {
+            event: &event,
+            timestamp: tracker_event.timestamp,
+            game_session: tracker_event.game_session.as_ref(),
+            players: &tracker_event.players,
+        };
+        for target in self.targets.iter() {
+            let body = match target.format {
+                WebhookFormat::Json => serde_json::to_string(&payload),
+                WebhookFormat::Discord => serde_json::to_string(&discord_payload(&payload)),
+            };
+            let body = match body {
+                Ok(body) => body,
+                Err(e) => {
+                    error!("Could not serialize {} webhook: {}", event.name(), e);
+                    continue;
+                }
+            };
+            let (client, target, retry_delay) =
+                (self.client.clone(), target.clone(), self.retry_delay);
+            let name = event.name();
+            tokio::spawn(async move {
+                if let Err(e) = deliver(&client, &target, body, retry_delay).await {
+                    error!("Webhook {} to {} failed: {}", name, target.url, e);
+                }
+            });
+        }
+    }
+}
+
+impl EventSink for Webhooks {
+    fn handle(&mut self, event: &TrackerEvent) {
+        if let Some(webhook_event) = WebhookEvent::from_event(&event.kind) {
+            self.notify(webhook_event, event);
+        }
+    }
+}
+
+pub(crate) fn signature(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// Posts the body, retrying with a doubling delay when the receiver cannot
+/// be reached or answers with a server error or rate limit.
+async fn deliver(
+    client: &Client,
+    target: &WebhookTarget,
+    body: String,
+    retry_delay: Duration,
+) -> Result<(), String> {
+    let mut delay = retry_delay;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let mut request = client
+            .post(target.url.as_str())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(secret) = &target.secret {
+            request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response)
+                if response.status().is_server_error()
+                    || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                format!("status {}", response.status())
+            }
+            Ok(response) => return Err(format!("status {}", response.status())),
+            Err(e) => e.to_string(),
+        };
+        if attempt > RETRIES {
+            return Err(error);
+        }
+        warn!(
+            "Webhook to {} failed ({}), retrying in {:?}",
+            target.url, error, delay
+        );
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests_sender {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Received {
+        requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+        failures: Arc<Mutex<u32>>,
+    }
+
+    async fn receive(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        let mut failures = received.failures.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        received.requests.lock().unwrap().push((headers, body));
+        StatusCode::NO_CONTENT
+    }
+
+    async fn start(received: Received) -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .with_state(received);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Url::parse(&format!("http://{}/hook", address)).unwrap()
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(
+            signature("key", "The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries_and_signs() {
+        let received = Received::default();
+        *received.failures.lock().unwrap() = 2;
+        let target = WebhookTarget {
+            url: start(received.clone()).await,
+            format: WebhookFormat::Json,
+            secret: Some(String::from("kissa123")),
+        };
+        let client = Client::new();
+        let body = String::from(r#"{"event":"server_full"}"#);
+        deliver(&client, &target, body.clone(), Duration::from_millis(10))
+            .await
+            .unwrap();
+
+        let requests = received.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let (headers, received_body) = &requests[0];
+        assert_eq!(received_body, &body);
+        assert_eq!(
+            headers[SIGNATURE_HEADER].to_str().unwrap(),
+            signature("kissa123", &body)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_gives_up() {
+        let received = Received::default();
+        *received.failures.lock().unwrap() = 10;
+        let target = WebhookTarget {
+            url: start(received.clone()).await,
+            format: WebhookFormat::Discord,
+            secret: None,
+        };
+        let result = deliver(
+            &Client::new(),
+            &target,
+            String::from("{}"),
+            Duration::from_millis(1),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(*received.failures.lock().unwrap(), 10 - (RETRIES + 1));
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let webhooks =
+            Webhooks::with_targets(Vec::new(), Some(vec![String::from("game_ended")])).unwrap();
+        assert!(!webhooks.is_enabled(&WebhookEvent::ServerFull));
+        assert!(webhooks.is_enabled(&WebhookEvent::GameEnded {
+            outcome: crate::kf2_event::GameOutcome::Won
+        }));
+    }
+}
@@ -1,31 +1,155 @@
 mod args;
+mod kf2_api;
+mod kf2_config;
 mod kf2_database;
+mod kf2_event;
+mod kf2_export;
 mod kf2_log;
+mod kf2_metrics;
+mod kf2_query;
 mod kf2_scrape;
+mod kf2_server_log;
+mod kf2_site;
+mod kf2_webhook;
 pub mod schema;
 
+use args::Command;
+use kf2_api::live::LiveState;
 use kf2_database::management::KfDbManager;
+use kf2_event::file::EventLog;
 use kf2_log::logger::Kf2Logger;
+use kf2_webhook::sender::Webhooks;
 use log::{error, info};
 use tokio::time::Instant;
 
+/// Shortest time between ticks when the server output asks for early ones.
+const MIN_TICK_GAP: std::time::Duration = std::time::Duration::from_secs(2);
+
 #[tokio::main]
 async fn main() {
     env_logger::init();
-    let (server_args, db_args) = args::parse();
+    let command = args::command().unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, args::USAGE);
+        std::process::exit(2);
+    });
+    match command {
+        Command::Log => log().await,
+        Command::Export(export_args) => {
+            let kf2db = KfDbManager::new_session(args::parse_db()).unwrap();
+            if let Err(err) = kf2_export::run(export_args, &kf2db) {
+                error!("Export failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::GenerateSite(site_args) => {
+            let kf2db = KfDbManager::new_session(args::parse_db()).unwrap();
+            if let Err(err) = kf2_site::run(site_args, &kf2db) {
+                error!("Site generation failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::Control(action) => {
+            let (server_args, db_args) = args::parse_server();
+            let kf2db = KfDbManager::new_session(db_args).unwrap();
+            let mut kf2 = Kf2Logger::new_session(server_args, kf2db).await.unwrap();
+            if kf2.control(action, "cli").await.is_err() {
+                std::process::exit(1);
+            }
+        }
+        Command::Query(query_args) => {
+            if let Err(err) = kf2_query::print(query_args).await {
+                error!("Query failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
 
-    let kf2db = KfDbManager::new_session(db_args).unwrap();
-    let mut kf2 = Kf2Logger::new_session(server_args, kf2db).await.unwrap();
+async fn log() {
+    let args = args::parse();
 
+    let kf2db = KfDbManager::new_session(args.db).unwrap();
+    if let Some(server_config) = args.server_config {
+        let server = args.server.server_id();
+        if let Err(err) = kf2db.log_server_settings(server, server_config.settings()) {
+            error!("Could not save the server settings: {}", err);
+        }
+    }
+    let live = LiveState::default();
+    let mut kf2 = Kf2Logger::new_session(args.server, kf2db.clone())
+        .await
+        .unwrap();
+    if let Some(ping_kick_args) = args.ping_kick {
+        kf2.set_ping_kick(ping_kick_args);
+    }
+    if let Some(afk_args) = args.afk {
+        kf2.set_afk(afk_args);
+    }
+    if let Some(ban_sync_args) = args.ban_sync {
+        kf2.set_ban_sync(ban_sync_args).unwrap();
+    }
+    if let Some(announce_args) = args.announce {
+        kf2.set_announcements(announce_args).unwrap();
+    }
+    if let Some(welcome_args) = args.welcome {
+        kf2.set_welcome(welcome_args).unwrap();
+    }
+    if let Some(query_args) = args.query {
+        kf2.set_query(query_args);
+    }
+    if let Some(config_history_args) = args.config_history {
+        kf2.set_config_history(config_history_args);
+    }
+    let events = kf2.events();
+    events.spawn_sink(live.clone());
+    if let Some(webhook_args) = args.webhook {
+        events.spawn_sink(Webhooks::new(webhook_args).unwrap());
+    }
+    if let Some(event_log_args) = args.event_log {
+        events.spawn_thread_sink("event-log", EventLog::new(event_log_args).unwrap());
+    }
+    if let Some(api_args) = args.api {
+        let (db, live, events) = (kf2db.clone(), live.clone(), events.clone());
+        tokio::spawn(async move {
+            if let Err(err) = kf2_api::server::serve(api_args, db, live, events).await {
+                error!("API server stopped: {}", err);
+            }
+        });
+    }
+
+    let mut server_log = args.server_log.map(kf2_server_log::spawn);
     let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
+    let mut last_tick: Option<Instant> = None;
     '_log: loop {
-        interval.tick().await;
+        match &mut server_log {
+            Some(lines) => tokio::select! {
+                _ = interval.tick() => {}
+                Some(line) = lines.recv() => kf2.server_log_line(line),
+            },
+            None => {
+                interval.tick().await;
+            }
+        }
+        // Early ticks wait for the gap, lines arriving meanwhile included
+        if let Some(last_tick) = last_tick {
+            tokio::time::sleep_until(last_tick + MIN_TICK_GAP).await;
+        }
+        // Lines that arrived together are handled by one tick
+        if let Some(lines) = &mut server_log {
+            while let Ok(line) = lines.try_recv() {
+                kf2.server_log_line(line);
+            }
+        }
+        // After an early tick the next one waits a full interval
+        interval.reset();
+        last_tick = Some(Instant::now());
         let start = Instant::now();
         if let Err(err) = kf2.log_unique_players().await {
             error!("{}", err);
         }
         let duration = start.elapsed();
         info!("Log Unique Players Duration: {:?}", duration);
+        kf2.kick_high_ping().await;
         let start = Instant::now();
         if let Err(err) = kf2.loq_in_game_players().await {
             error!("{}", err);
@@ -39,10 +163,29 @@ async fn main() {
         let duration = start.elapsed();
         info!("Log Game Session Duration: {:?}", duration);
         let start = Instant::now();
+        if let Err(err) = kf2.log_wave_session().await {
+            error!("{}", err);
+        }
+        let duration = start.elapsed();
+        info!("Log Wave Session Duration: {:?}", duration);
+        let start = Instant::now();
         if let Err(err) = kf2.log_player_sessions().await {
             error!("{}", err);
         }
         let duration = start.elapsed();
         info!("Log Player Sessions Duration: {:?}", duration);
+        if let Err(err) = kf2.track_afk().await {
+            error!("{}", err);
+        }
+        if let Err(err) = kf2.sync_bans().await {
+            error!("{}", err);
+        }
+        kf2.announce().await;
+        if let Err(err) = kf2.snapshot_config().await {
+            error!("{}", err);
+        }
+        kf2.check_query().await;
+        live.update(kf2.current_game(), kf2.current_players());
+        kf2.publish_snapshot();
     }
 }
@@ -1,5 +1,43 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    afk_periods (id) {
+        id -> Unsigned<Integer>,
+        player_session_id -> Unsigned<Integer>,
+        started_at -> Timestamp,
+        ended_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    bans (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 255]
+        server -> Varchar,
+        #[max_length = 50]
+        unique_net_id -> Varchar,
+        steam_id -> Nullable<Unsigned<Bigint>>,
+        #[max_length = 50]
+        name -> Nullable<Varchar>,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    control_actions (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 32]
+        action -> Varchar,
+        #[max_length = 255]
+        target -> Nullable<Varchar>,
+        #[max_length = 32]
+        source -> Varchar,
+        #[max_length = 255]
+        error -> Nullable<Varchar>,
+        created -> Timestamp,
+    }
+}
+
 diesel::table! {
     current_players (name) {
         #[max_length = 50]
@@ -16,6 +54,8 @@ diesel::table! {
 diesel::table! {
     game_sessions (id) {
         id -> Unsigned<Integer>,
+        #[max_length = 255]
+        server -> Nullable<Varchar>,
         max_waves -> Unsigned<Smallint>,
         reached_wave -> Unsigned<Smallint>,
         max_players -> Unsigned<Smallint>,
@@ -27,7 +67,13 @@ diesel::table! {
         #[max_length = 50]
         game_type -> Varchar,
         #[max_length = 50]
+        game_mode -> Varchar,
+        #[max_length = 50]
         boss -> Varchar,
+        #[max_length = 50]
+        weekly_outbreak -> Nullable<Varchar>,
+        objectives -> Nullable<Unsigned<Smallint>>,
+        versus_round -> Nullable<Unsigned<Smallint>>,
         started_at -> Timestamp,
         ended_at -> Nullable<Timestamp>,
     }
@@ -42,6 +88,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    ip_bans (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 255]
+        server -> Varchar,
+        #[max_length = 45]
+        ip_address -> Varchar,
+        created -> Timestamp,
+    }
+}
+
 diesel::table! {
     player_sessions (id) {
         id -> Unsigned<Integer>,
@@ -50,11 +107,36 @@ diesel::table! {
         #[max_length = 50]
         perk -> Varchar,
         kills -> Unsigned<Integer>,
+        #[max_length = 50]
+        team -> Nullable<Varchar>,
         started_at -> Timestamp,
         ended_at -> Timestamp,
     }
 }
 
+diesel::table! {
+    server_config_versions (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 255]
+        server -> Varchar,
+        settings -> Text,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    server_settings (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 255]
+        server -> Varchar,
+        #[max_length = 50]
+        name -> Varchar,
+        #[max_length = 255]
+        value -> Varchar,
+        updated -> Timestamp,
+    }
+}
+
 diesel::table! {
     unique_players (steam_id) {
         steam_id -> Unsigned<Bigint>,
@@ -69,14 +151,41 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    wave_sessions (id) {
+        id -> Unsigned<Integer>,
+        game_session_id -> Unsigned<Integer>,
+        wave -> Unsigned<Smallint>,
+        ai_remaining -> Unsigned<Integer>,
+        longest_stall -> Unsigned<Integer>,
+        started_at -> Timestamp,
+        trader_opened_at -> Nullable<Timestamp>,
+        ended_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::joinable!(afk_periods -> player_sessions (player_session_id));
 diesel::joinable!(ip_addresses -> unique_players (steam_id));
 diesel::joinable!(player_sessions -> game_sessions (game_session_id));
 diesel::joinable!(player_sessions -> unique_players (steam_id));
+diesel::joinable!(wave_sessions -> game_sessions (game_session_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    afk_periods,
+    bans,
+    control_actions,
     current_players,
     game_sessions,
     ip_addresses,
+    ip_bans,
     player_sessions,
+    server_config_versions,
+    server_settings,
     unique_players,
+    wave_sessions,
+);
+
+diesel::allow_columns_to_appear_in_same_group_by_clause!(
+    player_sessions::steam_id,
+    unique_players::name,
 );